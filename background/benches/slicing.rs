@@ -0,0 +1,83 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use geng::prelude::*;
+
+#[path = "../src/geometry.rs"]
+#[allow(dead_code)]
+mod geometry;
+
+use geometry::Plane;
+
+fn plane() -> Plane {
+    Plane {
+        normal: vec3(0.2, 0.1, 1.0),
+        offset: 0.1,
+    }
+}
+
+fn bench_intersect(c: &mut Criterion) {
+    let plane = plane();
+    let mut group = c.benchmark_group("intersect");
+    let mut points = Vec::new();
+
+    let cube = geometry::CUBE.to_vec();
+    group.bench_function("cube", |b| {
+        b.iter(|| {
+            points.clear();
+            plane.intersect_into(black_box(&cube), &mut points);
+        })
+    });
+
+    for subdivisions in [1, 2, 3, 4] {
+        let sphere = geometry::sphere(subdivisions);
+        group.bench_with_input(
+            BenchmarkId::new("sphere", subdivisions),
+            &sphere,
+            |b, sphere| {
+                b.iter(|| {
+                    points.clear();
+                    plane.intersect_into(black_box(sphere), &mut points);
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_frame(c: &mut Criterion) {
+    let plane = plane();
+    let geometry = geometry::CUBE.to_vec();
+    let mut group = c.benchmark_group("frame");
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut scratch = Vec::new();
+    let mut sections: Vec<Vec<vec2<f32>>> = Vec::new();
+
+    for objects in [10, 100, 1000] {
+        let matrices: Vec<mat4<f32>> = (0..objects)
+            .map(|_| {
+                mat4::translate(vec3(
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-10.0..10.0),
+                    rng.gen_range(-0.5..0.5),
+                )) * mat4::rotate_x(Angle::from_degrees(rng.gen_range(0.0..360.0)))
+                    * mat4::rotate_y(Angle::from_degrees(rng.gen_range(0.0..360.0)))
+                    * mat4::scale_uniform(rng.gen_range(0.3..1.0))
+            })
+            .collect();
+        sections.resize_with(objects, Vec::new);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(objects),
+            &matrices,
+            |b, matrices| {
+                b.iter(|| {
+                    for (&matrix, points) in matrices.iter().zip(&mut sections) {
+                        geometry::slice_object(&plane, matrix, &geometry, &mut scratch, points);
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_intersect, bench_frame);
+criterion_main!(benches);
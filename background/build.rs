@@ -0,0 +1,81 @@
+//! Bakes the geometry of the common prefabs, so that it is not generated on startup.
+
+use std::fmt::Write;
+
+type Vertex = [f32; 3];
+type Triangle = [Vertex; 3];
+
+/// Spheres up to this many subdivisions are baked, the finer ones are generated at runtime.
+const BAKED_SUBDIVISIONS: u32 = 3;
+
+/// Same as `geometry::OCTAHEDRON`.
+const OCTAHEDRON: [Triangle; 8] = [
+    [[-1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+    [[-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]],
+    [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]],
+    [[-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+    [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+];
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let mut source = String::new();
+    let mut triangles = OCTAHEDRON.to_vec();
+    let mut names = vec!["OCTAHEDRON".to_owned()];
+    for subdivisions in 1..=BAKED_SUBDIVISIONS {
+        triangles = subdivide(&triangles);
+        for triangle in &mut triangles {
+            for v in triangle {
+                *v = normalize(*v);
+            }
+        }
+        let name = format!("SPHERE_{subdivisions}");
+        writeln!(source, "const {name}: [Triangle; {}] = [", triangles.len()).unwrap();
+        for [a, b, c] in &triangles {
+            writeln!(source, "    tri({a:?}, {b:?}, {c:?}),").unwrap();
+        }
+        writeln!(source, "];").unwrap();
+        names.push(name);
+    }
+    writeln!(
+        source,
+        "/// Octahedron subdivided up to {BAKED_SUBDIVISIONS} times, as generated by [sphere]."
+    )
+    .unwrap();
+    writeln!(
+        source,
+        "pub const SPHERES: [&[Triangle]; {}] = [{}];",
+        names.len(),
+        names
+            .iter()
+            .map(|name| format!("&{name}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+    .unwrap();
+
+    let path = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("spheres.rs");
+    std::fs::write(path, source).unwrap();
+}
+
+/// Same as `geometry::subdivide`.
+fn subdivide(triangles: &[Triangle]) -> Vec<Triangle> {
+    let mid = |a: Vertex, b: Vertex| [0, 1, 2].map(|i| (a[i] + b[i]) / 2.0);
+    triangles
+        .iter()
+        .flat_map(|&[a, b, c]| {
+            let [ab, bc, ca] = [mid(a, b), mid(b, c), mid(c, a)];
+            [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        })
+        .collect()
+}
+
+/// Same as `vec3::normalize`.
+fn normalize(v: Vertex) -> Vertex {
+    let len = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    v.map(|x| x / len)
+}
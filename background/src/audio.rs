@@ -0,0 +1,212 @@
+use crate::config::AudioSource;
+
+/// Loudness of the frequency bands, from 0 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AudioLevels {
+    /// Up to 250 Hz.
+    pub bass: f32,
+    /// From 250 Hz to 2 kHz.
+    pub mid: f32,
+    /// From 2 kHz to 8 kHz.
+    pub treble: f32,
+}
+
+impl AudioLevels {
+    /// Upper edges of the bands in Hz.
+    const EDGES: [f32; 3] = [250.0, 2000.0, 8000.0];
+
+    /// Average the spectrum of bins from 0 to 255, spread evenly up to `max_frequency`.
+    pub fn from_spectrum(spectrum: &[u8], max_frequency: f32) -> Self {
+        let mut sums = [0.0; 3];
+        let mut counts = [0; 3];
+        for (i, &value) in spectrum.iter().enumerate() {
+            let frequency = (i as f32 + 0.5) / spectrum.len() as f32 * max_frequency;
+            let Some(band) = Self::EDGES.iter().position(|&edge| frequency < edge) else {
+                break;
+            };
+            sums[band] += value as f32 / 255.0;
+            counts[band] += 1;
+        }
+        let [bass, mid, treble] = [0, 1, 2].map(|band| sums[band] / (counts[band] as f32).max(1.0));
+        Self { bass, mid, treble }
+    }
+
+    /// Move towards the target levels, mostly reaching them in `smoothing` seconds.
+    pub fn smooth_towards(&mut self, target: Self, smoothing: f32, delta_time: f32) {
+        let t = if smoothing > 0.0 {
+            1.0 - (-delta_time * 3.0 / smoothing).exp()
+        } else {
+            1.0
+        };
+        self.bass += (target.bass - self.bass) * t;
+        self.mid += (target.mid - self.mid) * t;
+        self.treble += (target.treble - self.treble) * t;
+    }
+}
+
+/// Listens to an audio source and splits it into frequency bands.
+/// Only supported on the web with the Web Audio API, elsewhere everything is silent.
+pub struct AudioAnalyzer {
+    source: AudioSource,
+    #[cfg(target_arch = "wasm32")]
+    web: Option<WebAudio>,
+    #[cfg(target_arch = "wasm32")]
+    spectrum: Vec<u8>,
+}
+
+#[cfg(target_arch = "wasm32")]
+struct WebAudio {
+    context: web_sys::AudioContext,
+    analyser: web_sys::AnalyserNode,
+    /// Kept to start the playback after a user gesture and to stop it when dropped.
+    track: Option<web_sys::HtmlAudioElement>,
+}
+
+impl AudioAnalyzer {
+    pub fn source(&self) -> &AudioSource {
+        &self.source
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl AudioAnalyzer {
+    const FFT_SIZE: u32 = 1024;
+
+    pub fn new(source: &AudioSource) -> Self {
+        let web = WebAudio::new(source)
+            .map_err(|err| log::warn!("Failed to start the audio: {err:?}"))
+            .ok();
+        Self {
+            source: source.clone(),
+            spectrum: vec![0; Self::FFT_SIZE as usize / 2],
+            web,
+        }
+    }
+
+    /// Browsers only allow the audio to start after a user gesture, so this is called on input.
+    pub fn resume(&self) {
+        if let Some(web) = &self.web {
+            if web.context.state() == web_sys::AudioContextState::Suspended {
+                let _ = web.context.resume();
+            }
+            if let Some(track) = web.track.as_ref().filter(|track| track.paused()) {
+                let _ = track.play();
+            }
+        }
+    }
+
+    /// Levels of the sound at the moment.
+    pub fn levels(&mut self) -> AudioLevels {
+        let Some(web) = &self.web else {
+            return AudioLevels::default();
+        };
+        web.analyser.get_byte_frequency_data(&mut self.spectrum);
+        AudioLevels::from_spectrum(&self.spectrum, web.context.sample_rate() / 2.0)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebAudio {
+    fn new(source: &AudioSource) -> Result<Self, wasm_bindgen::JsValue> {
+        use wasm_bindgen::{prelude::*, JsCast};
+
+        let context = web_sys::AudioContext::new()?;
+        let analyser = context.create_analyser()?;
+        analyser.set_fft_size(AudioAnalyzer::FFT_SIZE);
+        // The smoothing is done on the levels instead
+        analyser.set_smoothing_time_constant(0.0);
+
+        let mut track = None;
+        match source {
+            AudioSource::Track(url) => {
+                let audio = web_sys::HtmlAudioElement::new_with_src(url)?;
+                audio.set_cross_origin(Some("anonymous"));
+                audio.set_loop(true);
+                let node = context.create_media_element_source(&audio)?;
+                node.connect_with_audio_node(&analyser)?;
+                // Only the track is played back, not the microphone
+                analyser.connect_with_audio_node(&context.destination())?;
+                // Rejected until the first user gesture, [AudioAnalyzer::resume] retries then
+                let _ = audio.play();
+                track = Some(audio);
+            }
+            AudioSource::Microphone => {
+                let constraints = web_sys::MediaStreamConstraints::new();
+                constraints.set_audio(&JsValue::TRUE);
+                let request = web_sys::window()
+                    .ok_or_else(|| JsValue::from_str("no window"))?
+                    .navigator()
+                    .media_devices()?
+                    .get_user_media_with_constraints(&constraints)?;
+                let connect = {
+                    let context = context.clone();
+                    let analyser = analyser.clone();
+                    Closure::<dyn FnMut(JsValue)>::new(move |stream: JsValue| {
+                        let Ok(stream) = stream.dyn_into::<web_sys::MediaStream>() else {
+                            return;
+                        };
+                        let connected = context
+                            .create_media_stream_source(&stream)
+                            .and_then(|node| node.connect_with_audio_node(&analyser));
+                        if let Err(err) = connected {
+                            log::warn!("Failed to connect the microphone: {err:?}");
+                        }
+                    })
+                };
+                let denied = Closure::<dyn FnMut(JsValue)>::new(|err: JsValue| {
+                    log::info!("Microphone is not available: {err:?}");
+                });
+                let _ = request.then2(&connect, &denied);
+                connect.forget();
+                denied.forget();
+            }
+        }
+        Ok(Self {
+            context,
+            analyser,
+            track,
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Drop for WebAudio {
+    fn drop(&mut self) {
+        if let Some(track) = &self.track {
+            let _ = track.pause();
+        }
+        let _ = self.context.close();
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AudioAnalyzer {
+    pub fn new(source: &AudioSource) -> Self {
+        log::info!("Audio is only supported on the web");
+        Self {
+            source: source.clone(),
+        }
+    }
+
+    pub fn resume(&self) {}
+
+    pub fn levels(&mut self) -> AudioLevels {
+        AudioLevels::default()
+    }
+}
+
+#[test]
+fn test_audio_levels() {
+    // 8 bins of 125 Hz each
+    let spectrum = [255, 255, 0, 0, 0, 0, 0, 0];
+    let levels = AudioLevels::from_spectrum(&spectrum, 1000.0);
+    assert_eq!(levels.bass, 1.0);
+    assert_eq!(levels.mid, 0.0);
+    assert_eq!(levels.treble, 0.0);
+
+    let mut smoothed = AudioLevels::default();
+    smoothed.smooth_towards(levels, 0.5, 0.5);
+    assert!(smoothed.bass > 0.9 && smoothed.bass < 1.0);
+    smoothed.smooth_towards(levels, 0.0, 0.01);
+    assert_eq!(smoothed, levels);
+}
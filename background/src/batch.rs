@@ -0,0 +1,124 @@
+use geng::prelude::*;
+
+/// Accumulates the triangles of all the sections in a frame to draw them in a single call.
+#[derive(Default)]
+pub struct SectionBatch {
+    vertices: Vec<draw2d::ColoredVertex>,
+    /// Temporaries of the current frame, freed all at once on [Self::clear].
+    arena: bumpalo::Bump,
+}
+
+impl SectionBatch {
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.arena.reset();
+    }
+
+    /// Fill a convex polygon.
+    pub fn add_fill(&mut self, points: &[vec2<f32>], color: Rgba<f32>) {
+        let Some((&first, rest)) = points.split_first() else {
+            return;
+        };
+        for pair in rest.windows(2) {
+            triangle(&mut self.vertices, [first, pair[0], pair[1]], color);
+        }
+    }
+
+    /// A straight line with a stroke centered on it.
+    pub fn add_line(&mut self, a: vec2<f32>, b: vec2<f32>, width: f32, color: Rgba<f32>) {
+        let offset = (b - a).normalize_or_zero().rotate_90() * (width / 2.0);
+        triangle(
+            &mut self.vertices,
+            [a - offset, a + offset, b + offset],
+            color,
+        );
+        triangle(
+            &mut self.vertices,
+            [a - offset, b + offset, b - offset],
+            color,
+        );
+    }
+
+    /// Outline a closed loop with a stroke centered on it.
+    pub fn add_outline(&mut self, points: &[vec2<f32>], width: f32, color: Rgba<f32>) {
+        let n = points.len();
+        if n < 3 {
+            return;
+        }
+        let half_width = width / 2.0;
+
+        // Offset of the stroke edges at every vertex, with mitered joints
+        let mut offsets = bumpalo::collections::Vec::with_capacity_in(n, &self.arena);
+        offsets.extend((0..n).map(|i| {
+            let prev = points[(i + n - 1) % n];
+            let point = points[i];
+            let next = points[(i + 1) % n];
+            let normal_in = (point - prev).normalize_or_zero().rotate_90();
+            let normal_out = (next - point).normalize_or_zero().rotate_90();
+            let miter = (normal_in + normal_out).normalize_or_zero();
+            let cos = vec2::dot(miter, normal_out);
+            // Limit the spikes on sharp corners
+            let length = half_width / cos.max(0.25);
+            miter * length
+        }));
+
+        for i in 0..n {
+            let j = (i + 1) % n;
+            let (a, b) = (points[i], points[j]);
+            let (da, db) = (offsets[i], offsets[j]);
+            triangle(&mut self.vertices, [a - da, a + da, b + db], color);
+            triangle(&mut self.vertices, [a - da, b + db, b - db], color);
+        }
+    }
+
+    pub fn draw(
+        &mut self,
+        geng: &Geng,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl geng::AbstractCamera2d,
+    ) {
+        if self.vertices.is_empty() {
+            return;
+        }
+        let polygon = draw2d::Polygon {
+            transform: mat3::identity(),
+            draw_mode: ugli::DrawMode::Triangles,
+            // Reuse the allocation on the next frame
+            vertices: std::mem::take(&mut self.vertices),
+        };
+        geng.draw2d().draw2d(framebuffer, camera, &polygon);
+        self.vertices = polygon.vertices;
+    }
+}
+
+fn triangle(
+    vertices: &mut Vec<draw2d::ColoredVertex>,
+    [a, b, c]: [vec2<f32>; 3],
+    color: Rgba<f32>,
+) {
+    vertices.extend([a, b, c].map(|a_pos| draw2d::ColoredVertex {
+        a_pos,
+        a_color: color,
+    }));
+}
+
+#[test]
+fn test_section_batch() {
+    let square = [
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+        vec2(1.0, 1.0),
+        vec2(-1.0, 1.0),
+    ];
+    let mut batch = SectionBatch::default();
+    batch.add_fill(&square, Rgba::WHITE);
+    assert_eq!(batch.vertices.len(), 2 * 3);
+
+    batch.clear();
+    batch.add_outline(&square, 0.2, Rgba::WHITE);
+    assert_eq!(batch.vertices.len(), 4 * 2 * 3);
+    // Mitered corners stay on the diagonals
+    let corner = batch.vertices[0].a_pos;
+    assert!((corner.x - corner.y).abs() < 1e-5);
+    assert!((corner.len() - 1.1 * 2.0f32.sqrt()).abs() < 1e-5);
+}
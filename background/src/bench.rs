@@ -0,0 +1,47 @@
+use crate::{Config, ConfigReloader, State};
+
+use geng::prelude::*;
+
+/// Run the simulation without a window and print the timings of the steps and the slicing.
+pub fn run(mut config: Config, reloader: ConfigReloader, frames: usize) {
+    // The same objects on every run, so that the timings are comparable
+    config.seed.get_or_insert(0);
+    let delta_time = 1.0 / config.simulation_rate.unwrap_or(60.0);
+    let mut state = State::headless(config, reloader, vec2(1920, 1080));
+
+    let mut steps = Vec::with_capacity(frames);
+    let mut slicing = Vec::with_capacity(frames);
+    let mut objects = 0;
+    let timer = Timer::new();
+    for _ in 0..frames {
+        let (step, slice) = state.bench_frame(delta_time);
+        steps.push(step);
+        slicing.push(slice);
+        objects += state.object_count();
+    }
+    let total = timer.elapsed().as_secs_f64();
+
+    println!(
+        "{} frames in {:.2} s, {:.1} objects on average",
+        frames,
+        total,
+        objects as f32 / frames.max(1) as f32
+    );
+    print_stats("step", &mut steps);
+    print_stats("slicing", &mut slicing);
+}
+
+fn print_stats(name: &str, times: &mut [f32]) {
+    if times.is_empty() {
+        return;
+    }
+    times.sort_by(f32::total_cmp);
+    let percentile = |p: f32| times[((times.len() - 1) as f32 * p).round() as usize];
+    let mean = times.iter().sum::<f32>() / times.len() as f32;
+    println!(
+        "{name}: mean {mean:.3} ms, median {:.3} ms, p95 {:.3} ms, max {:.3} ms",
+        percentile(0.5),
+        percentile(0.95),
+        times[times.len() - 1]
+    );
+}
@@ -0,0 +1,3196 @@
+use crate::{
+    geometry::Plane,
+    noise,
+    palette::{Palette, PaletteColor},
+    prefab::Shape,
+};
+
+use geng::prelude::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Name of the theme to use on startup.
+    pub theme: String,
+    pub themes: HashMap<String, Theme>,
+    /// Force a color scheme instead of following the browser preference.
+    pub color_scheme: Option<ColorScheme>,
+    /// Take the background and object colors from the CSS custom properties of the page
+    /// (`--bg-color` and `--accent-1`, `--accent-2`, ...), if it defines them.
+    pub css_colors: bool,
+    /// Origins of the pages allowed to control the scene with `postMessage`,
+    /// e.g. `"https://nertsal.github.io"`, besides the own origin of the page.
+    /// `"*"` allows any page.
+    pub allowed_origins: Vec<String>,
+    /// Seed for the random number generator, making the scene reproducible.
+    /// If not set, the scene is different every time.
+    pub seed: Option<u64>,
+    /// Limit the frame rate to save power.
+    pub target_fps: Option<f32>,
+    /// Render at most this many physical pixels per logical pixel on high-DPI displays,
+    /// upscaling the result, to limit the fill rate. If not set, render at the full resolution.
+    pub max_pixel_ratio: Option<f32>,
+    /// Objects smaller than this many pixels across are sliced with a simplified mesh,
+    /// and an even coarser one below half the size. If not set, always use the full detail.
+    pub lod_size: Option<f32>,
+    /// Slice the objects on a background thread while the previous frame is drawn,
+    /// at the cost of the sections lagging one frame behind. Native only.
+    pub slicing_thread: bool,
+    /// Experimental: slice the objects on the GPU, if compiled with the `gpu-slicing` feature. Only the outlines are drawn,
+    /// and the sections cannot be hovered or clicked.
+    pub gpu_slicing: bool,
+    /// Notify the page when the frame rate drops below this value.
+    pub low_fps: Option<f32>,
+    /// When to switch to the low power profile and what it changes.
+    pub low_power: LowPower,
+    /// Lowering the quality step by step while the frames take too long.
+    pub adaptive_quality: AdaptiveQuality,
+    /// Stop the simulation while the page is hidden or the window is unfocused.
+    pub pause_when_hidden: bool,
+    /// Force the reduced motion profile on or off instead of following the browser preference.
+    pub reduce_motion: Option<bool>,
+    /// Motion profile used when reduced motion is requested.
+    pub reduced_motion: ReducedMotion,
+    /// Multiplier for the simulation speed.
+    pub time_scale: f32,
+    /// Longer frames, e.g. after the tab was asleep, are simulated as this many seconds.
+    pub max_frame_time: f32,
+    /// Objects spawned at most in a single simulation step when catching up.
+    pub max_spawns_per_step: usize,
+    /// Advance the simulation in fixed steps this many times per second,
+    /// interpolating the objects in between. If not set, step once per frame.
+    pub simulation_rate: Option<f32>,
+    /// Slowing down while the visitor is not interacting with the page.
+    pub idle: Idle,
+    /// Objects spawned per second.
+    pub spawn_rate: f32,
+    /// Speed at which objects travel through the plane.
+    pub speed: f32,
+    /// Random spread of the speed of each object, as a fraction of `speed`.
+    pub speed_variation: f32,
+    /// Largest sideways speed of an object along each axis, as a fraction of `speed`.
+    pub lateral_drift: f32,
+    /// Push apart the objects whose bounding spheres overlap.
+    pub collisions: bool,
+    /// How the objects move besides their own velocity.
+    pub motion: Motion,
+    /// Drift shared by all the objects.
+    pub wind: Wind,
+    /// Small random displacement of the objects around their paths.
+    pub wobble: Wobble,
+    /// Breathing of the object sizes.
+    pub pulsation: Pulsation,
+    /// Growing the new objects and shrinking them away before they are removed.
+    pub lifetime: Lifetime,
+    /// Breaking the objects into shards as they leave the plane.
+    pub fracture: Fracture,
+    /// Links between the objects close to each other.
+    pub constellation: Constellation,
+    /// Purely visual lines between the nearby sections.
+    pub section_lines: SectionLines,
+    /// Briefly slowing down the time when a large object reaches the plane.
+    pub hit_stop: HitStop,
+    /// Chance that a spawned object comes with one or two others, orbiting their common center.
+    pub cluster_chance: f32,
+    /// Chance that a spawned object comes from in front of the plane, travelling backwards,
+    /// so that its section shrinks first and then grows.
+    pub reverse_chance: f32,
+    /// Objects are not spawned while there are this many on the screen.
+    pub max_objects: usize,
+    /// View area (in world units) for which `spawn_rate` and `max_objects` are specified.
+    /// Both are scaled proportionally to the actual view area, keeping the density constant.
+    /// If not set, they are used as is regardless of the view size.
+    pub reference_area: Option<f32>,
+    /// Distance beyond the edges of the view where objects can still spawn,
+    /// so that they don't pop in exactly at the screen edge.
+    pub spawn_margin: f32,
+    /// Shapes of the objects that can be spawned, by prefab name.
+    /// The geometry of each prefab is generated when it is first spawned.
+    pub prefabs: BTreeMap<String, Shape>,
+    /// Relative spawn probability of each prefab by name.
+    /// Prefabs not listed are never spawned. If empty, all prefabs are equally likely.
+    pub prefab_weights: HashMap<String, f32>,
+    /// Objects turning from one prefab into another over their flight.
+    pub morphs: Vec<Morph>,
+    /// Depth layers of the objects, from the farthest to the nearest, drawn in this order.
+    /// If empty, all objects are in a single layer.
+    pub layers: Vec<Layer>,
+    /// The plane that slices the objects.
+    pub cross_plane: Plane,
+    /// Sweep the plane back and forth through the objects hanging almost still.
+    /// `None` keeps the plane in place with the objects travelling through it.
+    pub sweep: Option<Sweep>,
+    /// How the objects react to the cursor.
+    pub cursor_field: CursorField,
+    /// Invisible points that pull the passing objects, curving their paths.
+    pub attractors: Vec<Attractor>,
+    /// Spawn an object where the user clicks.
+    pub click_spawn: bool,
+    /// Clicking on a section pops its object.
+    pub click_pop: bool,
+    /// Special objects that open a link when clicked.
+    pub links: Links,
+    /// Rings emitted by the cursor on clicks and fast movement.
+    pub cursor_ripple: CursorRipple,
+    /// Dragging the pointer tilts the plane.
+    pub drag_tilt: DragTilt,
+    /// Shifting the view as the page scrolls.
+    pub parallax: Parallax,
+    /// Shifting the view as the device tilts, on devices that report their orientation.
+    pub gyroscope: Gyroscope,
+    /// Enable the debug keyboard shortcuts, if compiled with the `debug` feature.
+    pub debug: bool,
+    /// Hidden scene triggered by the Konami code.
+    pub easter_egg: EasterEgg,
+    /// Scripted events played back at fixed times.
+    pub timeline: Timeline,
+    /// Seasonal presets applied when the local date falls within their range.
+    pub presets: BTreeMap<String, Preset>,
+    /// Scene parameters for the sections of the page, by the id reported by the page.
+    pub page_sections: BTreeMap<String, PageSection>,
+    /// Time in seconds it takes to mostly blend into the parameters of a new page section.
+    pub page_transition: f32,
+    /// Scene parameters following the local time of the visitor.
+    pub day_cycle: DayCycle,
+    /// Reacting to music or the microphone.
+    pub audio: AudioReactive,
+    /// Pulses in time with a track of a known tempo.
+    pub beat: Beat,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            theme: "default".to_owned(),
+            themes: HashMap::from_iter([("default".to_owned(), Theme::default())]),
+            color_scheme: None,
+            css_colors: true,
+            allowed_origins: Vec::new(),
+            seed: None,
+            target_fps: Some(60.0),
+            max_pixel_ratio: None,
+            lod_size: Some(48.0),
+            slicing_thread: false,
+            gpu_slicing: false,
+            low_fps: Some(30.0),
+            low_power: LowPower::default(),
+            adaptive_quality: AdaptiveQuality::default(),
+            pause_when_hidden: true,
+            reduce_motion: None,
+            reduced_motion: ReducedMotion::default(),
+            time_scale: 1.0,
+            max_frame_time: 0.25,
+            max_spawns_per_step: 5,
+            simulation_rate: Some(60.0),
+            idle: Idle::default(),
+            spawn_rate: 10.0,
+            speed: 0.5,
+            speed_variation: 0.2,
+            lateral_drift: 0.1,
+            collisions: true,
+            motion: Motion::Drift,
+            wind: Wind::default(),
+            wobble: Wobble::default(),
+            pulsation: Pulsation::default(),
+            lifetime: Lifetime::default(),
+            fracture: Fracture::default(),
+            constellation: Constellation::default(),
+            section_lines: SectionLines::default(),
+            hit_stop: HitStop::default(),
+            cluster_chance: 0.05,
+            reverse_chance: 0.0,
+            max_objects: 100,
+            reference_area: Some(700.0),
+            spawn_margin: 1.0,
+            prefabs: BTreeMap::from_iter([
+                ("cube".to_owned(), Shape::Cube),
+                ("octahedron".to_owned(), Shape::Octahedron),
+            ]),
+            prefab_weights: HashMap::new(),
+            cross_plane: Plane {
+                normal: vec3::UNIT_Z,
+                offset: 0.0,
+            },
+            sweep: None,
+            cursor_field: CursorField::default(),
+            attractors: Vec::new(),
+            morphs: Vec::new(),
+            layers: Vec::new(),
+            click_spawn: true,
+            click_pop: true,
+            links: Links::default(),
+            cursor_ripple: CursorRipple::default(),
+            drag_tilt: DragTilt::default(),
+            parallax: Parallax::default(),
+            gyroscope: Gyroscope::default(),
+            debug: false,
+            easter_egg: EasterEgg::default(),
+            timeline: Timeline::default(),
+            presets: BTreeMap::new(),
+            page_sections: BTreeMap::new(),
+            page_transition: 1.0,
+            day_cycle: DayCycle::default(),
+            audio: AudioReactive::default(),
+            beat: Beat::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Layer of the objects by index, the default one if there are no layers.
+    pub fn layer(&self, index: usize) -> Layer {
+        self.layers.get(index).copied().unwrap_or_default()
+    }
+
+    /// The default config with every optional field populated with an example value,
+    /// useful to discover the available options.
+    pub fn example() -> Self {
+        let mut config = Self::default();
+        config.color_scheme = Some(ColorScheme::Dark);
+        config.seed = Some(0);
+        config.allowed_origins = vec!["https://nertsal.github.io".to_owned()];
+        config.max_pixel_ratio = Some(2.0);
+        config.reduce_motion = Some(false);
+        config.adaptive_quality.budget = Some(20.0);
+        config.sweep = Some(Sweep::default());
+        config.audio.source = Some(AudioSource::Track("assets/music.mp3".to_owned()));
+        config.beat.bpm = Some(120.0);
+        config.links.targets = vec![Link {
+            url: "/projects".to_owned(),
+            color: Some(rgb(0xffd700)),
+        }];
+        config.page_sections = BTreeMap::from_iter([(
+            "projects".to_owned(),
+            PageSection {
+                palette: Some(Palette::from_colors([rgb(0x565a75), rgb(0xc6b7be)])),
+                density: 0.5,
+                speed: 0.8,
+            },
+        )]);
+        config.prefab_weights =
+            HashMap::from_iter([("cube".to_owned(), 5.0), ("octahedron".to_owned(), 1.0)]);
+        for theme in config.themes.values_mut() {
+            let accent = PaletteColor::Weighted {
+                color: rgb(0xfafbf6),
+                weight: 0.1,
+            };
+            theme.prefab_colors =
+                HashMap::from_iter([("octahedron".to_owned(), Palette::Colors(vec![accent]))]);
+            theme.stroke.fill_below = Some(0.05);
+            theme.light = Some(Box::new(Theme::default()));
+        }
+        config.timeline = Timeline {
+            events: vec![
+                TimelineEvent {
+                    time: 10.0,
+                    action: TimelineAction::Burst(10),
+                },
+                TimelineEvent {
+                    time: 20.0,
+                    action: TimelineAction::Tilt(vec2(15.0, 0.0)),
+                },
+            ],
+            repeat: Some(30.0),
+        };
+        config.morphs = vec![Morph {
+            from: "cube".to_owned(),
+            to: "octahedron".to_owned(),
+            chance: 0.2,
+            min_duration: 4.0,
+            max_duration: 8.0,
+            easing: Easing::Smoothstep,
+        }];
+        config.layers = vec![
+            Layer {
+                weight: 1.0,
+                speed: 0.6,
+                scale: 0.6,
+                saturation: 0.5,
+                parallax: 0.5,
+            },
+            Layer::default(),
+        ];
+        config.attractors = vec![Attractor {
+            position: vec2(4.0, -2.0),
+            strength: 0.5,
+            falloff: 3.0,
+        }];
+        config.presets = BTreeMap::from_iter([(
+            "new_year".to_owned(),
+            Preset {
+                start: (12, 30),
+                end: (1, 2),
+                overrides: ConfigOverrides {
+                    preset: None,
+                    theme: Some(config.theme.clone()),
+                    seed: Some(0),
+                    time_scale: Some(config.time_scale),
+                    spawn_rate: Some(config.spawn_rate),
+                    speed: Some(config.speed),
+                    fps_limit: None,
+                    palette: Some(vec![rgb(0xffd700), rgb(0xfafbf6)]),
+                },
+            },
+        )]);
+        config
+    }
+
+    /// The default config embedded into the binary,
+    /// used until the external one is loaded so that the canvas is never dead.
+    pub fn embedded() -> Self {
+        let mut config: Self = ConfigFormat::Ron
+            .parse(include_str!("../assets/config.ron"))
+            .unwrap_or_else(|err| {
+                log::error!("Invalid embedded config: {:?}", err);
+                Self::default()
+            });
+        // Palettes extracted from images need the external assets,
+        // if they are not available immediately, the default palette is used instead
+        let _ = config
+            .resolve_palettes(std::path::Path::new("assets"))
+            .now_or_never();
+        config.validate();
+        config
+    }
+
+    /// Load and validate the config.
+    /// The format is picked based on the file extension: RON, JSON, or TOML.
+    /// Parsing errors report the path to the offending field.
+    /// If a local override file exists next to the config (see [`local_path`]),
+    /// it is layered on top.
+    pub async fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path)?;
+        let source = file::load_string(path).await?;
+        let mut config: Self = format
+            .parse(&source)
+            .map_err(|err| err.context(format!("invalid config {:?}", path)))?;
+
+        if let Some(local) = local_path(path) {
+            match ConfigPatch::load(&local).await {
+                Ok(patch) => {
+                    log::info!("Applying local config overrides from {:?}", local);
+                    patch.apply(&mut config);
+                }
+                Err(err) => log::error!("Ignoring local config overrides: {:?}", err),
+            }
+        }
+
+        config
+            .resolve_palettes(path.parent().unwrap_or(std::path::Path::new(".")))
+            .await;
+        config.validate();
+        Ok(config)
+    }
+
+    /// Generate or extract the palettes that are not explicit lists of colors.
+    async fn resolve_palettes(&mut self, base_path: &std::path::Path) {
+        for theme in self.themes.values_mut() {
+            theme.resolve_palettes(base_path).await;
+            if let Some(light) = &mut theme.light {
+                light.resolve_palettes(base_path).await;
+            }
+        }
+        self.easter_egg.palette.resolve(base_path).await;
+        for section in self.page_sections.values_mut() {
+            if let Some(palette) = &mut section.palette {
+                palette.resolve(base_path).await;
+            }
+        }
+    }
+
+    /// Replace invalid values with sane defaults, logging every correction.
+    pub fn validate(&mut self) {
+        if self.themes.is_empty() {
+            log::warn!("config: `themes` is empty, using the default theme");
+            self.themes = Self::default().themes;
+        }
+        if !self.themes.contains_key(&self.theme) {
+            let fallback = self.themes.keys().min().unwrap().clone();
+            log::warn!(
+                "config: `theme` {:?} does not exist, using {:?}",
+                self.theme,
+                fallback
+            );
+            self.theme = fallback;
+        }
+        for (name, theme) in &mut self.themes {
+            theme.validate(&format!("themes.{name}"));
+            if let Some(light) = &mut theme.light {
+                light.validate(&format!("themes.{name}.light"));
+            }
+        }
+
+        let default = Self::default();
+        if let Some(fps) = self.target_fps {
+            if !fps.is_finite() || fps <= 0.0 {
+                log::warn!("config: `target_fps` must be positive, got {}", fps);
+                self.target_fps = default.target_fps;
+            }
+        }
+        if !self.time_scale.is_finite() || self.time_scale < 0.0 {
+            log::warn!(
+                "config: `time_scale` must be non-negative, got {}",
+                self.time_scale
+            );
+            self.time_scale = default.time_scale;
+        }
+        if let Some(size) = self.lod_size {
+            if !size.is_finite() || size < 0.0 {
+                log::warn!("config: `lod_size` must be non-negative, got {}", size);
+                self.lod_size = default.lod_size;
+            }
+        }
+        if !self.max_frame_time.is_finite() || self.max_frame_time <= 0.0 {
+            log::warn!(
+                "config: `max_frame_time` must be positive, got {}",
+                self.max_frame_time
+            );
+            self.max_frame_time = default.max_frame_time;
+        }
+        if self.max_spawns_per_step == 0 {
+            log::warn!("config: `max_spawns_per_step` must be positive");
+            self.max_spawns_per_step = default.max_spawns_per_step;
+        }
+        if let Some(rate) = self.simulation_rate {
+            if !rate.is_finite() || rate <= 0.0 {
+                log::warn!("config: `simulation_rate` must be positive, got {}", rate);
+                self.simulation_rate = default.simulation_rate;
+            }
+        }
+        if !self.spawn_rate.is_finite() || self.spawn_rate < 0.0 {
+            log::warn!(
+                "config: `spawn_rate` must be non-negative, got {}",
+                self.spawn_rate
+            );
+            self.spawn_rate = default.spawn_rate;
+        }
+        if !self.speed.is_finite() || self.speed < 0.0 {
+            log::warn!("config: `speed` must be non-negative, got {}", self.speed);
+            self.speed = default.speed;
+        }
+        if !(0.0..=1.0).contains(&self.speed_variation) {
+            log::warn!(
+                "config: `speed_variation` must be in range [0, 1], got {}",
+                self.speed_variation
+            );
+            self.speed_variation = default.speed_variation;
+        }
+        if !(0.0..=1.0).contains(&self.cluster_chance) {
+            log::warn!(
+                "config: `cluster_chance` must be in range [0, 1], got {}",
+                self.cluster_chance
+            );
+            self.cluster_chance = default.cluster_chance;
+        }
+        if !(0.0..=1.0).contains(&self.reverse_chance) {
+            log::warn!(
+                "config: `reverse_chance` must be in range [0, 1], got {}",
+                self.reverse_chance
+            );
+            self.reverse_chance = default.reverse_chance;
+        }
+        if !self.lateral_drift.is_finite() || self.lateral_drift < 0.0 {
+            log::warn!(
+                "config: `lateral_drift` must be non-negative, got {}",
+                self.lateral_drift
+            );
+            self.lateral_drift = default.lateral_drift;
+        }
+
+        if let Some(area) = self.reference_area {
+            if !area.is_finite() || area <= 0.0 {
+                log::warn!("config: `reference_area` must be positive, got {}", area);
+                self.reference_area = default.reference_area;
+            }
+        }
+
+        if !self.spawn_margin.is_finite() || self.spawn_margin < 0.0 {
+            log::warn!(
+                "config: `spawn_margin` must be non-negative, got {}",
+                self.spawn_margin
+            );
+            self.spawn_margin = default.spawn_margin;
+        }
+        if let Some(ratio) = self.max_pixel_ratio {
+            if !ratio.is_finite() || ratio <= 0.0 {
+                log::warn!("config: `max_pixel_ratio` must be positive, got {}", ratio);
+                self.max_pixel_ratio = default.max_pixel_ratio;
+            }
+        }
+
+        self.prefabs
+            .retain(|name, shape| shape.is_valid(&format!("prefabs.{name}")));
+
+        for (name, weight) in &mut self.prefab_weights {
+            if !weight.is_finite() || *weight < 0.0 {
+                log::warn!("config: `prefab_weights.{name}` must be non-negative, got {weight}");
+                *weight = 0.0;
+            }
+        }
+
+        let mut index = 0;
+        let prefabs = &self.prefabs;
+        self.morphs.retain(|morph| {
+            let valid = morph.is_valid(&format!("morphs[{index}]"), prefabs);
+            index += 1;
+            valid
+        });
+
+        let mut index = 0;
+        self.layers.retain(|layer| {
+            let valid = layer.is_valid(&format!("layers[{index}]"));
+            index += 1;
+            valid
+        });
+
+        self.cursor_field.validate();
+        self.wind.validate();
+        self.wobble.validate();
+        self.pulsation.validate();
+        self.lifetime.validate();
+        self.fracture.validate();
+        self.constellation.validate();
+        self.section_lines.validate();
+        self.hit_stop.validate();
+        if let Motion::Flow(flow) = &mut self.motion {
+            flow.validate();
+        }
+        if let Some(sweep) = &mut self.sweep {
+            sweep.validate();
+        }
+        let mut index = 0;
+        self.attractors.retain(|attractor| {
+            let valid = attractor.is_valid(&format!("attractors[{index}]"));
+            index += 1;
+            valid
+        });
+        self.cursor_ripple.validate();
+        self.links.validate();
+        self.drag_tilt.validate();
+        self.reduced_motion.validate();
+        self.low_power.validate();
+        self.adaptive_quality.validate();
+        self.idle.validate();
+        self.easter_egg.validate();
+        self.timeline.validate();
+        self.day_cycle.validate();
+        self.audio.validate();
+        self.beat.validate();
+
+        for (name, section) in &mut self.page_sections {
+            section.validate(&format!("page_sections.{name}"));
+        }
+        if !self.page_transition.is_finite() || self.page_transition < 0.0 {
+            log::warn!(
+                "config: `page_transition` must be non-negative, got {}",
+                self.page_transition
+            );
+            self.page_transition = default.page_transition;
+        }
+        self.parallax.validate();
+        self.gyroscope.validate();
+
+        if self.cross_plane.normal.len_sqr() < 1e-5 || !self.cross_plane.offset.is_finite() {
+            log::warn!("config: `cross_plane` is degenerate, using the default plane");
+            self.cross_plane = default.cross_plane;
+        }
+    }
+}
+
+/// Force that the cursor exerts on the objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorField {
+    pub mode: CursorMode,
+    /// Lateral speed of the objects right next to the cursor.
+    /// Zero disables the interaction completely.
+    pub strength: f32,
+    /// The force falls off linearly to zero at this distance.
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CursorMode {
+    /// The cursor does not affect the objects.
+    None,
+    /// Objects drift away from the cursor.
+    Repel,
+    /// Objects gravitate towards the cursor.
+    Attract,
+}
+
+impl CursorField {
+    /// Whether the cursor has any effect on the objects.
+    pub fn is_active(&self) -> bool {
+        self.mode != CursorMode::None && self.strength > 0.0
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.strength.is_finite() || self.strength < 0.0 {
+            log::warn!(
+                "config: `cursor_field.strength` must be non-negative, got {}",
+                self.strength
+            );
+            self.strength = default.strength;
+        }
+        if !self.radius.is_finite() || self.radius <= 0.0 {
+            log::warn!(
+                "config: `cursor_field.radius` must be positive, got {}",
+                self.radius
+            );
+            self.radius = default.radius;
+        }
+    }
+}
+
+impl Default for CursorField {
+    fn default() -> Self {
+        Self {
+            mode: CursorMode::Repel,
+            strength: 1.0,
+            radius: 3.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Motion {
+    /// The objects keep their own velocity.
+    Drift,
+    /// The objects are carried by a slowly changing curl noise flow.
+    Flow(FlowField),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowField {
+    /// Size of the swirls in world units.
+    pub scale: f32,
+    /// Typical speed of the flow, relative to the configured speed.
+    pub strength: f32,
+    /// How fast the flow changes, in swirl sizes per second.
+    pub evolution: f32,
+}
+
+impl FlowField {
+    /// Velocity of the flow at the given position and time, relative to the configured speed.
+    pub fn velocity(&self, position: vec3<f32>, time: f32) -> vec3<f32> {
+        // Slide through the noise diagonally, so that the swirls change shape over time
+        let shift = vec3(0.6, -0.4, 0.7) * self.evolution * time;
+        noise::curl_noise(position / self.scale + shift) * self.strength
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.scale.is_finite() || self.scale <= 0.0 {
+            log::warn!(
+                "config: `motion.scale` must be positive, got {}",
+                self.scale
+            );
+            self.scale = default.scale;
+        }
+        if !self.strength.is_finite() || self.strength < 0.0 {
+            log::warn!(
+                "config: `motion.strength` must be non-negative, got {}",
+                self.strength
+            );
+            self.strength = default.strength;
+        }
+        if !self.evolution.is_finite() || self.evolution < 0.0 {
+            log::warn!(
+                "config: `motion.evolution` must be non-negative, got {}",
+                self.evolution
+            );
+            self.evolution = default.evolution;
+        }
+    }
+}
+
+impl Default for FlowField {
+    fn default() -> Self {
+        Self {
+            scale: 6.0,
+            strength: 0.5,
+            evolution: 0.02,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wind {
+    /// Added to the velocity of every object, relative to the configured speed.
+    pub velocity: vec3<f32>,
+    /// Fraction of the velocity by which the wind swings stronger and weaker.
+    pub variation: f32,
+    /// Seconds of a full swing.
+    pub period: f32,
+}
+
+impl Wind {
+    pub fn velocity(&self, time: f32) -> vec3<f32> {
+        let phase = std::f32::consts::TAU * time / self.period;
+        self.velocity * (1.0 + self.variation * phase.sin())
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.velocity.x.is_finite()
+            || !self.velocity.y.is_finite()
+            || !self.velocity.z.is_finite()
+        {
+            log::warn!(
+                "config: `wind.velocity` must be finite, got {:?}",
+                self.velocity
+            );
+            self.velocity = default.velocity;
+        }
+        if !(0.0..=1.0).contains(&self.variation) {
+            log::warn!(
+                "config: `wind.variation` must be in range [0, 1], got {}",
+                self.variation
+            );
+            self.variation = default.variation;
+        }
+        if !self.period.is_finite() || self.period <= 0.0 {
+            log::warn!(
+                "config: `wind.period` must be positive, got {}",
+                self.period
+            );
+            self.period = default.period;
+        }
+    }
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            velocity: vec3(0.05, 0.0, 0.0),
+            variation: 0.5,
+            period: 20.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lifetime {
+    /// Seconds it takes a new object to grow from a point to its full size.
+    pub grow_time: f32,
+    pub grow_easing: Easing,
+    /// Distance in world units before the removal over which the object shrinks to a point.
+    pub shrink_distance: f32,
+    pub shrink_easing: Easing,
+}
+
+/// Plane oscillating along its normal, while the objects spawn across its range
+/// and are removed after a fixed lifetime instead of when passing the plane.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sweep {
+    /// Distance in world units the plane moves to either side of its offset.
+    /// The objects spawn within this range along the Z axis.
+    pub amplitude: f32,
+    /// Seconds of a full back and forth.
+    pub period: f32,
+    /// Multiplier for the speed of the objects, keeping them nearly still.
+    pub drift: f32,
+    /// Seconds the objects stay, including growing and shrinking.
+    pub lifetime: f32,
+}
+
+impl Sweep {
+    /// Offset of the plane from its configured position at the given time.
+    pub fn offset(&self, time: f32) -> f32 {
+        self.amplitude * (time / self.period * std::f32::consts::TAU).sin()
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.amplitude.is_finite() || self.amplitude < 0.0 {
+            log::warn!(
+                "config: `sweep.amplitude` must be non-negative, got {}",
+                self.amplitude
+            );
+            self.amplitude = default.amplitude;
+        }
+        if !self.period.is_finite() || self.period <= 0.0 {
+            log::warn!(
+                "config: `sweep.period` must be positive, got {}",
+                self.period
+            );
+            self.period = default.period;
+        }
+        if !self.drift.is_finite() || self.drift < 0.0 {
+            log::warn!(
+                "config: `sweep.drift` must be non-negative, got {}",
+                self.drift
+            );
+            self.drift = default.drift;
+        }
+        if !self.lifetime.is_finite() || self.lifetime <= 0.0 {
+            log::warn!(
+                "config: `sweep.lifetime` must be positive, got {}",
+                self.lifetime
+            );
+            self.lifetime = default.lifetime;
+        }
+    }
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Self {
+            amplitude: 3.0,
+            period: 8.0,
+            drift: 0.05,
+            lifetime: 20.0,
+        }
+    }
+}
+
+/// Shape of a transition from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    Linear,
+    /// Slowing down towards the end.
+    QuadOut,
+    CubicOut,
+    /// Slow at both ends.
+    Smoothstep,
+    /// Overshooting a bit before settling.
+    BackOut,
+}
+
+impl Easing {
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Self::Smoothstep => t * t * (3.0 - 2.0 * t),
+            Self::BackOut => {
+                const OVERSHOOT: f32 = 1.70158;
+                let t = t - 1.0;
+                1.0 + t * t * ((OVERSHOOT + 1.0) * t + OVERSHOOT)
+            }
+        }
+    }
+}
+
+impl Lifetime {
+    /// Fraction of the full size of an object, given its age in seconds
+    /// and the distance left until it is removed.
+    pub fn size(&self, age: f32, distance_left: f32) -> f32 {
+        let grow = if self.grow_time > 0.0 {
+            self.grow_easing.apply(age / self.grow_time)
+        } else {
+            1.0
+        };
+        let shrink = if self.shrink_distance > 0.0 {
+            self.shrink_easing
+                .apply(distance_left / self.shrink_distance)
+        } else {
+            1.0
+        };
+        grow * shrink
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.grow_time.is_finite() || self.grow_time < 0.0 {
+            log::warn!(
+                "config: `lifetime.grow_time` must be non-negative, got {}",
+                self.grow_time
+            );
+            self.grow_time = default.grow_time;
+        }
+        if !self.shrink_distance.is_finite() || self.shrink_distance < 0.0 {
+            log::warn!(
+                "config: `lifetime.shrink_distance` must be non-negative, got {}",
+                self.shrink_distance
+            );
+            self.shrink_distance = default.shrink_distance;
+        }
+    }
+}
+
+impl Default for Lifetime {
+    fn default() -> Self {
+        Self {
+            grow_time: 0.6,
+            grow_easing: Easing::BackOut,
+            shrink_distance: 0.5,
+            shrink_easing: Easing::QuadOut,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Fracture {
+    pub enabled: bool,
+    /// Number of random cuts through an object, each splitting all of its shards in two.
+    pub cuts: usize,
+    /// Speed of the shards flying apart, relative to the speed of the objects.
+    pub spread: f32,
+    /// Seconds it takes the shards to fade away.
+    pub fade_time: f32,
+}
+
+impl Fracture {
+    /// More cuts make too many tiny shards to be worth slicing.
+    const MAX_CUTS: usize = 4;
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if self.cuts > Self::MAX_CUTS {
+            log::warn!(
+                "config: `fracture.cuts` must be at most {}, got {}",
+                Self::MAX_CUTS,
+                self.cuts
+            );
+            self.cuts = Self::MAX_CUTS;
+        }
+        if !self.spread.is_finite() || self.spread < 0.0 {
+            log::warn!(
+                "config: `fracture.spread` must be non-negative, got {}",
+                self.spread
+            );
+            self.spread = default.spread;
+        }
+        if !self.fade_time.is_finite() || self.fade_time <= 0.0 {
+            log::warn!(
+                "config: `fracture.fade_time` must be positive, got {}",
+                self.fade_time
+            );
+            self.fade_time = default.fade_time;
+        }
+    }
+}
+
+impl Default for Fracture {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cuts: 2,
+            spread: 0.6,
+            fade_time: 0.8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Wobble {
+    /// Largest displacement along each axis in world units. Zero disables the wobble.
+    pub amplitude: f32,
+    /// How fast the wobble changes over time.
+    pub frequency: f32,
+}
+
+impl Wobble {
+    /// Displacement of an object at the given position and time.
+    pub fn offset(&self, position: vec3<f32>, time: f32) -> vec3<f32> {
+        if self.amplitude == 0.0 {
+            return vec3::ZERO;
+        }
+        let p = position + vec3(0.0, 0.0, time * self.frequency);
+        noise::vector_noise(p) * self.amplitude
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.amplitude.is_finite() || self.amplitude < 0.0 {
+            log::warn!(
+                "config: `wobble.amplitude` must be non-negative, got {}",
+                self.amplitude
+            );
+            self.amplitude = default.amplitude;
+        }
+        if !self.frequency.is_finite() || self.frequency < 0.0 {
+            log::warn!(
+                "config: `wobble.frequency` must be non-negative, got {}",
+                self.frequency
+            );
+            self.frequency = default.frequency;
+        }
+    }
+}
+
+impl Default for Wobble {
+    fn default() -> Self {
+        Self {
+            amplitude: 0.1,
+            frequency: 0.5,
+        }
+    }
+}
+
+/// Sinusoidal change of the object sizes, with the parameters picked per object
+/// from the ranges and a random phase.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pulsation {
+    /// Range of the amplitude as a fraction of the size. Zero disables the pulsation.
+    pub min_amplitude: f32,
+    pub max_amplitude: f32,
+    /// Range of the frequency in cycles per second.
+    pub min_frequency: f32,
+    pub max_frequency: f32,
+}
+
+impl Pulsation {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !(0.0..1.0).contains(&self.min_amplitude)
+            || !(0.0..1.0).contains(&self.max_amplitude)
+            || self.min_amplitude > self.max_amplitude
+        {
+            log::warn!(
+                "config: `pulsation` amplitudes must be in range [0, 1) with `min_amplitude` not above `max_amplitude`, got {} and {}",
+                self.min_amplitude,
+                self.max_amplitude
+            );
+            self.min_amplitude = default.min_amplitude;
+            self.max_amplitude = default.max_amplitude;
+        }
+        if !self.min_frequency.is_finite()
+            || !self.max_frequency.is_finite()
+            || self.min_frequency < 0.0
+            || self.min_frequency > self.max_frequency
+        {
+            log::warn!(
+                "config: `pulsation` frequencies must be non-negative with `min_frequency` not above `max_frequency`, got {} and {}",
+                self.min_frequency,
+                self.max_frequency
+            );
+            self.min_frequency = default.min_frequency;
+            self.max_frequency = default.max_frequency;
+        }
+    }
+}
+
+impl Default for Pulsation {
+    fn default() -> Self {
+        Self {
+            min_amplitude: 0.0,
+            max_amplitude: 0.0,
+            min_frequency: 0.2,
+            max_frequency: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HitStop {
+    pub enabled: bool,
+    /// Objects at least this large trigger it, the spawned objects range from 0.3 to 1.
+    pub min_scale: f32,
+    /// Time scale at the start of the dip, easing back to normal.
+    pub time_scale: f32,
+    /// Seconds of real time the dip lasts.
+    pub duration: f32,
+    pub easing: Easing,
+    /// Seconds the outline of the object flashes.
+    pub flash_time: f32,
+}
+
+impl HitStop {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.min_scale.is_finite() || self.min_scale < 0.0 {
+            log::warn!(
+                "config: `hit_stop.min_scale` must be non-negative, got {}",
+                self.min_scale
+            );
+            self.min_scale = default.min_scale;
+        }
+        if !(0.0..=1.0).contains(&self.time_scale) {
+            log::warn!(
+                "config: `hit_stop.time_scale` must be in range [0, 1], got {}",
+                self.time_scale
+            );
+            self.time_scale = default.time_scale;
+        }
+        if !self.duration.is_finite() || self.duration <= 0.0 {
+            log::warn!(
+                "config: `hit_stop.duration` must be positive, got {}",
+                self.duration
+            );
+            self.duration = default.duration;
+        }
+        if !self.flash_time.is_finite() || self.flash_time < 0.0 {
+            log::warn!(
+                "config: `hit_stop.flash_time` must be non-negative, got {}",
+                self.flash_time
+            );
+            self.flash_time = default.flash_time;
+        }
+    }
+}
+
+impl Default for HitStop {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_scale: 0.9,
+            time_scale: 0.2,
+            duration: 0.3,
+            easing: Easing::QuadOut,
+            flash_time: 0.3,
+        }
+    }
+}
+
+/// Lines between the sections of the objects close to each other,
+/// with weak springs pulling the linked objects together.
+/// The lines are not drawn with the GPU slicing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Constellation {
+    pub enabled: bool,
+    /// Objects closer than this in world units are linked.
+    pub distance: f32,
+    /// Fraction per second of the stretch beyond half the `distance` that the springs pull back.
+    pub strength: f32,
+    /// Width of the lines in world units.
+    pub width: f32,
+    /// Opacity of the lines, which take the colors of the linked objects.
+    pub opacity: f32,
+}
+
+impl Constellation {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.distance.is_finite() || self.distance <= 0.0 {
+            log::warn!(
+                "config: `constellation.distance` must be positive, got {}",
+                self.distance
+            );
+            self.distance = default.distance;
+        }
+        if !self.strength.is_finite() || self.strength < 0.0 {
+            log::warn!(
+                "config: `constellation.strength` must be non-negative, got {}",
+                self.strength
+            );
+            self.strength = default.strength;
+        }
+        if !self.width.is_finite() || self.width <= 0.0 {
+            log::warn!(
+                "config: `constellation.width` must be positive, got {}",
+                self.width
+            );
+            self.width = default.width;
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            log::warn!(
+                "config: `constellation.opacity` must be in range [0, 1], got {}",
+                self.opacity
+            );
+            self.opacity = default.opacity;
+        }
+    }
+}
+
+impl Default for Constellation {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            distance: 4.0,
+            strength: 0.1,
+            width: 0.03,
+            opacity: 0.4,
+        }
+    }
+}
+
+/// Thin lines between the centers of the sections close to each other on the plane,
+/// fading out with the distance. Unlike the [Constellation], the objects are not pulled.
+/// The lines are not drawn with the GPU slicing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SectionLines {
+    pub enabled: bool,
+    /// Sections with the centers closer than this in world units are connected.
+    pub distance: f32,
+    /// Maximum number of lines from a section, the shortest ones are kept.
+    pub max_degree: usize,
+    /// Width of the lines in world units.
+    pub width: f32,
+    /// Opacity of the shortest lines, which take the colors of the connected sections.
+    pub opacity: f32,
+}
+
+impl SectionLines {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.distance.is_finite() || self.distance <= 0.0 {
+            log::warn!(
+                "config: `section_lines.distance` must be positive, got {}",
+                self.distance
+            );
+            self.distance = default.distance;
+        }
+        if !self.width.is_finite() || self.width <= 0.0 {
+            log::warn!(
+                "config: `section_lines.width` must be positive, got {}",
+                self.width
+            );
+            self.width = default.width;
+        }
+        if !(0.0..=1.0).contains(&self.opacity) {
+            log::warn!(
+                "config: `section_lines.opacity` must be in range [0, 1], got {}",
+                self.opacity
+            );
+            self.opacity = default.opacity;
+        }
+    }
+}
+
+impl Default for SectionLines {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            distance: 3.0,
+            max_degree: 3,
+            width: 0.02,
+            opacity: 0.5,
+        }
+    }
+}
+
+/// Spawned objects of one prefab that gradually take the shape of another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Morph {
+    /// Name of the prefab that morphs.
+    pub from: String,
+    /// Name of the prefab it turns into.
+    pub to: String,
+    /// Chance that a spawned object of the `from` prefab morphs.
+    pub chance: f32,
+    /// Range of the seconds the morph takes, starting from the spawn.
+    pub min_duration: f32,
+    pub max_duration: f32,
+    pub easing: Easing,
+}
+
+impl Morph {
+    /// Check that the morph is usable, logging the problem if not.
+    fn is_valid(&self, field: &str, prefabs: &BTreeMap<String, Shape>) -> bool {
+        for name in [&self.from, &self.to] {
+            if !prefabs.contains_key(name) {
+                log::warn!("config: `{field}` refers to an unknown prefab `{name}`");
+                return false;
+            }
+        }
+        if !(0.0..=1.0).contains(&self.chance) {
+            log::warn!(
+                "config: `{field}.chance` must be in range [0, 1], got {}",
+                self.chance
+            );
+            return false;
+        }
+        if !self.min_duration.is_finite()
+            || self.min_duration <= 0.0
+            || !self.max_duration.is_finite()
+            || self.max_duration < self.min_duration
+        {
+            log::warn!(
+                "config: `{field}` durations must be positive with `min_duration` not above `max_duration`, got {} and {}",
+                self.min_duration,
+                self.max_duration
+            );
+            return false;
+        }
+        true
+    }
+}
+
+/// A group of objects at the same depth.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Layer {
+    /// Relative probability of the new objects to spawn in this layer.
+    pub weight: f32,
+    /// Multiplier for the speed of the objects.
+    pub speed: f32,
+    /// Multiplier for the scale of the objects.
+    pub scale: f32,
+    /// Saturation of the colors, from 0 (gray) to 1 (unchanged).
+    pub saturation: f32,
+    /// How much the objects follow the scroll and the device tilt parallax,
+    /// lower values make the layer appear farther.
+    pub parallax: f32,
+}
+
+impl Layer {
+    /// Check that the layer is usable, logging the problem if not.
+    fn is_valid(&self, field: &str) -> bool {
+        if !self.weight.is_finite() || self.weight < 0.0 {
+            log::warn!(
+                "config: `{field}.weight` must be non-negative, got {}",
+                self.weight
+            );
+            return false;
+        }
+        for (name, value) in [("speed", self.speed), ("scale", self.scale)] {
+            if !value.is_finite() || value <= 0.0 {
+                log::warn!("config: `{field}.{name}` must be positive, got {value}");
+                return false;
+            }
+        }
+        if !(0.0..=1.0).contains(&self.saturation) {
+            log::warn!(
+                "config: `{field}.saturation` must be in range [0, 1], got {}",
+                self.saturation
+            );
+            return false;
+        }
+        if !self.parallax.is_finite() {
+            log::warn!(
+                "config: `{field}.parallax` must be finite, got {}",
+                self.parallax
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Desaturate the color of an object in this layer.
+    pub fn apply_color(&self, color: Rgba<f32>) -> Rgba<f32> {
+        let gray = 0.299 * color.r + 0.587 * color.g + 0.114 * color.b;
+        let mix = |x: f32| gray + (x - gray) * self.saturation;
+        Rgba::new(mix(color.r), mix(color.g), mix(color.b), color.a)
+    }
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            weight: 1.0,
+            speed: 1.0,
+            scale: 1.0,
+            saturation: 1.0,
+            parallax: 1.0,
+        }
+    }
+}
+
+/// A gravity well pulling the objects sideways towards its position.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attractor {
+    /// Position in world units, the view is centered on the origin.
+    pub position: vec2<f32>,
+    /// Acceleration of the objects at the center, relative to the configured speed.
+    /// Negative values push the objects away instead.
+    pub strength: f32,
+    /// Distance at which the pull weakens to a half.
+    pub falloff: f32,
+}
+
+impl Attractor {
+    /// Check that the attractor is usable, logging the problem if not.
+    fn is_valid(&self, field: &str) -> bool {
+        if !self.position.x.is_finite() || !self.position.y.is_finite() {
+            log::warn!(
+                "config: `{field}.position` must be finite, got {:?}",
+                self.position
+            );
+            return false;
+        }
+        if !self.strength.is_finite() {
+            log::warn!(
+                "config: `{field}.strength` must be finite, got {}",
+                self.strength
+            );
+            return false;
+        }
+        if !self.falloff.is_finite() || self.falloff <= 0.0 {
+            log::warn!(
+                "config: `{field}.falloff` must be positive, got {}",
+                self.falloff
+            );
+            return false;
+        }
+        true
+    }
+
+    /// Acceleration of an object at the given position.
+    pub fn pull(&self, position: vec2<f32>) -> vec2<f32> {
+        let delta = self.position - position;
+        let falloff = 1.0 / (1.0 + delta.len_sqr() / (self.falloff * self.falloff));
+        delta.normalize_or_zero() * self.strength * falloff
+    }
+}
+
+/// Automatic degradation on weak or discharging devices.
+/// The profile is also used when the visitor asks for low quality.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LowPower {
+    /// Battery level (from 0 to 1) below which the profile is used while discharging.
+    pub battery_level: Option<f32>,
+    /// Device memory in gigabytes at or below which the profile is used.
+    pub device_memory: Option<f32>,
+    pub profile: PowerProfile,
+}
+
+impl LowPower {
+    fn validate(&mut self) {
+        if let Some(level) = self.battery_level {
+            if !(0.0..=1.0).contains(&level) {
+                log::warn!(
+                    "config: `low_power.battery_level` must be in range [0, 1], got {}",
+                    level
+                );
+                self.battery_level = Self::default().battery_level;
+            }
+        }
+        self.profile.validate();
+    }
+}
+
+impl Default for LowPower {
+    fn default() -> Self {
+        Self {
+            battery_level: Some(0.2),
+            device_memory: Some(2.0),
+            profile: PowerProfile::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerProfile {
+    /// Multiplier for the frame rate limit.
+    pub fps_scale: f32,
+    /// Multiplier for the number of objects.
+    pub density_scale: f32,
+    /// Show the transient effects, like bursts and ripples.
+    pub effects: bool,
+}
+
+impl PowerProfile {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.fps_scale.is_finite() || self.fps_scale <= 0.0 {
+            log::warn!(
+                "config: `low_power.profile.fps_scale` must be positive, got {}",
+                self.fps_scale
+            );
+            self.fps_scale = default.fps_scale;
+        }
+        if !self.density_scale.is_finite() || self.density_scale < 0.0 {
+            log::warn!(
+                "config: `low_power.profile.density_scale` must be non-negative, got {}",
+                self.density_scale
+            );
+            self.density_scale = default.density_scale;
+        }
+    }
+}
+
+impl Default for PowerProfile {
+    fn default() -> Self {
+        Self {
+            fps_scale: 0.5,
+            density_scale: 0.5,
+            effects: false,
+        }
+    }
+}
+
+/// Feedback loop on the frame time: when the average exceeds the budget,
+/// the next level is applied on top of the previous ones,
+/// and when there is enough headroom, the last one is lifted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveQuality {
+    pub enabled: bool,
+    /// Frame time budget in milliseconds.
+    /// If not set, a quarter more than the frame time at the target frame rate.
+    pub budget: Option<f32>,
+    /// Fraction of the budget the frame time has to drop below to restore a level.
+    pub headroom: f32,
+    /// Seconds over which the frame time is averaged before changing the level.
+    pub window: f32,
+    /// Degradations in the order they are applied.
+    pub levels: Vec<QualityLevel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QualityLevel {
+    /// Multiply the spawn rate.
+    SpawnRate(f32),
+    /// Hide the transient effects, like bursts and ripples.
+    NoEffects,
+    /// Multiply the resolution the scene is rendered at.
+    RenderScale(f32),
+}
+
+impl AdaptiveQuality {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if let Some(budget) = self.budget {
+            if !budget.is_finite() || budget <= 0.0 {
+                log::warn!(
+                    "config: `adaptive_quality.budget` must be positive, got {}",
+                    budget
+                );
+                self.budget = default.budget;
+            }
+        }
+        if !(0.0..=1.0).contains(&self.headroom) {
+            log::warn!(
+                "config: `adaptive_quality.headroom` must be in range [0, 1], got {}",
+                self.headroom
+            );
+            self.headroom = default.headroom;
+        }
+        if !self.window.is_finite() || self.window <= 0.0 {
+            log::warn!(
+                "config: `adaptive_quality.window` must be positive, got {}",
+                self.window
+            );
+            self.window = default.window;
+        }
+        self.levels.retain(|level| match *level {
+            QualityLevel::SpawnRate(scale) | QualityLevel::RenderScale(scale) => {
+                let valid = scale.is_finite() && scale > 0.0 && scale <= 1.0;
+                if !valid {
+                    log::warn!(
+                        "config: `adaptive_quality.levels` scales must be in range (0, 1], got {}",
+                        scale
+                    );
+                }
+                valid
+            }
+            QualityLevel::NoEffects => true,
+        });
+    }
+}
+
+impl Default for AdaptiveQuality {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget: None,
+            headroom: 0.7,
+            window: 2.0,
+            levels: vec![
+                QualityLevel::SpawnRate(0.75),
+                QualityLevel::NoEffects,
+                QualityLevel::RenderScale(0.75),
+                QualityLevel::SpawnRate(0.5),
+                QualityLevel::RenderScale(0.5),
+            ],
+        }
+    }
+}
+
+/// Slow-down after a period without input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Idle {
+    /// Seconds without input or scrolling before slowing down.
+    /// If not set, the simulation never slows down.
+    pub delay: Option<f32>,
+    /// Multiplier for the simulation speed while idle.
+    pub time_scale: f32,
+    /// Time in seconds it takes to mostly ramp the speed down or back up.
+    pub ramp: f32,
+}
+
+impl Idle {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if let Some(delay) = self.delay {
+            if !delay.is_finite() || delay < 0.0 {
+                log::warn!("config: `idle.delay` must be non-negative, got {}", delay);
+                self.delay = default.delay;
+            }
+        }
+        if !self.time_scale.is_finite() || self.time_scale < 0.0 {
+            log::warn!(
+                "config: `idle.time_scale` must be non-negative, got {}",
+                self.time_scale
+            );
+            self.time_scale = default.time_scale;
+        }
+        if !self.ramp.is_finite() || self.ramp < 0.0 {
+            log::warn!(
+                "config: `idle.ramp` must be non-negative, got {}",
+                self.ramp
+            );
+            self.ramp = default.ramp;
+        }
+    }
+}
+
+impl Default for Idle {
+    fn default() -> Self {
+        Self {
+            delay: Some(30.0),
+            time_scale: 0.25,
+            ramp: 2.0,
+        }
+    }
+}
+
+/// A gentler mode for users who prefer reduced motion.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReducedMotion {
+    /// Multiplier for the speed of the objects, both through the plane and from the cursor.
+    pub speed_scale: f32,
+    /// Multiplier for the rotation speed of the objects.
+    pub rotation_scale: f32,
+    /// Keep the objects completely still, sliced at random depths.
+    pub frozen: bool,
+    /// Show the transient effects, like bursts and ripples.
+    pub effects: bool,
+}
+
+impl ReducedMotion {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.speed_scale.is_finite() || self.speed_scale < 0.0 {
+            log::warn!(
+                "config: `reduced_motion.speed_scale` must be non-negative, got {}",
+                self.speed_scale
+            );
+            self.speed_scale = default.speed_scale;
+        }
+        if !self.rotation_scale.is_finite() || self.rotation_scale < 0.0 {
+            log::warn!(
+                "config: `reduced_motion.rotation_scale` must be non-negative, got {}",
+                self.rotation_scale
+            );
+            self.rotation_scale = default.rotation_scale;
+        }
+    }
+}
+
+impl Default for ReducedMotion {
+    fn default() -> Self {
+        Self {
+            speed_scale: 0.1,
+            rotation_scale: 0.0,
+            frozen: false,
+            effects: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Links {
+    pub targets: Vec<Link>,
+    /// Probability that a new object carries a random link.
+    pub chance: f32,
+    /// No more links are assigned while there are this many on the screen.
+    pub max_active: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Link {
+    pub url: String,
+    /// Color of the objects carrying the link, instead of the theme colors.
+    #[serde(default, deserialize_with = "deserialize_color_option")]
+    pub color: Option<Rgba<f32>>,
+}
+
+impl Links {
+    fn validate(&mut self) {
+        if !(0.0..=1.0).contains(&self.chance) {
+            log::warn!(
+                "config: `links.chance` must be in range [0, 1], got {}",
+                self.chance
+            );
+            self.chance = Self::default().chance;
+        }
+    }
+}
+
+impl Default for Links {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            chance: 0.05,
+            max_active: 3,
+        }
+    }
+}
+
+/// Rings emitted by the cursor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CursorRipple {
+    pub enabled: bool,
+    /// Cursor speed in world units per second above which ripples are emitted.
+    pub speed_threshold: f32,
+    /// Radius the ripples expand to.
+    pub radius: f32,
+    /// Minimum time in seconds between ripples from movement.
+    pub cooldown: f32,
+}
+
+impl CursorRipple {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.speed_threshold.is_finite() || self.speed_threshold < 0.0 {
+            log::warn!(
+                "config: `cursor_ripple.speed_threshold` must be non-negative, got {}",
+                self.speed_threshold
+            );
+            self.speed_threshold = default.speed_threshold;
+        }
+        if !self.radius.is_finite() || self.radius <= 0.0 {
+            log::warn!(
+                "config: `cursor_ripple.radius` must be positive, got {}",
+                self.radius
+            );
+            self.radius = default.radius;
+        }
+        if !self.cooldown.is_finite() || self.cooldown < 0.0 {
+            log::warn!(
+                "config: `cursor_ripple.cooldown` must be non-negative, got {}",
+                self.cooldown
+            );
+            self.cooldown = default.cooldown;
+        }
+    }
+}
+
+impl Default for CursorRipple {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            speed_threshold: 40.0,
+            radius: 1.0,
+            cooldown: 0.15,
+        }
+    }
+}
+
+/// Tilting the plane by dragging the pointer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DragTilt {
+    /// Tilt in degrees per pixel dragged.
+    /// Zero disables the interaction.
+    pub sensitivity: f32,
+    /// Maximum tilt in degrees along each axis.
+    pub max_angle: f32,
+    /// Stiffness of the spring pulling the plane back when released.
+    pub stiffness: f32,
+}
+
+impl DragTilt {
+    pub fn is_active(&self) -> bool {
+        self.sensitivity > 0.0 && self.max_angle > 0.0
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.sensitivity.is_finite() || self.sensitivity < 0.0 {
+            log::warn!(
+                "config: `drag_tilt.sensitivity` must be non-negative, got {}",
+                self.sensitivity
+            );
+            self.sensitivity = default.sensitivity;
+        }
+        if !self.max_angle.is_finite() || !(0.0..90.0).contains(&self.max_angle) {
+            log::warn!(
+                "config: `drag_tilt.max_angle` must be in range [0, 90), got {}",
+                self.max_angle
+            );
+            self.max_angle = default.max_angle;
+        }
+        if !self.stiffness.is_finite() || self.stiffness <= 0.0 {
+            log::warn!(
+                "config: `drag_tilt.stiffness` must be positive, got {}",
+                self.stiffness
+            );
+            self.stiffness = default.stiffness;
+        }
+    }
+}
+
+impl Default for DragTilt {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.1,
+            max_angle: 30.0,
+            stiffness: 50.0,
+        }
+    }
+}
+
+/// Scroll-linked parallax.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Parallax {
+    /// World units the view moves per pixel scrolled.
+    pub strength: f32,
+    /// Additional movement of the objects proportional to their size,
+    /// so that bigger (closer) objects appear to move faster.
+    pub depth: f32,
+    /// How quickly the view catches up with the scroll position.
+    pub smoothing: f32,
+}
+
+impl Parallax {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.strength.is_finite() {
+            log::warn!(
+                "config: `parallax.strength` must be finite, got {}",
+                self.strength
+            );
+            self.strength = default.strength;
+        }
+        if !self.depth.is_finite() {
+            log::warn!(
+                "config: `parallax.depth` must be finite, got {}",
+                self.depth
+            );
+            self.depth = default.depth;
+        }
+        if !self.smoothing.is_finite() || self.smoothing <= 0.0 {
+            log::warn!(
+                "config: `parallax.smoothing` must be positive, got {}",
+                self.smoothing
+            );
+            self.smoothing = default.smoothing;
+        }
+    }
+}
+
+impl Default for Parallax {
+    fn default() -> Self {
+        Self {
+            strength: 0.005,
+            depth: 0.5,
+            smoothing: 8.0,
+        }
+    }
+}
+
+/// Device orientation parallax.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Gyroscope {
+    /// World units the view moves per degree of tilt.
+    /// Zero disables the effect.
+    pub sensitivity: f32,
+    /// Degrees the plane tilts per degree of the device tilt.
+    pub plane_tilt: f32,
+    /// Tilt beyond this many degrees from the initial attitude is ignored.
+    pub max_angle: f32,
+}
+
+impl Gyroscope {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.sensitivity.is_finite() {
+            log::warn!(
+                "config: `gyroscope.sensitivity` must be finite, got {}",
+                self.sensitivity
+            );
+            self.sensitivity = default.sensitivity;
+        }
+        if !self.plane_tilt.is_finite() {
+            log::warn!(
+                "config: `gyroscope.plane_tilt` must be finite, got {}",
+                self.plane_tilt
+            );
+            self.plane_tilt = default.plane_tilt;
+        }
+        if !self.max_angle.is_finite() || self.max_angle < 0.0 {
+            log::warn!(
+                "config: `gyroscope.max_angle` must be non-negative, got {}",
+                self.max_angle
+            );
+            self.max_angle = default.max_angle;
+        }
+    }
+}
+
+impl Default for Gyroscope {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.02,
+            plane_tilt: 0.0,
+            max_angle: 30.0,
+        }
+    }
+}
+
+/// Scene parameters while a section of the page is visible.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageSection {
+    /// Colors of the newly spawned objects, instead of the theme colors.
+    pub palette: Option<Palette>,
+    /// Multiplier for the number of objects.
+    pub density: f32,
+    /// Multiplier for the speed of the objects.
+    pub speed: f32,
+}
+
+impl PageSection {
+    fn validate(&mut self, field: &str) {
+        if self
+            .palette
+            .as_ref()
+            .is_some_and(|palette| palette.is_empty())
+        {
+            log::warn!("config: `{field}.palette` is empty, using the theme colors");
+            self.palette = None;
+        }
+        if !self.density.is_finite() || self.density < 0.0 {
+            log::warn!(
+                "config: `{field}.density` must be non-negative, got {}",
+                self.density
+            );
+            self.density = 1.0;
+        }
+        if !self.speed.is_finite() || self.speed < 0.0 {
+            log::warn!(
+                "config: `{field}.speed` must be non-negative, got {}",
+                self.speed
+            );
+            self.speed = 1.0;
+        }
+    }
+}
+
+impl Default for PageSection {
+    fn default() -> Self {
+        Self {
+            palette: None,
+            density: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EasterEgg {
+    /// Colors of the objects spawned while the scene is active.
+    pub palette: Palette,
+    /// Number of objects spawned at once when triggered.
+    pub count: usize,
+    /// Seconds the scene stays active.
+    pub duration: f32,
+}
+
+/// Events played back at fixed times, for a choreographed scene.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+    pub events: Vec<TimelineEvent>,
+    /// Seconds after which the timeline starts over. If not set, it is played once.
+    pub repeat: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// Seconds since the start of the timeline.
+    pub time: f32,
+    pub action: TimelineAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TimelineAction {
+    /// Switch to the theme with the given name.
+    Theme(String),
+    /// Color the new objects from the palette for the given number of seconds.
+    Palette { palette: Palette, duration: f32 },
+    /// Spawn this many objects at once.
+    Burst(usize),
+    /// Tilt the plane by the angles in degrees around the Y and X axes, it springs back afterwards.
+    Tilt(vec2<f32>),
+    /// Switch how the objects move.
+    Motion(Motion),
+}
+
+impl Timeline {
+    fn validate(&mut self) {
+        self.events.retain(|event| {
+            let valid = event.time.is_finite() && event.time >= 0.0;
+            if !valid {
+                log::warn!(
+                    "config: `timeline.events` times must be non-negative, got {}",
+                    event.time
+                );
+            }
+            valid
+        });
+        // Played back in order
+        self.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        if let Some(repeat) = self.repeat {
+            if !repeat.is_finite() || repeat <= 0.0 {
+                log::warn!("config: `timeline.repeat` must be positive, got {}", repeat);
+                self.repeat = None;
+            } else if self.events.last().is_some_and(|event| event.time >= repeat) {
+                log::warn!("config: `timeline.events` after `timeline.repeat` are never played");
+            }
+        }
+    }
+}
+
+impl EasterEgg {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if self.palette.is_empty() {
+            log::warn!("config: `easter_egg.palette` is empty, using the default palette");
+            self.palette = default.palette;
+        }
+        if !self.duration.is_finite() || self.duration < 0.0 {
+            log::warn!(
+                "config: `easter_egg.duration` must be non-negative, got {}",
+                self.duration
+            );
+            self.duration = default.duration;
+        }
+    }
+}
+
+impl Default for EasterEgg {
+    fn default() -> Self {
+        Self {
+            palette: Palette::from_colors([rgb(0xffd700), rgb(0xf4c430), rgb(0xfff1a8)]),
+            count: 30,
+            duration: 8.0,
+        }
+    }
+}
+
+/// A set of overrides active during a range of dates each year.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    /// First day of the range as `(month, day)`.
+    pub start: (u32, u32),
+    /// Last day of the range (inclusive), may wrap around the new year.
+    pub end: (u32, u32),
+    pub overrides: ConfigOverrides,
+}
+
+impl Preset {
+    pub fn is_active(&self, date: (u32, u32)) -> bool {
+        if self.start <= self.end {
+            self.start <= date && date <= self.end
+        } else {
+            self.start <= date || date <= self.end
+        }
+    }
+}
+
+/// Blending between four sets of parameters over the day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayCycle {
+    pub enabled: bool,
+    pub dawn: DayPhase,
+    pub day: DayPhase,
+    pub dusk: DayPhase,
+    pub night: DayPhase,
+    /// Curve of the blend from one phase to the next.
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DayPhase {
+    /// Local hour, from 0 to 24, at which the phase is in full effect.
+    pub hour: f32,
+    /// Colors of the newly spawned objects, instead of the theme colors.
+    pub palette: Option<Palette>,
+    /// Multiplier for the number of objects.
+    pub density: f32,
+    /// Multiplier for the speed of the objects.
+    pub speed: f32,
+}
+
+impl DayCycle {
+    /// The phases around the given local hour and how far it is from the first to the second.
+    pub fn blend(&self, hour: f32) -> Option<(&DayPhase, &DayPhase, f32)> {
+        if !self.enabled {
+            return None;
+        }
+        let mut phases = [&self.dawn, &self.day, &self.dusk, &self.night];
+        phases.sort_by(|a, b| a.hour.total_cmp(&b.hour));
+        // Wrap around midnight
+        let next = phases
+            .iter()
+            .position(|phase| phase.hour > hour)
+            .unwrap_or(0);
+        let (from, to) = (
+            phases[(next + phases.len() - 1) % phases.len()],
+            phases[next],
+        );
+        let span = (to.hour - from.hour).rem_euclid(24.0);
+        let t = if span > 0.0 {
+            (hour - from.hour).rem_euclid(24.0) / span
+        } else {
+            1.0
+        };
+        Some((from, to, self.easing.apply(t)))
+    }
+
+    fn validate(&mut self) {
+        for (name, phase) in [
+            ("dawn", &mut self.dawn),
+            ("day", &mut self.day),
+            ("dusk", &mut self.dusk),
+            ("night", &mut self.night),
+        ] {
+            let field = format!("day_cycle.{name}");
+            if !(0.0..24.0).contains(&phase.hour) {
+                log::warn!(
+                    "config: `{field}.hour` must be in range [0, 24), got {}",
+                    phase.hour
+                );
+                phase.hour = phase.hour.clamp(0.0, 23.99);
+            }
+            if phase
+                .palette
+                .as_ref()
+                .is_some_and(|palette| palette.is_empty())
+            {
+                log::warn!("config: `{field}.palette` is empty, using the theme colors");
+                phase.palette = None;
+            }
+            if !phase.density.is_finite() || phase.density < 0.0 {
+                log::warn!(
+                    "config: `{field}.density` must be non-negative, got {}",
+                    phase.density
+                );
+                phase.density = 1.0;
+            }
+            if !phase.speed.is_finite() || phase.speed < 0.0 {
+                log::warn!(
+                    "config: `{field}.speed` must be non-negative, got {}",
+                    phase.speed
+                );
+                phase.speed = 1.0;
+            }
+        }
+    }
+}
+
+impl Default for DayCycle {
+    fn default() -> Self {
+        let phase = |hour, density, speed| DayPhase {
+            hour,
+            palette: None,
+            density,
+            speed,
+        };
+        Self {
+            enabled: false,
+            dawn: phase(6.0, 0.8, 0.8),
+            day: phase(12.0, 1.0, 1.0),
+            dusk: phase(19.0, 0.8, 0.8),
+            night: phase(0.0, 0.5, 0.6),
+            easing: Easing::Smoothstep,
+        }
+    }
+}
+
+impl Default for DayPhase {
+    fn default() -> Self {
+        Self {
+            hour: 12.0,
+            palette: None,
+            density: 1.0,
+            speed: 1.0,
+        }
+    }
+}
+
+/// Frequency bands of an audio source driving the scene.
+/// Bass speeds up the spawning, mids pulse the size of the objects
+/// and treble makes the sections glow.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioReactive {
+    /// What to listen to, `None` disables the audio.
+    pub source: Option<AudioSource>,
+    /// Time in seconds it takes the levels to mostly follow the sound, so that the visuals do not strobe.
+    pub smoothing: f32,
+    /// Extra spawn rate at full bass, as a fraction of the normal rate.
+    pub spawn_rate: f32,
+    /// Extra size of the objects at full mids, as a fraction of their size.
+    pub pulse: f32,
+    /// How far the sections brighten towards white at full treble, from 0 to 1.
+    pub glow: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AudioSource {
+    /// A looping track, by its url relative to the page.
+    Track(String),
+    /// The microphone, asking the visitor for the permission.
+    Microphone,
+}
+
+impl AudioReactive {
+    fn validate(&mut self) {
+        let default = Self::default();
+        if !self.smoothing.is_finite() || self.smoothing < 0.0 {
+            log::warn!(
+                "config: `audio.smoothing` must be non-negative, got {}",
+                self.smoothing
+            );
+            self.smoothing = default.smoothing;
+        }
+        if !self.spawn_rate.is_finite() || self.spawn_rate < 0.0 {
+            log::warn!(
+                "config: `audio.spawn_rate` must be non-negative, got {}",
+                self.spawn_rate
+            );
+            self.spawn_rate = default.spawn_rate;
+        }
+        if !self.pulse.is_finite() || self.pulse < 0.0 {
+            log::warn!(
+                "config: `audio.pulse` must be non-negative, got {}",
+                self.pulse
+            );
+            self.pulse = default.pulse;
+        }
+        if !(0.0..=1.0).contains(&self.glow) {
+            log::warn!(
+                "config: `audio.glow` must be in range [0, 1], got {}",
+                self.glow
+            );
+            self.glow = default.glow;
+        }
+    }
+}
+
+impl Default for AudioReactive {
+    fn default() -> Self {
+        Self {
+            source: None,
+            smoothing: 0.15,
+            spawn_rate: 2.0,
+            pulse: 0.15,
+            glow: 0.3,
+        }
+    }
+}
+
+/// Pulses on the beat of a looping track embedded in the page, without analyzing the audio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Beat {
+    /// Tempo in beats per minute, `None` disables the pulses.
+    pub bpm: Option<f32>,
+    /// Time in seconds from the start to the first beat.
+    pub offset: f32,
+    /// Number of beats in a bar, the first of them accented.
+    pub beats_per_bar: u32,
+    /// Time in seconds it takes a pulse to mostly fade.
+    pub decay: f32,
+    /// Change of the sections at the peak of a pulse.
+    pub pulse: Highlight,
+    /// Number of objects spawned at the start of every bar.
+    pub accent: usize,
+}
+
+impl Beat {
+    /// Number of the last beat and the seconds passed since it, `None` before the first beat.
+    pub fn position(&self, time: f32) -> Option<(u64, f32)> {
+        let interval = 60.0 / self.bpm?;
+        let time = time - self.offset;
+        if time < 0.0 {
+            return None;
+        }
+        let beat = (time / interval).floor();
+        Some((beat as u64, time - beat * interval))
+    }
+
+    /// Strength of the pulse at the given time, from 0 to 1.
+    pub fn pulse(&self, time: f32) -> f32 {
+        let Some((_, since)) = self.position(time) else {
+            return 0.0;
+        };
+        if self.decay > 0.0 {
+            (-since * 3.0 / self.decay).exp()
+        } else {
+            0.0
+        }
+    }
+
+    fn validate(&mut self) {
+        let default = Self::default();
+        if let Some(bpm) = self.bpm {
+            if !bpm.is_finite() || bpm <= 0.0 {
+                log::warn!("config: `beat.bpm` must be positive, got {}", bpm);
+                self.bpm = None;
+            }
+        }
+        if !self.offset.is_finite() {
+            log::warn!("config: `beat.offset` must be finite, got {}", self.offset);
+            self.offset = default.offset;
+        }
+        if self.beats_per_bar == 0 {
+            log::warn!("config: `beat.beats_per_bar` must be positive");
+            self.beats_per_bar = default.beats_per_bar;
+        }
+        if !self.decay.is_finite() || self.decay < 0.0 {
+            log::warn!(
+                "config: `beat.decay` must be non-negative, got {}",
+                self.decay
+            );
+            self.decay = default.decay;
+        }
+        self.pulse.validate("beat.pulse");
+    }
+}
+
+impl Default for Beat {
+    fn default() -> Self {
+        Self {
+            bpm: None,
+            offset: 0.0,
+            beats_per_bar: 4,
+            decay: 0.2,
+            pulse: Highlight {
+                lighten: 0.15,
+                width_scale: 1.5,
+            },
+            accent: 3,
+        }
+    }
+}
+
+/// Hours since the local midnight, with the fraction.
+pub fn local_hour() -> f32 {
+    use chrono::Timelike;
+    let now = chrono::Local::now();
+    now.hour() as f32 + now.minute() as f32 / 60.0 + now.second() as f32 / 3600.0
+}
+
+/// Today's `(month, day)` in the local timezone.
+fn local_date() -> (u32, u32) {
+    use chrono::Datelike;
+    let today = chrono::Local::now().date_naive();
+    (today.month(), today.day())
+}
+
+/// Config values overridden from the command line.
+#[derive(clap::Args, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigOverrides {
+    /// Force a seasonal preset regardless of the date, or `none` to disable presets.
+    #[clap(long)]
+    pub preset: Option<String>,
+    /// Name of the theme to use on startup.
+    #[clap(long)]
+    pub theme: Option<String>,
+    /// Seed for the random number generator.
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// Multiplier for the simulation speed.
+    #[clap(long)]
+    pub time_scale: Option<f32>,
+    /// Objects spawned per second.
+    #[clap(long)]
+    pub spawn_rate: Option<f32>,
+    /// Speed at which objects travel through the plane.
+    #[clap(long)]
+    pub speed: Option<f32>,
+    /// Limit the frame rate, or `0` to remove the limit.
+    #[clap(long)]
+    pub fps_limit: Option<f32>,
+    /// Comma-separated list of hex colors used for the objects in every theme.
+    #[clap(long, value_delimiter = ',', value_parser = parse_color)]
+    #[serde(deserialize_with = "deserialize_colors_option")]
+    pub palette: Option<Vec<Rgba<f32>>>,
+}
+
+impl ConfigOverrides {
+    /// Apply the active seasonal preset followed by the overrides themselves.
+    pub fn apply(&self, config: &mut Config) {
+        let preset = match self.preset.as_deref() {
+            Some("none") => None,
+            Some(name) => {
+                let preset = config.presets.get(name).cloned();
+                if preset.is_none() {
+                    log::warn!("Preset {:?} not found in the config", name);
+                }
+                preset
+            }
+            None => {
+                let today = local_date();
+                config
+                    .presets
+                    .values()
+                    .find(|preset| preset.is_active(today))
+                    .cloned()
+            }
+        };
+        if let Some(preset) = preset {
+            preset.overrides.apply_values(config);
+        }
+        self.apply_values(config);
+        config.validate();
+    }
+
+    fn apply_values(&self, config: &mut Config) {
+        if let Some(theme) = &self.theme {
+            config.theme = theme.clone();
+        }
+        if let Some(seed) = self.seed {
+            config.seed = Some(seed);
+        }
+        if let Some(time_scale) = self.time_scale {
+            config.time_scale = time_scale;
+        }
+        if let Some(spawn_rate) = self.spawn_rate {
+            config.spawn_rate = spawn_rate;
+        }
+        if let Some(speed) = self.speed {
+            config.speed = speed;
+        }
+        if let Some(fps) = self.fps_limit {
+            config.target_fps = (fps != 0.0).then_some(fps);
+        }
+        if let Some(palette) = &self.palette {
+            for theme in config.themes.values_mut() {
+                theme.object_colors = Palette::from_colors(palette.iter().copied());
+                theme.prefab_colors.clear();
+                if let Some(light) = &mut theme.light {
+                    light.object_colors = Palette::from_colors(palette.iter().copied());
+                    light.prefab_colors.clear();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Ron,
+    Json,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &std::path::Path) -> anyhow::Result<Self> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        match extension {
+            "ron" => Ok(Self::Ron),
+            "json" => Ok(Self::Json),
+            "toml" => Ok(Self::Toml),
+            _ => anyhow::bail!("unsupported config format {:?}", path),
+        }
+    }
+
+    pub fn parse<T: serde::de::DeserializeOwned>(self, source: &str) -> anyhow::Result<T> {
+        match self {
+            Self::Ron => deserialize(&mut ron::Deserializer::from_str(source)?),
+            Self::Json => deserialize(&mut serde_json::Deserializer::from_str(source)),
+            Self::Toml => deserialize(toml::Deserializer::new(source)),
+        }
+    }
+}
+
+/// Deserialize keeping track of the path to the field that failed.
+fn deserialize<'de, D, T>(deserializer: D) -> anyhow::Result<T>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+    D::Error: std::fmt::Display,
+{
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|err| anyhow::anyhow!("at `{}`: {}", err.path(), err.inner()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    #[serde(deserialize_with = "deserialize_color")]
+    pub background_color: Rgba<f32>,
+    /// A list of colors, a rule to generate them from a base hue,
+    /// or an image to extract them from.
+    pub object_colors: Palette,
+    /// Colors used for specific prefabs by name, instead of `object_colors`.
+    #[serde(default)]
+    pub prefab_colors: HashMap<String, Palette>,
+    /// Style of the cross section outlines.
+    #[serde(default)]
+    pub stroke: Stroke,
+    /// Style of the section under the cursor.
+    #[serde(default)]
+    pub highlight: Highlight,
+    /// Variant of the theme used with the light color scheme.
+    #[serde(default)]
+    pub light: Option<Box<Theme>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+pub enum ColorScheme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn variant(&self, scheme: ColorScheme) -> &Self {
+        match scheme {
+            ColorScheme::Dark => self,
+            ColorScheme::Light => self.light.as_deref().unwrap_or(self),
+        }
+    }
+
+    async fn resolve_palettes(&mut self, base_path: &std::path::Path) {
+        self.object_colors.resolve(base_path).await;
+        for palette in self.prefab_colors.values_mut() {
+            palette.resolve(base_path).await;
+        }
+    }
+
+    fn validate(&mut self, field: &str) {
+        if self.object_colors.is_empty() {
+            log::warn!("config: `{field}.object_colors` is empty, using the default palette");
+            self.object_colors = Self::default().object_colors;
+        }
+        self.prefab_colors.retain(|name, palette| {
+            if palette.is_empty() {
+                log::warn!(
+                    "config: `{field}.prefab_colors.{name}` is empty, using `object_colors`"
+                );
+            }
+            !palette.is_empty()
+        });
+        self.stroke.validate(&format!("{field}.stroke"));
+        self.highlight.validate(&format!("{field}.highlight"));
+    }
+
+    /// Pick a random color for the given prefab.
+    pub fn random_color(&self, prefab: &str, rng: &mut impl Rng) -> Rgba<f32> {
+        self.prefab_colors
+            .get(prefab)
+            .unwrap_or(&self.object_colors)
+            .choose(rng)
+            .unwrap_or(Rgba::WHITE)
+    }
+
+    /// Pick a random color from `object_colors`, regardless of the prefab.
+    pub fn random_object_color(&self, rng: &mut impl Rng) -> Rgba<f32> {
+        self.object_colors.choose(rng).unwrap_or(Rgba::WHITE)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background_color: rgb(0x0f0f1b),
+            object_colors: Palette::from_colors([rgb(0x565a75), rgb(0xc6b7be), rgb(0xfafbf6)]),
+            prefab_colors: HashMap::new(),
+            stroke: Stroke::default(),
+            highlight: Highlight::default(),
+            light: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stroke {
+    /// Width of the outline.
+    pub width: f32,
+    /// The outline is thinned down for small sections, but never below this width.
+    pub min_width: f32,
+    /// Sections smaller than this radius are drawn filled instead of outlined.
+    pub fill_below: Option<f32>,
+}
+
+impl Stroke {
+    fn validate(&mut self, field: &str) {
+        let default = Self::default();
+        if !self.width.is_finite() || self.width < 0.0 {
+            log::warn!(
+                "config: `{field}.width` must be non-negative, got {}",
+                self.width
+            );
+            self.width = default.width;
+        }
+        if !self.min_width.is_finite() || self.min_width < 0.0 {
+            log::warn!(
+                "config: `{field}.min_width` must be non-negative, got {}",
+                self.min_width
+            );
+            self.min_width = default.min_width;
+        }
+        if self.min_width > self.width {
+            log::warn!("config: `{field}.min_width` is larger than `{field}.width`");
+            self.min_width = self.width;
+        }
+    }
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        Self {
+            width: 0.1,
+            min_width: 0.0,
+            fill_below: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Highlight {
+    /// Mix the color towards white by this amount, or towards black if negative.
+    pub lighten: f32,
+    /// Multiplier for the outline width.
+    pub width_scale: f32,
+}
+
+impl Highlight {
+    pub fn apply_color(&self, color: Rgba<f32>) -> Rgba<f32> {
+        let target = if self.lighten >= 0.0 { 1.0 } else { 0.0 };
+        let t = self.lighten.abs();
+        let mix = |x: f32| x + (target - x) * t;
+        Rgba::new(mix(color.r), mix(color.g), mix(color.b), color.a)
+    }
+
+    /// Only a part of the effect, from none at 0 to full at 1.
+    pub fn scaled(&self, t: f32) -> Self {
+        Self {
+            lighten: self.lighten * t,
+            width_scale: 1.0 + (self.width_scale - 1.0) * t,
+        }
+    }
+
+    pub fn apply_stroke(&self, stroke: &Stroke) -> Stroke {
+        Stroke {
+            width: stroke.width * self.width_scale,
+            ..stroke.clone()
+        }
+    }
+
+    fn validate(&mut self, field: &str) {
+        let default = Self::default();
+        if !(-1.0..=1.0).contains(&self.lighten) {
+            log::warn!(
+                "config: `{field}.lighten` must be in range [-1, 1], got {}",
+                self.lighten
+            );
+            self.lighten = default.lighten;
+        }
+        if !self.width_scale.is_finite() || self.width_scale < 0.0 {
+            log::warn!(
+                "config: `{field}.width_scale` must be non-negative, got {}",
+                self.width_scale
+            );
+            self.width_scale = default.width_scale;
+        }
+    }
+}
+
+impl Default for Highlight {
+    fn default() -> Self {
+        Self {
+            lighten: 0.3,
+            width_scale: 2.0,
+        }
+    }
+}
+
+/// Color from a `0xRRGGBB` literal.
+fn rgb(hex: u32) -> Rgba<f32> {
+    let channel = |shift: u32| ((hex >> shift) & 0xff) as f32 / 255.0;
+    Rgba::opaque(channel(16), channel(8), channel(0))
+}
+
+/// Parse a hex color in the `#RGB`, `#RGBA`, `#RRGGBB`, or `#RRGGBBAA` format.
+/// The leading `#` is optional.
+pub fn parse_color(s: &str) -> anyhow::Result<Rgba<f32>> {
+    if let Some(args) = s
+        .trim()
+        .strip_prefix("rgba(")
+        .or_else(|| s.trim().strip_prefix("rgb("))
+    {
+        return parse_rgb_function(s, args);
+    }
+    let hex = s.trim().trim_start_matches('#');
+    // Expand the short form by duplicating every digit
+    let hex = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+        _ => hex.to_owned(),
+    };
+    let value = u32::from_str_radix(&hex, 16)?;
+    match hex.len() {
+        6 => Ok(rgb(value)),
+        8 => {
+            let mut color = rgb(value >> 8);
+            color.a = (value & 0xff) as f32 / 255.0;
+            Ok(color)
+        }
+        _ => anyhow::bail!("expected a color in the #RRGGBB format, got {:?}", s),
+    }
+}
+
+/// Parse the arguments of the CSS `rgb(r, g, b)` or `rgba(r, g, b, a)` notation,
+/// as returned by the browser for computed styles.
+fn parse_rgb_function(s: &str, args: &str) -> anyhow::Result<Rgba<f32>> {
+    let args = args
+        .strip_suffix(')')
+        .ok_or_else(|| anyhow::anyhow!("unclosed parenthesis in {:?}", s))?;
+    let values = args
+        .split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .map(|value| value.parse::<f32>())
+        .collect::<Result<Vec<f32>, _>>()?;
+    match values[..] {
+        [r, g, b] => Ok(Rgba::opaque(r / 255.0, g / 255.0, b / 255.0)),
+        [r, g, b, a] => Ok(Rgba::new(r / 255.0, g / 255.0, b / 255.0, a)),
+        _ => anyhow::bail!("expected 3 or 4 components in {:?}", s),
+    }
+}
+
+/// A color in the config, parsed with [parse_color] when written as a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorRepr {
+    Text(String),
+    Components(Rgba<f32>),
+}
+
+impl ColorRepr {
+    fn parse<E: serde::de::Error>(self) -> Result<Rgba<f32>, E> {
+        match self {
+            Self::Text(s) => parse_color(&s).map_err(E::custom),
+            Self::Components(color) => Ok(color),
+        }
+    }
+}
+
+/// Deserialize a color from any format accepted by [parse_color].
+pub fn deserialize_color<'de, D>(deserializer: D) -> Result<Rgba<f32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    ColorRepr::deserialize(deserializer)?.parse()
+}
+
+/// Same as [deserialize_color] for an optional color.
+pub fn deserialize_color_option<'de, D>(deserializer: D) -> Result<Option<Rgba<f32>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<ColorRepr>::deserialize(deserializer)?
+        .map(ColorRepr::parse)
+        .transpose()
+}
+
+/// Same as [deserialize_color] for an optional list of colors.
+pub fn deserialize_colors_option<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<Rgba<f32>>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<Vec<ColorRepr>>::deserialize(deserializer)?
+        .map(|colors| colors.into_iter().map(ColorRepr::parse).collect())
+        .transpose()
+}
+
+/// Path to the local overrides for the config at `path`,
+/// e.g. `config.local.ron` for `config.ron`.
+/// Local overrides are only used natively and only if the file exists.
+pub fn local_path(path: &std::path::Path) -> Option<std::path::PathBuf> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let stem = path.file_stem()?.to_str()?;
+        let extension = path.extension()?.to_str()?;
+        let local = path.with_file_name(format!("{stem}.local.{extension}"));
+        local.exists().then_some(local)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let _ = path;
+        None
+    }
+}
+
+/// Deserializes a field that is present as `Some`, so that optional fields
+/// don't have to be wrapped twice.
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+macro_rules! config_patch {
+    (
+        replace { $($field:ident: $ty:ty,)* }
+        merge { $($map:ident: $map_ty:ty,)* }
+    ) => {
+        /// A partial config layered on top of another one.
+        /// Present fields replace the base values, maps are merged entry by entry.
+        #[derive(Debug, Default, Deserialize)]
+        #[serde(deny_unknown_fields)]
+        pub struct ConfigPatch {
+            $(
+                #[serde(default, deserialize_with = "present")]
+                $field: Option<$ty>,
+            )*
+            $(
+                #[serde(default)]
+                $map: $map_ty,
+            )*
+        }
+
+        impl ConfigPatch {
+            pub fn apply(self, config: &mut Config) {
+                $(
+                    if let Some(value) = self.$field {
+                        config.$field = value;
+                    }
+                )*
+                $(
+                    config.$map.extend(self.$map);
+                )*
+            }
+        }
+    };
+}
+
+config_patch! {
+    replace {
+        theme: String,
+        color_scheme: Option<ColorScheme>,
+        css_colors: bool,
+        allowed_origins: Vec<String>,
+        seed: Option<u64>,
+        target_fps: Option<f32>,
+        max_pixel_ratio: Option<f32>,
+        lod_size: Option<f32>,
+        slicing_thread: bool,
+        gpu_slicing: bool,
+        low_fps: Option<f32>,
+        low_power: LowPower,
+        adaptive_quality: AdaptiveQuality,
+        pause_when_hidden: bool,
+        reduce_motion: Option<bool>,
+        reduced_motion: ReducedMotion,
+        time_scale: f32,
+        max_frame_time: f32,
+        max_spawns_per_step: usize,
+        simulation_rate: Option<f32>,
+        idle: Idle,
+        spawn_rate: f32,
+        speed: f32,
+        speed_variation: f32,
+        lateral_drift: f32,
+        collisions: bool,
+        motion: Motion,
+        wind: Wind,
+        wobble: Wobble,
+        pulsation: Pulsation,
+        lifetime: Lifetime,
+        fracture: Fracture,
+        constellation: Constellation,
+        section_lines: SectionLines,
+        hit_stop: HitStop,
+        cluster_chance: f32,
+        reverse_chance: f32,
+        max_objects: usize,
+        reference_area: Option<f32>,
+        spawn_margin: f32,
+        morphs: Vec<Morph>,
+        layers: Vec<Layer>,
+        cross_plane: Plane,
+        sweep: Option<Sweep>,
+        cursor_field: CursorField,
+        attractors: Vec<Attractor>,
+        click_spawn: bool,
+        click_pop: bool,
+        links: Links,
+        cursor_ripple: CursorRipple,
+        drag_tilt: DragTilt,
+        parallax: Parallax,
+        gyroscope: Gyroscope,
+        debug: bool,
+        easter_egg: EasterEgg,
+        timeline: Timeline,
+        page_transition: f32,
+        day_cycle: DayCycle,
+        audio: AudioReactive,
+        beat: Beat,
+    }
+    merge {
+        themes: HashMap<String, Theme>,
+        prefabs: BTreeMap<String, Shape>,
+        prefab_weights: HashMap<String, f32>,
+        presets: BTreeMap<String, Preset>,
+        page_sections: BTreeMap<String, PageSection>,
+    }
+}
+
+impl ConfigPatch {
+    pub async fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let format = ConfigFormat::from_path(path)?;
+        let source = file::load_string(path).await?;
+        format
+            .parse(&source)
+            .map_err(|err| err.context(format!("invalid config {:?}", path)))
+    }
+}
+
+/// Loads the config in the background.
+/// When watching, the config file (and its local overrides) is reloaded whenever it changes:
+/// natively, the file's modification time is polled;
+/// on the web, the file is periodically refetched.
+pub struct ConfigReloader {
+    path: std::path::PathBuf,
+    overrides: ConfigOverrides,
+    watch: bool,
+    next_check: f32,
+    #[cfg(not(target_arch = "wasm32"))]
+    last_modified: [Option<std::time::SystemTime>; 2],
+    loading: Option<future::LocalBoxFuture<'static, anyhow::Result<Config>>>,
+}
+
+impl ConfigReloader {
+    #[cfg(not(target_arch = "wasm32"))]
+    const CHECK_INTERVAL: f32 = 0.5;
+    #[cfg(target_arch = "wasm32")]
+    const CHECK_INTERVAL: f32 = 2.0;
+
+    pub fn new(path: impl AsRef<std::path::Path>, overrides: ConfigOverrides, watch: bool) -> Self {
+        let path = path.as_ref().to_owned();
+        Self {
+            overrides,
+            watch,
+            #[cfg(not(target_arch = "wasm32"))]
+            last_modified: modified_times(&path),
+            path,
+            next_check: Self::CHECK_INTERVAL,
+            loading: None,
+        }
+    }
+
+    pub fn is_watching(&self) -> bool {
+        self.watch
+    }
+
+    /// Start reloading the config, unless a reload is already in progress.
+    pub fn request(&mut self) {
+        if self.loading.is_some() {
+            return;
+        }
+        let path = self.path.clone();
+        let overrides = self.overrides.clone();
+        self.loading = Some(
+            async move {
+                let mut config = Config::load(path).await?;
+                overrides.apply(&mut config);
+                Ok(config)
+            }
+            .boxed_local(),
+        );
+    }
+
+    /// Returns the new config once it has been reloaded.
+    pub fn update(&mut self, delta_time: f32) -> Option<Config> {
+        self.next_check -= delta_time;
+        if self.watch && self.next_check < 0.0 {
+            self.next_check = Self::CHECK_INTERVAL;
+            self.check();
+        }
+
+        let loading = self.loading.as_mut()?;
+        let result = loading.now_or_never()?;
+        self.loading = None;
+        match result {
+            Ok(config) => {
+                log::info!("Loaded config from {:?}", self.path);
+                Some(config)
+            }
+            Err(err) => {
+                log::error!("Failed to load config: {:?}", err);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn check(&mut self) {
+        let modified = modified_times(&self.path);
+        if modified != self.last_modified {
+            self.last_modified = modified;
+            self.request();
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn check(&mut self) {
+        self.request();
+    }
+}
+
+/// Modification times of the config and its local overrides.
+#[cfg(not(target_arch = "wasm32"))]
+fn modified_times(path: &std::path::Path) -> [Option<std::time::SystemTime>; 2] {
+    let modified = |path: &std::path::Path| {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    };
+    [
+        modified(path),
+        local_path(path).and_then(|local| modified(&local)),
+    ]
+}
+
+#[test]
+fn test_easing() {
+    for easing in [
+        Easing::Linear,
+        Easing::QuadOut,
+        Easing::CubicOut,
+        Easing::Smoothstep,
+        Easing::BackOut,
+    ] {
+        assert!(easing.apply(0.0).abs() < 1e-5, "{easing:?}");
+        assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{easing:?}");
+    }
+    assert!(Easing::BackOut.apply(0.8) > 1.0);
+    let lifetime = Lifetime::default();
+    assert!(lifetime.size(0.0, 10.0).abs() < 1e-5);
+    assert!((lifetime.size(10.0, 10.0) - 1.0).abs() < 1e-5);
+    assert_eq!(lifetime.size(10.0, 0.0), 0.0);
+}
+
+#[test]
+fn test_day_cycle() {
+    let mut cycle = DayCycle {
+        enabled: true,
+        easing: Easing::Linear,
+        ..DayCycle::default()
+    };
+    let (from, to, t) = cycle.blend(9.0).unwrap();
+    assert_eq!((from.hour, to.hour), (6.0, 12.0));
+    assert!((t - 0.5).abs() < 1e-5);
+    // Across midnight
+    let (from, to, t) = cycle.blend(21.5).unwrap();
+    assert_eq!((from.hour, to.hour), (19.0, 0.0));
+    assert!((t - 0.5).abs() < 1e-5);
+    let (from, _, t) = cycle.blend(0.0).unwrap();
+    assert_eq!(from.hour, 0.0);
+    assert!(t.abs() < 1e-5);
+
+    cycle.enabled = false;
+    assert!(cycle.blend(9.0).is_none());
+}
+
+#[test]
+fn test_beat() {
+    let mut beat = Beat {
+        bpm: Some(120.0),
+        offset: 0.25,
+        ..Beat::default()
+    };
+    assert_eq!(beat.position(0.0), None);
+    let (index, since) = beat.position(1.5).unwrap();
+    assert_eq!(index, 2);
+    assert!((since - 0.25).abs() < 1e-5);
+    assert!(beat.pulse(1.25) > beat.pulse(1.5));
+
+    beat.bpm = None;
+    assert_eq!(beat.pulse(1.25), 0.0);
+}
+
+#[test]
+fn test_parse_color() {
+    let check = |s: &str, expected: Rgba<f32>| {
+        let color = parse_color(s).unwrap();
+        let delta = [
+            color.r - expected.r,
+            color.g - expected.g,
+            color.b - expected.b,
+            color.a - expected.a,
+        ];
+        assert!(
+            delta.iter().all(|d| d.abs() < 1e-5),
+            "{:?}: {:?} != {:?}",
+            s,
+            color,
+            expected
+        );
+    };
+
+    check("#1e1e2e", rgb(0x1e1e2e));
+    check("1e1e2e", rgb(0x1e1e2e));
+    check("#fff", Rgba::WHITE);
+    check("#0000", Rgba::TRANSPARENT_BLACK);
+    check("#ff000080", Rgba::new(1.0, 0.0, 0.0, 128.0 / 255.0));
+    check("rgb(30, 30, 46)", rgb(0x1e1e2e));
+    check("rgba(255, 0, 0, 0.5)", Rgba::new(1.0, 0.0, 0.0, 0.5));
+    check(" rgb(255 255 255) ", Rgba::WHITE);
+    assert!(parse_color("rgb(1, 2)").is_err());
+    assert!(parse_color("#12345").is_err());
+    assert!(parse_color("#xyzxyz").is_err());
+}
+
+#[test]
+fn test_deserialize_colors() {
+    let theme: Theme = ron::from_str(
+        r##"(
+            background_color: "rgb(30, 30, 46)",
+            object_colors: ["#fff", (color: "#000", weight: 2.0)],
+        )"##,
+    )
+    .unwrap();
+    assert!((theme.background_color.b - 46.0 / 255.0).abs() < 1e-5);
+    let colors = theme.object_colors.colors();
+    assert_eq!(colors[0].color(), Rgba::WHITE);
+    assert_eq!(colors[1].color(), rgb(0x000000));
+
+    let link: Link = serde_json::from_str(r##"{ "url": "/", "color": "#f00" }"##).unwrap();
+    assert_eq!(link.color, Some(rgb(0xff0000)));
+    let overrides: ConfigOverrides = toml::from_str(r##"palette = ["#fff", "#000"]"##).unwrap();
+    assert_eq!(overrides.palette, Some(vec![Rgba::WHITE, rgb(0x000000)]));
+}
+
+#[test]
+fn test_patch_covers_config() {
+    // Every field of the config has to be patchable, as unknown fields reject the whole patch
+    let source = ron::to_string(&Config::example()).unwrap();
+    let patch: ConfigPatch = ron::from_str(&source).unwrap();
+    let mut config = Config::default();
+    patch.apply(&mut config);
+    assert_eq!(config.morphs.len(), Config::example().morphs.len());
+}
+
+#[test]
+fn test_example_config() {
+    let pretty = ron::ser::PrettyConfig::default().indentor("    ".to_owned());
+    let text = ron::ser::to_string_pretty(&Config::example(), pretty).unwrap();
+    for line in text.lines() {
+        let top_level = line.starts_with("    ") && !line.starts_with("     ");
+        assert!(
+            !(top_level && line.ends_with(": None,")),
+            "not populated in the example: {line}"
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use geng::prelude::*;
+
+/// Short-lived decorative effects, drawn on top of the sections.
+#[derive(Default)]
+pub struct Effects {
+    particles: Vec<Particle>,
+    ripples: Vec<Ripple>,
+}
+
+struct Particle {
+    position: vec2<f32>,
+    velocity: vec2<f32>,
+    radius: f32,
+    color: Rgba<f32>,
+    lifetime: Lifetime,
+}
+
+/// An expanding and fading ring.
+struct Ripple {
+    position: vec2<f32>,
+    max_radius: f32,
+    width: f32,
+    color: Rgba<f32>,
+    lifetime: Lifetime,
+}
+
+struct Lifetime {
+    time: f32,
+    duration: f32,
+}
+
+impl Lifetime {
+    fn new(duration: f32) -> Self {
+        Self {
+            time: 0.0,
+            duration,
+        }
+    }
+
+    /// Goes from 0 to 1 over the lifetime.
+    fn progress(&self) -> f32 {
+        (self.time / self.duration).clamp(0.0, 1.0)
+    }
+
+    fn is_over(&self) -> bool {
+        self.time >= self.duration
+    }
+}
+
+impl Effects {
+    /// Scatter particles from a point.
+    pub fn burst(&mut self, position: vec2<f32>, color: Rgba<f32>, rng: &mut impl Rng) {
+        for _ in 0..12 {
+            let angle = Angle::from_degrees(rng.gen_range(0.0..360.0));
+            let speed = rng.gen_range(1.0..3.0);
+            self.particles.push(Particle {
+                position,
+                velocity: vec2(speed, 0.0).rotate(angle),
+                radius: rng.gen_range(0.05..0.1),
+                color,
+                lifetime: Lifetime::new(rng.gen_range(0.4..0.8)),
+            });
+        }
+    }
+
+    pub fn ripple(&mut self, position: vec2<f32>, max_radius: f32, width: f32, color: Rgba<f32>) {
+        self.ripples.push(Ripple {
+            position,
+            max_radius,
+            width,
+            color,
+            lifetime: Lifetime::new(0.6),
+        });
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        for particle in &mut self.particles {
+            particle.lifetime.time += delta_time;
+            particle.position += particle.velocity * delta_time;
+            // Drag
+            particle.velocity *= (1.0 - 3.0 * delta_time).max(0.0);
+        }
+        for ripple in &mut self.ripples {
+            ripple.lifetime.time += delta_time;
+        }
+        self.particles
+            .retain(|particle| !particle.lifetime.is_over());
+        self.ripples.retain(|ripple| !ripple.lifetime.is_over());
+    }
+
+    pub fn draw(&self, geng: &Geng, framebuffer: &mut ugli::Framebuffer, camera: &Camera2d) {
+        let fade = |color: Rgba<f32>, t: f32| Rgba {
+            a: color.a * (1.0 - t),
+            ..color
+        };
+
+        for ripple in &self.ripples {
+            let t = ripple.lifetime.progress();
+            // Ease out
+            let radius = ripple.max_radius * (1.0 - (1.0 - t).powi(2));
+            geng.draw2d().draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Ellipse::circle_with_cut(
+                    ripple.position,
+                    (radius - ripple.width).max(0.0),
+                    radius,
+                    fade(ripple.color, t),
+                ),
+            );
+        }
+
+        for particle in &self.particles {
+            let t = particle.lifetime.progress();
+            geng.draw2d().draw2d(
+                framebuffer,
+                camera,
+                &draw2d::Ellipse::circle(
+                    particle.position,
+                    particle.radius,
+                    fade(particle.color, t),
+                ),
+            );
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use geng::prelude::*;
+
+/// Limits how often frames are simulated and rendered.
+/// Natively, the thread sleeps until the next frame is due;
+/// on the web, frames that come too early are skipped.
+pub struct FrameLimiter {
+    timer: Timer,
+    last_frame: f64,
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self {
+            timer: Timer::new(),
+            last_frame: 0.0,
+        }
+    }
+
+    /// Returns whether the current frame should be processed.
+    pub fn frame(&mut self, target_fps: Option<f32>) -> bool {
+        let Some(target_fps) = target_fps else {
+            self.last_frame = self.timer.elapsed().as_secs_f64();
+            return true;
+        };
+        let frame_time = 1.0 / target_fps as f64;
+        let elapsed = self.timer.elapsed().as_secs_f64() - self.last_frame;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if elapsed < frame_time {
+                std::thread::sleep(std::time::Duration::from_secs_f64(frame_time - elapsed));
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            // Small tolerance so that we don't skip a frame due to jitter
+            if elapsed + 0.002 < frame_time {
+                return false;
+            }
+        }
+
+        self.last_frame = self.timer.elapsed().as_secs_f64();
+        true
+    }
+}
@@ -1,6 +1,7 @@
 use geng::prelude::*;
 
 /// `M` must be equal to `N * 2`
+#[cfg(test)]
 fn array_flatten<T: Copy, const N: usize, const M: usize>(arr: [[T; N]; 2]) -> [T; M] {
     arr.into_iter()
         .flatten()
@@ -10,14 +11,17 @@ fn array_flatten<T: Copy, const N: usize, const M: usize>(arr: [[T; N]; 2]) -> [
         .unwrap()
 }
 
+#[cfg(test)]
 pub fn unit_line() -> [i32; 2] {
     [-1, 1]
 }
 
+#[cfg(test)]
 pub fn unit_square() -> [(i32, i32); 4] {
     array_flatten([unit_line().map(|x| (x, -1)), unit_line().map(|x| (x, 1))])
 }
 
+#[cfg(test)]
 pub fn unit_cube() -> [(i32, i32, i32); 8] {
     array_flatten([
         unit_square().map(|(x, y)| (x, y, -1)),
@@ -25,7 +29,8 @@ pub fn unit_cube() -> [(i32, i32, i32); 8] {
     ])
 }
 
-pub fn unit_cube_triangulated() -> Vec<Vertex> {
+#[cfg(test)]
+pub fn unit_cube_triangulated() -> Vec<Triangle> {
     let vertices = unit_cube().map(|(x, y, z)| vec3(x as f32, y as f32, z as f32));
 
     let triangles = [
@@ -44,58 +49,273 @@ pub fn unit_cube_triangulated() -> Vec<Vertex> {
     ];
     triangles
         .into_iter()
-        .flat_map(|(i, j, k)| Triangle::new(vertices[i], vertices[j], vertices[k]).into_vertices())
+        .map(|(i, j, k)| Triangle::new(vertices[i], vertices[j], vertices[k]))
         .collect()
 }
 
-#[derive(ugli::Vertex, Debug, Clone, Copy)]
-pub struct Vertex {
-    pub a_pos: vec3<f32>,
-    pub a_normal: vec3<f32>,
+#[cfg(test)]
+pub fn unit_octahedron_triangulated() -> Vec<Triangle> {
+    let signs = unit_cube();
+    signs
+        .into_iter()
+        .map(|(x, y, z)| {
+            let a = vec3::UNIT_X * x as f32;
+            let b = vec3::UNIT_Y * y as f32;
+            let c = vec3::UNIT_Z * z as f32;
+            // Keep the normal pointing outwards
+            if x * y * z > 0 {
+                Triangle::new(a, b, c)
+            } else {
+                Triangle::new(a, c, b)
+            }
+        })
+        .collect()
+}
+
+const fn tri(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> Triangle {
+    Triangle {
+        vertices: [
+            vec3(a[0], a[1], a[2]),
+            vec3(b[0], b[1], b[2]),
+            vec3(c[0], c[1], c[2]),
+        ],
+    }
+}
+
+/// Cube with the vertices at `-1` and `1`, baked from [unit_cube_triangulated].
+pub const CUBE: [Triangle; 12] = [
+    tri([-1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [1.0, -1.0, -1.0]),
+    tri([-1.0, -1.0, -1.0], [-1.0, 1.0, -1.0], [1.0, 1.0, -1.0]),
+    tri([-1.0, -1.0, -1.0], [1.0, -1.0, 1.0], [-1.0, -1.0, 1.0]),
+    tri([-1.0, -1.0, -1.0], [1.0, -1.0, -1.0], [1.0, -1.0, 1.0]),
+    tri([1.0, -1.0, -1.0], [1.0, 1.0, 1.0], [1.0, -1.0, 1.0]),
+    tri([1.0, -1.0, -1.0], [1.0, 1.0, -1.0], [1.0, 1.0, 1.0]),
+    tri([-1.0, 1.0, -1.0], [-1.0, -1.0, 1.0], [-1.0, 1.0, 1.0]),
+    tri([-1.0, 1.0, -1.0], [-1.0, -1.0, -1.0], [-1.0, -1.0, 1.0]),
+    tri([1.0, 1.0, -1.0], [-1.0, 1.0, 1.0], [1.0, 1.0, 1.0]),
+    tri([1.0, 1.0, -1.0], [-1.0, 1.0, -1.0], [-1.0, 1.0, 1.0]),
+    tri([-1.0, -1.0, 1.0], [1.0, 1.0, 1.0], [-1.0, 1.0, 1.0]),
+    tri([-1.0, -1.0, 1.0], [1.0, -1.0, 1.0], [1.0, 1.0, 1.0]),
+];
+
+/// Octahedron with the vertices on the unit axes, baked from [unit_octahedron_triangulated].
+pub const OCTAHEDRON: [Triangle; 8] = [
+    tri([-1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+    tri([1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+    tri([-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, -1.0]),
+    tri([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+    tri([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0], [0.0, 0.0, 1.0]),
+    tri([1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+    tri([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+    tri([1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+];
+
+// Spheres with the common subdivision levels, baked by the build script
+include!(concat!(env!("OUT_DIR"), "/spheres.rs"));
+
+/// Triangles of a prefab.
+#[derive(Debug, Clone)]
+pub enum Mesh {
+    /// Baked into the binary.
+    Static(&'static [Triangle]),
+    /// Generated at runtime.
+    Shared(Arc<[Triangle]>),
+}
+
+impl std::ops::Deref for Mesh {
+    type Target = [Triangle];
+
+    fn deref(&self) -> &[Triangle] {
+        match self {
+            Self::Static(triangles) => triangles,
+            Self::Shared(triangles) => triangles,
+        }
+    }
+}
+
+/// A mesh together with its simplified versions, from the full detail to the coarsest.
+#[derive(Debug, Clone)]
+pub struct Lods(Arc<[Mesh]>);
+
+impl Lods {
+    /// Simplify the mesh up to `levels` times,
+    /// stopping when simplifying does not remove any more triangles.
+    pub fn generate(mesh: Mesh, levels: usize) -> Self {
+        let radius = mesh
+            .iter()
+            .flat_map(|triangle| triangle.vertices)
+            .map(|v| v.len())
+            .fold(0.0, f32::max);
+        let mut lods = vec![mesh];
+        for level in 1..=levels {
+            let last = lods.last().unwrap();
+            let cell_size = radius * 0.25 * 2.0_f32.powi(level as i32 - 1);
+            let simplified = simplify(last, cell_size);
+            if simplified.len() >= last.len() || simplified.is_empty() {
+                break;
+            }
+            lods.push(Mesh::Shared(simplified.into()));
+        }
+        Self(lods.into())
+    }
+
+    pub fn full(&self) -> &Mesh {
+        &self.0[0]
+    }
+
+    /// The mesh to use for an object `size` pixels across:
+    /// the next level is used every time the size halves below `threshold`.
+    pub fn select(&self, size: f32, threshold: Option<f32>) -> &Mesh {
+        let Some(threshold) = threshold else {
+            return self.full();
+        };
+        let mut level = 0;
+        let mut threshold = threshold;
+        while level + 1 < self.0.len() && size < threshold {
+            level += 1;
+            threshold /= 2.0;
+        }
+        &self.0[level]
+    }
 }
 
+/// Simplify the mesh by merging the vertices that fall into the same cell of a grid.
+/// Triangles that collapse are removed.
+pub fn simplify(triangles: &[Triangle], cell_size: f32) -> Vec<Triangle> {
+    let cell = |v: vec3<f32>| (v / cell_size).map(|x| x.round() as i32);
+
+    // Merge the vertices in a cell into their average
+    let mut clusters: HashMap<vec3<i32>, (vec3<f32>, f32)> = HashMap::new();
+    for &v in triangles.iter().flat_map(|triangle| &triangle.vertices) {
+        let (sum, count) = clusters.entry(cell(v)).or_insert((vec3::ZERO, 0.0));
+        *sum += v;
+        *count += 1.0;
+    }
+
+    triangles
+        .iter()
+        .filter_map(|triangle| {
+            let [a, b, c] = triangle.vertices.map(cell);
+            if a == b || b == c || c == a {
+                return None;
+            }
+            let [a, b, c] = [a, b, c].map(|cell| {
+                let (sum, count) = clusters[&cell];
+                sum / count
+            });
+            Some(Triangle::new(a, b, c))
+        })
+        .collect()
+}
+
+/// Split every triangle into four at the midpoints of its edges.
+pub fn subdivide(triangles: &[Triangle]) -> Vec<Triangle> {
+    triangles
+        .iter()
+        .flat_map(|triangle| {
+            let [a, b, c] = triangle.vertices;
+            let [ab, bc, ca] = [a + b, b + c, c + a].map(|p| p / 2.0);
+            [
+                Triangle::new(a, ab, ca),
+                Triangle::new(ab, b, bc),
+                Triangle::new(ca, bc, c),
+                Triangle::new(ab, bc, ca),
+            ]
+        })
+        .collect()
+}
+
+/// Octahedron subdivided the given number of times with the vertices pushed onto the unit sphere.
+pub fn sphere(subdivisions: u32) -> Vec<Triangle> {
+    let mut triangles = OCTAHEDRON.to_vec();
+    for _ in 0..subdivisions {
+        triangles = subdivide(&triangles);
+        for triangle in &mut triangles {
+            for v in &mut triangle.vertices {
+                *v = v.normalize();
+            }
+        }
+    }
+    triangles
+}
+
+/// Slice the prefab in its own space and transform only the intersection points.
+/// Returns `false` if the object does not cross the plane.
+pub fn slice_object(
+    cross_plane: &Plane,
+    matrix: mat4<f32>,
+    geometry: &[Triangle],
+    scratch: &mut Vec<vec3<f32>>,
+    points: &mut Vec<vec2<f32>>,
+) -> bool {
+    let local_plane = cross_plane.to_local(matrix);
+    scratch.clear();
+    local_plane.intersect_into(geometry, scratch);
+    if scratch.len() < 3 {
+        return false;
+    }
+    for p in scratch.iter_mut() {
+        *p = (matrix * p.extend(1.0)).into_3d();
+    }
+
+    points.clear();
+    cross_plane.section_loop_into(scratch, points);
+    // Convert coordinate system
+    for p in points.iter_mut() {
+        p.x = -p.x;
+    }
+    true
+}
+
+/// Distance from the origin to the farthest of the triangles along the direction,
+/// or `None` if the ray misses them all.
+pub fn ray_distance(triangles: &[Triangle], direction: vec3<f32>) -> Option<f32> {
+    const EPSILON: f32 = 1e-5;
+    let direction = direction.normalize_or_zero();
+    triangles
+        .iter()
+        .filter_map(|triangle| {
+            // Möller–Trumbore with the ray starting at the origin
+            let [a, b, c] = triangle.vertices;
+            let (e1, e2) = (b - a, c - a);
+            let p = vec3::cross(direction, e2);
+            let det = vec3::dot(e1, p);
+            if det.abs() < EPSILON {
+                return None;
+            }
+            let s = -a;
+            let u = vec3::dot(s, p) / det;
+            let q = vec3::cross(s, e1);
+            let v = vec3::dot(direction, q) / det;
+            if u < -EPSILON || v < -EPSILON || u + v > 1.0 + EPSILON {
+                return None;
+            }
+            let t = vec3::dot(e2, q) / det;
+            (t > 0.0).then_some(t)
+        })
+        .max_by(f32::total_cmp)
+}
+
+/// A triangle with the vertices in the counter clockwise order when looking at its front.
 #[derive(Debug, Clone, Copy)]
 pub struct Triangle {
     pub vertices: [vec3<f32>; 3],
-    pub normal: vec3<f32>,
 }
 
 impl Triangle {
     pub fn new(a: vec3<f32>, b: vec3<f32>, c: vec3<f32>) -> Self {
-        let ab = b - a;
-        let ac = c - a;
-        let normal = vec3(
-            ab.y * ac.z - ab.z * ac.y,
-            ab.z * ac.x - ab.x * ac.z,
-            ab.x * ac.y - ab.y * ac.x,
-        )
-        .normalize_or_zero();
         Self {
             vertices: [a, b, c],
-            normal,
         }
     }
-
-    pub fn into_vertices(self) -> [Vertex; 3] {
-        self.vertices.map(|a_pos| Vertex {
-            a_pos,
-            a_normal: self.normal,
-        })
-    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plane {
     pub normal: vec3<f32>,
     pub offset: f32,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct CrossSectionVertex {
-    pub world_pos: vec3<f32>,
-    pub projected: vec2<f32>,
-}
-
 impl Plane {
     pub fn matrix(&self) -> mat4<f32> {
         let flat = vec2(self.normal.x, self.normal.z);
@@ -120,68 +340,359 @@ impl Plane {
         vec3::dot(self.normal.normalize_or_zero(), point) - self.offset
     }
 
-    pub fn intersect_segment(&self, p1: vec3<f32>, p2: vec3<f32>) -> Option<vec3<f32>> {
-        let d1 = self.distance(p1);
-        let d2 = self.distance(p2);
-
-        if (d1 - d2).abs() < 1e-5 {
-            // Parallel
-            return None;
-        }
-
-        let t = d1 / (d1 - d2);
-        (0.0..=1.0).contains(&t).then_some(p1 + t * (p2 - p1))
+    /// Signed distances to a batch of points given as separate coordinate arrays,
+    /// laid out so that the compiler evaluates several lanes per instruction.
+    pub fn distances(
+        &self,
+        xs: &[f32; LANES],
+        ys: &[f32; LANES],
+        zs: &[f32; LANES],
+    ) -> [f32; LANES] {
+        let normal = self.normal.normalize_or_zero();
+        std::array::from_fn(|i| {
+            normal.x * xs[i] + normal.y * ys[i] + normal.z * zs[i] - self.offset
+        })
     }
 
-    pub fn intersect_triangle(&self, triangle: &Triangle) -> Option<(vec3<f32>, vec3<f32>)> {
-        let [a, b, c] = triangle.vertices;
-        let points: Vec<vec3<f32>> = [(a, b), (a, c), (b, c)]
-            .into_iter()
-            .flat_map(|(p1, p2)| self.intersect_segment(p1, p2))
-            .collect();
-        match &points[..] {
-            [a, b] => Some((*a, *b)),
-            _ => None,
+    /// The same plane in the local space of the given (affine) transformation,
+    /// so that `local.distance(p)` has the same sign as `self.distance(matrix * p)`.
+    pub fn to_local(&self, matrix: mat4<f32>) -> Self {
+        let normal = self.normal.normalize_or_zero();
+        let coeffs = matrix.transpose() * normal.extend(-self.offset);
+        let normal = coeffs.xyz();
+        let len = normal.len();
+        Self {
+            normal,
+            offset: -coeffs.w / len,
         }
     }
 
-    /// Calculate a cross section of `geometry` with the plane.
-    pub fn cross_sect(
+    /// Append the intersection points of the plane with the triangles, without duplicates.
+    pub fn intersect_into(
         &self,
         geometry: impl IntoIterator<Item = impl std::borrow::Borrow<Triangle>>,
-    ) -> Vec<CrossSectionVertex> {
-        let mut points: Vec<CrossSectionVertex> = Vec::new();
-        for triangle in geometry {
-            if let Some((a, b)) = self.intersect_triangle(triangle.borrow()) {
-                for p in [a, b] {
-                    let mut found = false;
-                    for q in &points {
-                        if (q.world_pos - p).len_sqr() < 1e-5 {
-                            found = true;
-                            break;
-                        }
-                    }
-                    if !found {
-                        points.push(CrossSectionVertex {
-                            world_pos: p,
-                            projected: self.project2d(p),
-                        });
-                    }
+        points: &mut Vec<vec3<f32>>,
+    ) {
+        let mut push = |p: vec3<f32>| {
+            if !points.iter().any(|q| (*q - p).len_sqr() < 1e-5) {
+                points.push(p);
+            }
+        };
+
+        // Evaluate the distances of the vertices of several triangles at a time
+        let mut batch = [Triangle::new(vec3::ZERO, vec3::ZERO, vec3::ZERO); LANES];
+        let mut coords = [[[0.0; LANES]; 3]; 3];
+        let mut len = 0;
+        let mut flush = |batch: &[Triangle], coords: &[[[f32; LANES]; 3]; 3]| {
+            let distances = (*coords).map(|[xs, ys, zs]| self.distances(&xs, &ys, &zs));
+            for (i, triangle) in batch.iter().enumerate() {
+                let distances = [distances[0][i], distances[1][i], distances[2][i]];
+                if let Some((a, b)) = intersect_triangle(triangle, distances) {
+                    push(a);
+                    push(b);
                 }
             }
+        };
+        for triangle in geometry {
+            let triangle = *triangle.borrow();
+            for (vertex, [xs, ys, zs]) in triangle.vertices.into_iter().zip(&mut coords) {
+                xs[len] = vertex.x;
+                ys[len] = vertex.y;
+                zs[len] = vertex.z;
+            }
+            batch[len] = triangle;
+            len += 1;
+            if len == LANES {
+                flush(&batch, &coords);
+                len = 0;
+            }
         }
+        flush(&batch[..len], &coords);
+    }
+
+    /// Project the points lying on the plane and append them sorted into a loop.
+    pub fn section_loop_into(&self, points: &[vec3<f32>], result: &mut Vec<vec2<f32>>) {
+        let start = result.len();
+        result.extend(points.iter().map(|&p| self.project2d(p)));
+        let projected = &mut result[start..];
 
-        if !points.is_empty() {
+        if !projected.is_empty() {
             // Sort counter clockwise
-            let com = points
-                .iter()
-                .map(|p| p.projected)
-                .fold(vec2::ZERO, vec2::add)
-                / points.len() as f32;
-            points.sort_by_key(|p| -r32((p.projected - com).arg().as_radians()));
+            let com =
+                projected.iter().copied().fold(vec2::ZERO, vec2::add) / projected.len() as f32;
+            // Unstable sort does not allocate
+            projected.sort_unstable_by_key(|p| -r32((*p - com).arg().as_radians()));
+        }
+    }
+}
+
+/// Split the triangles into the parts in front of the plane and behind it,
+/// cutting the ones that cross it in two.
+/// The cut is left open, which does not change the cross sections of a convex mesh,
+/// as those are sorted into closed loops anyway.
+pub fn cut(triangles: &[Triangle], plane: &Plane) -> (Vec<Triangle>, Vec<Triangle>) {
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    for triangle in triangles {
+        let distances = triangle.vertices.map(|v| plane.distance(v));
+        let sides = distances.map(|d| d > 0.0);
+        // The vertex alone on its side, if the triangle crosses the plane
+        let Some(lone) =
+            (0..3).find(|&i| sides[i] != sides[(i + 1) % 3] && sides[i] != sides[(i + 2) % 3])
+        else {
+            if sides[0] {
+                front.push(*triangle);
+            } else {
+                back.push(*triangle);
+            }
+            continue;
+        };
+        // Keep the winding by starting from the lone vertex
+        let [i, j, k] = [lone, (lone + 1) % 3, (lone + 2) % 3];
+        let [a, b, c] = [i, j, k].map(|i| triangle.vertices[i]);
+        let [da, db, dc] = [i, j, k].map(|i| distances[i]);
+        let p = a + (b - a) * (da / (da - db));
+        let q = a + (c - a) * (da / (da - dc));
+        let (lone_side, other_side) = if sides[lone] {
+            (&mut front, &mut back)
+        } else {
+            (&mut back, &mut front)
+        };
+        lone_side.push(Triangle::new(a, p, q));
+        other_side.push(Triangle::new(p, b, c));
+        other_side.push(Triangle::new(p, c, q));
+    }
+    (front, back)
+}
+
+/// Number of points processed together by [Plane::distances].
+pub const LANES: usize = 8;
+
+/// Intersect the segment with the plane, given the signed distances of its ends.
+fn intersect_segment(p1: vec3<f32>, p2: vec3<f32>, d1: f32, d2: f32) -> Option<vec3<f32>> {
+    if (d1 - d2).abs() < 1e-5 {
+        // Parallel
+        return None;
+    }
+
+    let t = d1 / (d1 - d2);
+    (0.0..=1.0).contains(&t).then_some(p1 + t * (p2 - p1))
+}
+
+/// Intersect the triangle with the plane, given the signed distances of its vertices.
+fn intersect_triangle(triangle: &Triangle, distances: [f32; 3]) -> Option<(vec3<f32>, vec3<f32>)> {
+    let [a, b, c] = triangle.vertices;
+    let [da, db, dc] = distances;
+    let mut points = [(a, b, da, db), (a, c, da, dc), (b, c, db, dc)]
+        .into_iter()
+        .flat_map(|(p1, p2, d1, d2)| intersect_segment(p1, p2, d1, d2));
+    match (points.next(), points.next(), points.next()) {
+        (Some(a), Some(b), None) => Some((a, b)),
+        _ => None,
+    }
+}
+
+/// Unit quaternion describing the orientation of an object.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rotation {
+    pub w: f32,
+    pub v: vec3<f32>,
+}
+
+impl Rotation {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        v: vec3(0.0, 0.0, 0.0),
+    };
+
+    /// Rotation by the length of the vector in radians around its direction.
+    pub fn from_scaled_axis(axis: vec3<f32>) -> Self {
+        let angle = axis.len();
+        if angle < 1e-6 {
+            return Self::IDENTITY;
+        }
+        let (sin, cos) = (angle / 2.0).sin_cos();
+        Self {
+            w: cos,
+            v: axis / angle * sin,
+        }
+    }
+
+    pub fn normalize(self) -> Self {
+        let len = (self.w * self.w + self.v.len_sqr()).sqrt();
+        if len < 1e-6 {
+            return Self::IDENTITY;
+        }
+        Self {
+            w: self.w / len,
+            v: self.v / len,
+        }
+    }
+
+    /// Blend along the shorter arc, good enough for the small angles between the steps.
+    pub fn nlerp(self, other: Self, t: f32) -> Self {
+        let other = if self.w * other.w + vec3::dot(self.v, other.v) < 0.0 {
+            Self {
+                w: -other.w,
+                v: -other.v,
+            }
+        } else {
+            other
+        };
+        Self {
+            w: self.w + (other.w - self.w) * t,
+            v: self.v + (other.v - self.v) * t,
         }
+        .normalize()
+    }
 
-        points
+    pub fn matrix(self) -> mat4<f32> {
+        let Self {
+            w,
+            v: vec3(x, y, z),
+        } = self;
+        mat4::new([
+            [
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y - w * z),
+                2.0 * (x * z + w * y),
+                0.0,
+            ],
+            [
+                2.0 * (x * y + w * z),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z - w * x),
+                0.0,
+            ],
+            [
+                2.0 * (x * z - w * y),
+                2.0 * (y * z + w * x),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl std::ops::Mul for Rotation {
+    type Output = Self;
+
+    /// Apply `rhs` first, then `self`.
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            w: self.w * rhs.w - vec3::dot(self.v, rhs.v),
+            v: rhs.v * self.w + self.v * rhs.w + vec3::cross(self.v, rhs.v),
+        }
+    }
+}
+
+/// Check whether the point is inside the polygon (using the even-odd rule).
+pub fn point_in_polygon(point: vec2<f32>, polygon: &[vec2<f32>]) -> bool {
+    let mut inside = false;
+    for (i, &a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y) {
+            let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[test]
+fn test_point_in_polygon() {
+    let square = [
+        vec2(-1.0, -1.0),
+        vec2(1.0, -1.0),
+        vec2(1.0, 1.0),
+        vec2(-1.0, 1.0),
+    ];
+    assert!(point_in_polygon(vec2(0.0, 0.0), &square));
+    assert!(point_in_polygon(vec2(0.9, -0.5), &square));
+    assert!(!point_in_polygon(vec2(1.5, 0.0), &square));
+    assert!(!point_in_polygon(vec2(0.0, -2.0), &square));
+    assert!(!point_in_polygon(vec2(0.0, 0.0), &[]));
+}
+
+#[test]
+fn test_baked_prefabs() {
+    for (baked, generated) in [
+        (&CUBE[..], unit_cube_triangulated()),
+        (&OCTAHEDRON[..], unit_octahedron_triangulated()),
+    ] {
+        assert_eq!(baked.len(), generated.len());
+        for (a, b) in baked.iter().zip(&generated) {
+            assert_eq!(a.vertices, b.vertices);
+        }
+    }
+}
+
+#[test]
+fn test_simplify() {
+    // Fine grid keeps the shape
+    assert_eq!(simplify(&CUBE, 0.1).len(), CUBE.len());
+    // Coarse grid collapses everything
+    assert!(simplify(&CUBE, 100.0).is_empty());
+
+    // A cube already has as few triangles as possible
+    let lods = Lods::generate(Mesh::Static(&CUBE), 2);
+    assert_eq!(lods.select(1.0, Some(100.0)).len(), CUBE.len());
+}
+
+#[test]
+fn test_intersect_batched() {
+    let plane = Plane {
+        normal: vec3(0.3, 1.0, -0.5),
+        offset: 0.2,
+    };
+    // More triangles than fit in a single batch
+    let geometry: Vec<Triangle> = unit_cube_triangulated()
+        .into_iter()
+        .chain(unit_octahedron_triangulated())
+        .collect();
+
+    let mut expected: Vec<vec3<f32>> = Vec::new();
+    for triangle in &geometry {
+        let distances = triangle.vertices.map(|p| plane.distance(p));
+        if let Some((a, b)) = intersect_triangle(triangle, distances) {
+            for p in [a, b] {
+                if !expected.iter().any(|q| (*q - p).len_sqr() < 1e-5) {
+                    expected.push(p);
+                }
+            }
+        }
+    }
+    assert!(!expected.is_empty());
+
+    let mut points = Vec::new();
+    plane.intersect_into(&geometry, &mut points);
+    assert_eq!(points.len(), expected.len());
+    for (p, q) in points.iter().zip(&expected) {
+        assert!((*p - *q).len() < 1e-5);
+    }
+}
+
+#[test]
+fn test_plane_to_local() {
+    let plane = Plane {
+        normal: vec3(0.0, 0.0, 2.0),
+        offset: 1.0,
+    };
+    let matrix = mat4::translate(vec3(0.0, 0.0, 3.0))
+        * mat4::rotate_x(Angle::from_degrees(90.0))
+        * mat4::scale_uniform(2.0);
+    let local = plane.to_local(matrix);
+    for p in [
+        vec3(0.0, 0.0, 0.0),
+        vec3(1.0, -1.0, 0.5),
+        vec3(0.3, 2.0, -1.0),
+    ] {
+        let world = (matrix * p.extend(1.0)).into_3d();
+        // Distances are scaled along with the space
+        assert!((local.distance(p) * 2.0 - plane.distance(world)).abs() < 1e-4);
     }
 }
 
@@ -233,3 +744,71 @@ fn test_plane_project() {
         check!(plane.project2d(vec3(1.0, 1.0, 2.0)), vec2(2.0, 0.0));
     }
 }
+
+#[test]
+fn test_cut() {
+    let plane = Plane {
+        normal: vec3(1.0, 0.0, 0.0),
+        offset: 0.25,
+    };
+    let (front, back) = cut(&unit_cube_triangulated(), &plane);
+    let xs = |triangles: &[Triangle]| {
+        triangles
+            .iter()
+            .flat_map(|triangle| triangle.vertices)
+            .map(|v| v.x)
+            .collect::<Vec<_>>()
+    };
+    assert!(xs(&front).iter().all(|&x| x >= 0.25 - 1e-5));
+    assert!(xs(&back).iter().all(|&x| x <= 0.25 + 1e-5));
+
+    // Both halves have the same cross section as the whole cube where they overlap
+    let slice = Plane {
+        normal: vec3(0.0, 0.0, 1.0),
+        offset: 0.0,
+    };
+    let mut points = Vec::new();
+    slice.intersect_into(&front, &mut points);
+    assert_eq!(points.len(), 4);
+}
+
+#[test]
+fn test_ray_distance() {
+    let cube = unit_cube_triangulated();
+    let distance = ray_distance(&cube, vec3(1.0, 0.0, 0.0)).unwrap();
+    assert!((distance - 1.0).abs() < 1e-5);
+    let distance = ray_distance(&cube, vec3(1.0, 1.0, 1.0)).unwrap();
+    assert!((distance - 3.0f32.sqrt()).abs() < 1e-4);
+
+    let subdivided = subdivide(&cube);
+    assert_eq!(subdivided.len(), cube.len() * 4);
+    let distance = ray_distance(&subdivided, vec3(0.0, -1.0, 0.0)).unwrap();
+    assert!((distance - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn test_baked_spheres() {
+    for (subdivisions, baked) in SPHERES.iter().enumerate() {
+        let generated = sphere(subdivisions as u32);
+        assert_eq!(baked.len(), generated.len());
+        for (a, b) in baked.iter().zip(&generated) {
+            for (a, b) in a.vertices.iter().zip(&b.vertices) {
+                assert!((*a - *b).len() < 1e-6);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_rotation() {
+    let rotation = Rotation::from_scaled_axis(vec3(0.0, std::f32::consts::FRAC_PI_2, 0.0));
+    let expected = mat4::rotate_y(Angle::from_degrees(90.0));
+    let point = vec3(1.0, 2.0, 3.0).extend(1.0);
+    assert!(((rotation.matrix() * point) - (expected * point)).len() < 1e-5);
+
+    // Two quarter turns make a half turn
+    let half = (rotation * rotation).matrix() * point;
+    assert!((half.xyz() - vec3(-1.0, 2.0, -3.0)).len() < 1e-5);
+    let blended = Rotation::IDENTITY.nlerp(rotation * rotation, 0.5).matrix() * point;
+    assert!((blended.xyz() - (expected * point).xyz()).len() < 1e-5);
+}
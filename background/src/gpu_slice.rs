@@ -0,0 +1,130 @@
+use crate::geometry::{Mesh, Plane};
+
+use geng::prelude::*;
+
+use std::collections::HashMap;
+
+/// Every triangle of a prefab, repeated for each corner of the quad it expands into.
+#[derive(ugli::Vertex, Debug, Clone, Copy)]
+struct SliceVertex {
+    a_a: vec3<f32>,
+    a_b: vec3<f32>,
+    a_c: vec3<f32>,
+    /// Position along the segment (`0` or `1`) and across it (`-1` or `1`).
+    a_corner: vec2<f32>,
+}
+
+#[derive(ugli::Vertex, Debug, Clone, Copy)]
+struct SliceInstance {
+    i_model_matrix: mat4<f32>,
+    i_color: Rgba<f32>,
+}
+
+/// Experimental renderer that slices the objects in the vertex shader:
+/// each triangle is expanded into a quad along its intersection with the plane,
+/// or collapsed if it does not cross the plane.
+/// The meshes are uploaded once, so the CPU only uploads the object transforms.
+///
+/// Only the outlines are drawn, since filling a section needs its whole loop.
+pub struct GpuSlicer {
+    program: ugli::Program,
+    /// Uploaded meshes by the address and the length of their triangles.
+    meshes: HashMap<(usize, usize), UploadedMesh>,
+}
+
+struct UploadedMesh {
+    /// Kept so that the address is not reused by another mesh while uploaded.
+    _mesh: Mesh,
+    vertices: ugli::VertexBuffer<SliceVertex>,
+    instances: ugli::VertexBuffer<SliceInstance>,
+}
+
+impl GpuSlicer {
+    pub fn new(geng: &Geng) -> Self {
+        Self {
+            program: geng
+                .shader_lib()
+                .compile(include_str!("slice.glsl"))
+                .expect("failed to compile the slicing shader"),
+            meshes: HashMap::new(),
+        }
+    }
+
+    /// Remove the instances of the last frame,
+    /// along with the meshes that had none, like those of the shards or the reloaded prefabs.
+    pub fn clear(&mut self) {
+        self.meshes.retain(|_, mesh| !mesh.instances.is_empty());
+        for mesh in self.meshes.values_mut() {
+            mesh.instances.clear();
+        }
+    }
+
+    pub fn add(&mut self, ugli: &Ugli, mesh: &Mesh, matrix: mat4<f32>, color: Rgba<f32>) {
+        let key = (mesh.as_ptr() as usize, mesh.len());
+        let uploaded = self.meshes.entry(key).or_insert_with(|| {
+            let vertices = mesh
+                .iter()
+                .flat_map(|triangle| {
+                    let [a_a, a_b, a_c] = triangle.vertices;
+                    [
+                        (0.0, -1.0),
+                        (1.0, -1.0),
+                        (1.0, 1.0),
+                        (0.0, -1.0),
+                        (1.0, 1.0),
+                        (0.0, 1.0),
+                    ]
+                    .map(|(x, y)| SliceVertex {
+                        a_a,
+                        a_b,
+                        a_c,
+                        a_corner: vec2(x, y),
+                    })
+                })
+                .collect();
+            UploadedMesh {
+                _mesh: mesh.clone(),
+                vertices: ugli::VertexBuffer::new_static(ugli, vertices),
+                instances: ugli::VertexBuffer::new_dynamic(ugli, Vec::new()),
+            }
+        });
+        uploaded.instances.push(SliceInstance {
+            i_model_matrix: matrix,
+            i_color: color,
+        });
+    }
+
+    pub fn draw(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        camera: &impl geng::AbstractCamera2d,
+        plane: &Plane,
+        width: f32,
+    ) {
+        let normal = plane.normal.normalize_or_zero();
+        let uniforms = (
+            ugli::uniforms! {
+                u_plane: normal.extend(plane.offset),
+                u_plane_matrix: plane.matrix(),
+                u_width: width,
+            },
+            geng::camera2d_uniforms(camera, framebuffer.size().map(|x| x as f32)),
+        );
+        for mesh in self.meshes.values() {
+            if mesh.instances.is_empty() {
+                continue;
+            }
+            ugli::draw(
+                framebuffer,
+                &self.program,
+                ugli::DrawMode::Triangles,
+                ugli::instanced(&mesh.vertices, &mesh.instances),
+                &uniforms,
+                ugli::DrawParameters {
+                    blend_mode: Some(ugli::BlendMode::straight_alpha()),
+                    ..default()
+                },
+            );
+        }
+    }
+}
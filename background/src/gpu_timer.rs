@@ -0,0 +1,159 @@
+#[cfg(target_arch = "wasm32")]
+use std::collections::VecDeque;
+
+/// Parts of a frame measured on the GPU by the [GpuTimer].
+#[derive(Debug, Clone, Copy)]
+pub enum GpuPass {
+    /// Filling and outlining the sections.
+    Sections,
+    /// The effects drawn on top of the sections.
+    Effects,
+    /// Upscaling the image rendered at a lower resolution.
+    Upscale,
+}
+
+impl GpuPass {
+    pub const ALL: [Self; 3] = [Self::Sections, Self::Effects, Self::Upscale];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sections => "sections",
+            Self::Effects => "effects",
+            Self::Upscale => "upscale",
+        }
+    }
+}
+
+/// Measures the GPU time of the passes with timer queries.
+/// Only supported on the web with `EXT_disjoint_timer_query` on the WebGL context of the engine,
+/// elsewhere nothing is measured.
+/// The results arrive a few frames late and are collected with [GpuTimer::poll].
+pub struct GpuTimer {
+    #[cfg(target_arch = "wasm32")]
+    gl: Option<(
+        web_sys::WebGlRenderingContext,
+        web_sys::ExtDisjointTimerQuery,
+    )>,
+    #[cfg(target_arch = "wasm32")]
+    active: Option<(GpuPass, web_sys::WebGlTimerQueryExt)>,
+    #[cfg(target_arch = "wasm32")]
+    in_flight: VecDeque<(GpuPass, web_sys::WebGlTimerQueryExt)>,
+    /// Finished queries to reuse.
+    #[cfg(target_arch = "wasm32")]
+    free: Vec<web_sys::WebGlTimerQueryExt>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl GpuTimer {
+    /// Passes are not measured while this many results are still pending.
+    const MAX_IN_FLIGHT: usize = 16;
+
+    pub fn new() -> Self {
+        let gl = Self::context();
+        if gl.is_none() {
+            log::info!("GPU timer queries are not supported");
+        }
+        Self {
+            gl,
+            active: None,
+            in_flight: VecDeque::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// The WebGL context created by the engine on its canvas, if it supports the timer queries.
+    /// Asking the canvas for the same context type returns the existing context.
+    fn context() -> Option<(
+        web_sys::WebGlRenderingContext,
+        web_sys::ExtDisjointTimerQuery,
+    )> {
+        use wasm_bindgen::JsCast;
+
+        let canvas = web_sys::window()?
+            .document()?
+            .get_element_by_id(crate::web::CANVAS_ID)?
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .ok()?;
+        let gl = canvas
+            .get_context("webgl")
+            .ok()??
+            .dyn_into::<web_sys::WebGlRenderingContext>()
+            .ok()?;
+        let ext = gl
+            .get_extension("EXT_disjoint_timer_query")
+            .ok()??
+            .unchecked_into::<web_sys::ExtDisjointTimerQuery>();
+        Some((gl, ext))
+    }
+
+    /// Start measuring a pass, finished with [Self::end].
+    pub fn begin(&mut self, pass: GpuPass) {
+        let Some((_, ext)) = &self.gl else {
+            return;
+        };
+        if self.active.is_some() || self.in_flight.len() >= Self::MAX_IN_FLIGHT {
+            return;
+        }
+        let Some(query) = self.free.pop().or_else(|| ext.create_query_ext()) else {
+            return;
+        };
+        ext.begin_query_ext(web_sys::ExtDisjointTimerQuery::TIME_ELAPSED_EXT, &query);
+        self.active = Some((pass, query));
+    }
+
+    pub fn end(&mut self) {
+        let (Some((_, ext)), Some(active)) = (&self.gl, self.active.take()) else {
+            return;
+        };
+        ext.end_query_ext(web_sys::ExtDisjointTimerQuery::TIME_ELAPSED_EXT);
+        self.in_flight.push_back(active);
+    }
+
+    /// Report the finished measurements in milliseconds.
+    pub fn poll(&mut self, mut report: impl FnMut(GpuPass, f32)) {
+        let Some((gl, ext)) = &self.gl else {
+            return;
+        };
+        // The results are meaningless if the GPU was interrupted, e.g. by a power state change
+        let disjoint = gl
+            .get_parameter(web_sys::ExtDisjointTimerQuery::GPU_DISJOINT_EXT)
+            .ok()
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        while let Some((_, query)) = self.in_flight.front() {
+            let available = ext
+                .get_query_object_ext(
+                    query,
+                    web_sys::ExtDisjointTimerQuery::QUERY_RESULT_AVAILABLE_EXT,
+                )
+                .as_bool()
+                .unwrap_or(false);
+            if !available && !disjoint {
+                break;
+            }
+            let (pass, query) = self.in_flight.pop_front().unwrap();
+            if !disjoint {
+                let result = ext
+                    .get_query_object_ext(&query, web_sys::ExtDisjointTimerQuery::QUERY_RESULT_EXT)
+                    .as_f64();
+                if let Some(nanos) = result {
+                    report(pass, (nanos / 1e6) as f32);
+                }
+            }
+            self.free.push(query);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl GpuTimer {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    pub fn begin(&mut self, _pass: GpuPass) {}
+
+    pub fn end(&mut self) {}
+
+    pub fn poll(&mut self, _report: impl FnMut(GpuPass, f32)) {}
+}
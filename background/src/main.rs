@@ -1,7 +1,35 @@
+mod audio;
+mod batch;
+#[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+mod bench;
+mod config;
+mod effects;
+mod frame_limiter;
 mod geometry;
+#[cfg(feature = "gpu-slicing")]
+mod gpu_slice;
+mod gpu_timer;
+mod noise;
+mod palette;
+mod prefab;
+mod preferences;
+mod profiler;
+#[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+mod replay;
+mod sequence;
+#[cfg(feature = "debug")]
+mod snapshot;
+mod spatial;
 mod state;
+mod web;
+#[cfg(not(target_arch = "wasm32"))]
+mod worker;
 
-use self::state::State;
+use self::{
+    config::{Config, ConfigOverrides, ConfigReloader},
+    preferences::Preferences,
+    state::State,
+};
 
 use geng::prelude::*;
 
@@ -9,18 +37,44 @@ use geng::prelude::*;
 struct Opts {
     #[clap(flatten)]
     window: geng::CliArgs,
-}
-
-#[derive(geng::asset::Load)]
-pub struct Assets {
-    pub config: Config,
-}
-
-#[derive(geng::asset::Load, Deserialize)]
-#[load(serde = "ron")]
-pub struct Config {
-    background_color: Rgba<f32>,
-    object_colors: Vec<Rgba<f32>>,
+    /// Path to the config file (RON, JSON, or TOML).
+    #[clap(long, default_value = "assets/config.ron")]
+    config: std::path::PathBuf,
+    #[clap(flatten)]
+    overrides: ConfigOverrides,
+    /// Print the default config with all options to stdout and exit.
+    #[clap(long)]
+    print_default_config: bool,
+    /// Run this many simulation steps with slicing but without a window,
+    /// print the timings, and exit.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    #[clap(long, value_name = "N")]
+    bench_sim: Option<usize>,
+    /// Record the run into this file, to play it back with `--replay`.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    #[clap(long, value_name = "PATH")]
+    record_replay: Option<std::path::PathBuf>,
+    /// Play back a run recorded with `--record-replay`, then continue live.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    #[clap(long, value_name = "PATH", conflicts_with = "record_replay")]
+    replay: Option<std::path::PathBuf>,
+    /// Start from a scene saved with the debug shortcut instead of an empty one.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    #[clap(long, value_name = "PATH", conflicts_with_all = ["record_replay", "replay"])]
+    snapshot: Option<std::path::PathBuf>,
+    /// Reload the config whenever the file changes, on the web with the `?hot_reload` url query.
+    #[clap(long)]
+    hot_reload: bool,
+    /// Disable vertical synchronization, so that only `--fps-limit` limits the frame rate.
+    #[clap(long)]
+    no_vsync: bool,
+    /// Enable the debug keyboard shortcuts.
+    #[cfg(feature = "debug")]
+    #[clap(long)]
+    debug: bool,
+    /// Override the color scheme preferred by the browser.
+    #[clap(long)]
+    color_scheme: Option<config::ColorScheme>,
 }
 
 fn main() {
@@ -28,13 +82,117 @@ fn main() {
 
     let opts: Opts = clap::Parser::parse();
 
+    if opts.print_default_config {
+        let pretty = ron::ser::PrettyConfig::default();
+        let config = ron::ser::to_string_pretty(&Config::example(), pretty)
+            .expect("failed to serialize the config");
+        println!("{config}");
+        return;
+    }
+
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    if let Some(frames) = opts.bench_sim {
+        let config_path = run_dir().join(&opts.config);
+        let mut config =
+            futures::executor::block_on(Config::load(&config_path)).unwrap_or_else(|err| {
+                log::error!("Failed to load config: {:?}", err);
+                Config::embedded()
+            });
+        opts.overrides.apply(&mut config);
+        let reloader = ConfigReloader::new(&config_path, opts.overrides, false);
+        bench::run(config, reloader, frames);
+        return;
+    }
+
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    let replay = match &opts.replay {
+        Some(path) => match replay::Replay::load(path) {
+            Ok(replay) => Some(replay),
+            Err(err) => {
+                log::error!("Failed to load the replay: {:?}", err);
+                return;
+            }
+        },
+        None => None,
+    };
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    let snapshot = match &opts.snapshot {
+        Some(path) => match snapshot::SceneSnapshot::load(path) {
+            Ok(snapshot) => Some(snapshot),
+            Err(err) => {
+                log::error!("Failed to load the snapshot: {:?}", err);
+                return;
+            }
+        },
+        None => None,
+    };
+
     let mut context = geng::ContextOptions::default();
     context.with_cli(&opts.window);
-    Geng::run_with(&context, |geng| async move {
-        let manager = geng.asset_manager();
-        let assets: Rc<Assets> = geng::asset::Load::load(manager, &run_dir().join("assets"), &())
-            .await
-            .expect("failed to load assets");
-        geng.run_state(State::new(geng.clone(), assets)).await
+    if opts.no_vsync {
+        context.window.vsync = false;
+    }
+    let config_path = run_dir().join(&opts.config);
+    let mut overrides = opts.overrides;
+    web::apply_query_overrides(&mut overrides);
+    web::listen_device_orientation();
+    web::listen_messages();
+    web::listen_theme_change();
+    web::listen_visibility();
+    web::define_custom_element();
+    let hot_reload = opts.hot_reload || web::query_hot_reload();
+    let color_scheme = opts.color_scheme;
+    #[cfg(feature = "debug")]
+    let debug = opts.debug;
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    let record_replay = opts.record_replay;
+    Geng::run_with(&context, move |geng| async move {
+        web::attach_canvas();
+        #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+        let tool_state = if let Some(replay) = replay {
+            let reloader = ConfigReloader::new(&config_path, overrides.clone(), false);
+            Some(State::replay(Some(geng.clone()), replay, reloader))
+        } else if let Some(path) = record_replay {
+            // The whole config up front, as it is saved with the recording
+            let mut config = Config::load(&config_path).await.unwrap_or_else(|err| {
+                log::error!("Failed to load config: {:?}", err);
+                Config::embedded()
+            });
+            overrides.apply(&mut config);
+            if color_scheme.is_some() {
+                config.color_scheme = color_scheme;
+            }
+            let reloader = ConfigReloader::new(&config_path, overrides.clone(), false);
+            Some(State::record(Some(geng.clone()), config, reloader, path))
+        } else {
+            None
+        };
+        #[cfg(not(all(feature = "debug", not(target_arch = "wasm32"))))]
+        let tool_state = None;
+
+        let mut state = if let Some(state) = tool_state {
+            state
+        } else {
+            // Start with the embedded config and load the external one in the background
+            let mut config = Config::embedded();
+            overrides.apply(&mut config);
+            let mut reloader = ConfigReloader::new(&config_path, overrides, hot_reload);
+            reloader.request();
+            let mut state = State::new(geng.clone(), config, reloader);
+            // The rest of the prefabs are streamed in after the first frame
+            state.prepare_prefabs();
+            state.set_preferences(Preferences::load());
+            #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+            if let Some(snapshot) = &snapshot {
+                state.restore(snapshot);
+            }
+            if color_scheme.is_some() {
+                state.set_color_scheme(color_scheme);
+            }
+            state
+        };
+        #[cfg(feature = "debug")]
+        state.set_debug(debug);
+        geng.run_state(state).await
     })
 }
@@ -0,0 +1,101 @@
+use geng::prelude::*;
+
+/// Smooth pseudo-random gradient noise, roughly in the range from -1 to 1.
+/// It is zero at the integer coordinates.
+pub fn gradient_noise(p: vec3<f32>) -> f32 {
+    let cell = p.map(|x| x.floor());
+    let local = p - cell;
+    let cell = cell.map(|x| x as i32);
+    let fade = local.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let offset = vec3(dx, dy, dz);
+        let gradient = gradient(hash(cell + offset));
+        vec3::dot(gradient, local - offset.map(|x| x as f32))
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fade.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fade.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fade.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fade.x);
+    lerp(lerp(x00, x10, fade.y), lerp(x01, x11, fade.y), fade.z)
+}
+
+/// Three unrelated noises, one per component.
+pub fn vector_noise(p: vec3<f32>) -> vec3<f32> {
+    let [x, y, z] = vector_noise_components(p);
+    vec3(x, y, z)
+}
+
+fn vector_noise_components(p: vec3<f32>) -> [f32; 3] {
+    // Far enough apart to be unrelated
+    const OFFSETS: [vec3<f32>; 3] = [
+        vec3(0.0, 0.0, 0.0),
+        vec3(31.4, -47.2, 12.9),
+        vec3(-83.1, 19.7, 56.3),
+    ];
+    OFFSETS.map(|offset| gradient_noise(p + offset))
+}
+
+/// Divergence-free flow: the curl of a [vector_noise].
+/// Its components are mostly within 1 or 2 from zero.
+pub fn curl_noise(p: vec3<f32>) -> vec3<f32> {
+    const EPSILON: f32 = 1e-3;
+    let derivative = |axis: vec3<f32>| {
+        let [a, b] = [p + axis * EPSILON, p - axis * EPSILON].map(vector_noise_components);
+        [0, 1, 2].map(|i| (a[i] - b[i]) / (2.0 * EPSILON))
+    };
+    let dx = derivative(vec3::UNIT_X);
+    let dy = derivative(vec3::UNIT_Y);
+    let dz = derivative(vec3::UNIT_Z);
+    vec3(dy[2] - dz[1], dz[0] - dx[2], dx[1] - dy[0])
+}
+
+fn hash(cell: vec3<i32>) -> u32 {
+    let mut h = (cell.x as u32).wrapping_mul(0x8da6b343)
+        ^ (cell.y as u32).wrapping_mul(0xd8163841)
+        ^ (cell.z as u32).wrapping_mul(0xcb1ab31f);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b3c6d);
+    h ^= h >> 12;
+    h
+}
+
+/// One of the directions to the edges of a cube.
+fn gradient(hash: u32) -> vec3<f32> {
+    const GRADIENTS: [vec3<f32>; 12] = [
+        vec3(1.0, 1.0, 0.0),
+        vec3(-1.0, 1.0, 0.0),
+        vec3(1.0, -1.0, 0.0),
+        vec3(-1.0, -1.0, 0.0),
+        vec3(1.0, 0.0, 1.0),
+        vec3(-1.0, 0.0, 1.0),
+        vec3(1.0, 0.0, -1.0),
+        vec3(-1.0, 0.0, -1.0),
+        vec3(0.0, 1.0, 1.0),
+        vec3(0.0, -1.0, 1.0),
+        vec3(0.0, 1.0, -1.0),
+        vec3(0.0, -1.0, -1.0),
+    ];
+    GRADIENTS[(hash % 12) as usize]
+}
+
+#[test]
+fn test_curl_noise() {
+    assert_eq!(gradient_noise(vec3(3.0, -2.0, 7.0)), 0.0);
+
+    // The flow neither converges nor diverges
+    let p = vec3(0.3, 1.7, -2.4);
+    let h = 1e-2;
+    let divergence = [vec3::UNIT_X, vec3::UNIT_Y, vec3::UNIT_Z]
+        .into_iter()
+        .enumerate()
+        .map(|(i, axis)| {
+            let a = curl_noise(p + axis * h);
+            let b = curl_noise(p - axis * h);
+            let component = |v: vec3<f32>| [v.x, v.y, v.z][i];
+            (component(a) - component(b)) / (2.0 * h)
+        })
+        .sum::<f32>();
+    assert!(divergence.abs() < 0.05, "divergence {divergence}");
+}
@@ -0,0 +1,282 @@
+use crate::config::deserialize_color;
+
+use geng::prelude::*;
+
+/// A palette as written in the config:
+/// either an explicit list of colors, a rule to generate them,
+/// or an image to extract them from.
+/// Generated and extracted palettes are resolved into colors when the config is loaded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Palette {
+    Colors(Vec<PaletteColor>),
+    Generated(GeneratedPalette),
+    Image(ImagePalette),
+}
+
+impl Palette {
+    pub fn from_colors(colors: impl IntoIterator<Item = Rgba<f32>>) -> Self {
+        Self::Colors(colors.into_iter().map(PaletteColor::Plain).collect())
+    }
+
+    /// The colors of a resolved palette, empty if the palette has not been resolved.
+    pub fn colors(&self) -> &[PaletteColor] {
+        match self {
+            Self::Colors(colors) => colors,
+            Self::Generated(_) | Self::Image(_) => &[],
+        }
+    }
+
+    /// Whether there are no colors that could be chosen.
+    pub fn is_empty(&self) -> bool {
+        self.colors().iter().all(|color| color.weight() <= 0.0)
+    }
+
+    /// Pick a random color according to the weights.
+    pub fn choose(&self, rng: &mut impl Rng) -> Option<Rgba<f32>> {
+        self.colors()
+            .choose_weighted(rng, |color| color.weight().max(0.0))
+            .ok()
+            .map(|color| color.color())
+    }
+
+    /// Turn the palette into an explicit list of colors.
+    /// Paths to images are relative to `base_path`.
+    pub async fn resolve(&mut self, base_path: &std::path::Path) {
+        let colors = match self {
+            Self::Colors(_) => return,
+            Self::Generated(palette) => palette.generate(),
+            Self::Image(palette) => match palette.extract(base_path).await {
+                Ok(colors) => colors,
+                Err(err) => {
+                    log::error!(
+                        "Failed to extract a palette from {:?}: {:?}",
+                        palette.image,
+                        err
+                    );
+                    Vec::new()
+                }
+            },
+        };
+        *self = Self::from_colors(colors);
+    }
+}
+
+/// A color in a palette with an optional relative weight.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PaletteColor {
+    Plain(#[serde(deserialize_with = "deserialize_color")] Rgba<f32>),
+    Weighted {
+        #[serde(deserialize_with = "deserialize_color")]
+        color: Rgba<f32>,
+        weight: f32,
+    },
+}
+
+impl PaletteColor {
+    pub fn color(self) -> Rgba<f32> {
+        match self {
+            Self::Plain(color) | Self::Weighted { color, .. } => color,
+        }
+    }
+
+    pub fn weight(self) -> f32 {
+        match self {
+            Self::Plain(_) => 1.0,
+            Self::Weighted { weight, .. } => weight,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Harmony {
+    /// Neighbouring hues.
+    Analogous,
+    /// The base hue and the opposite one.
+    Complementary,
+    /// Three hues evenly spaced around the color wheel.
+    Triadic,
+}
+
+impl Harmony {
+    /// Hue offsets from the base hue in degrees.
+    fn offsets(self) -> &'static [f32] {
+        match self {
+            Self::Analogous => &[0.0, -30.0, 30.0],
+            Self::Complementary => &[0.0, 180.0],
+            Self::Triadic => &[0.0, 120.0, 240.0],
+        }
+    }
+}
+
+/// Palette generated in HSL space from a base hue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedPalette {
+    /// Base hue in degrees.
+    pub hue: f32,
+    pub harmony: Harmony,
+    /// Number of colors to generate.
+    pub count: usize,
+    #[serde(default = "default_saturation")]
+    pub saturation: f32,
+    /// Colors are spread evenly over this lightness range.
+    #[serde(default = "default_lightness")]
+    pub lightness: (f32, f32),
+}
+
+fn default_saturation() -> f32 {
+    0.5
+}
+
+fn default_lightness() -> (f32, f32) {
+    (0.4, 0.8)
+}
+
+impl GeneratedPalette {
+    pub fn generate(&self) -> Vec<Rgba<f32>> {
+        let offsets = self.harmony.offsets();
+        let (min_light, max_light) = self.lightness;
+        (0..self.count)
+            .map(|i| {
+                let hue = self.hue + offsets[i % offsets.len()];
+                let t = if self.count > 1 {
+                    i as f32 / (self.count - 1) as f32
+                } else {
+                    0.5
+                };
+                let lightness = min_light + (max_light - min_light) * t;
+                hsl(hue, self.saturation, lightness)
+            })
+            .collect()
+    }
+}
+
+/// Convert a color from HSL, with the hue in degrees.
+pub fn hsl(hue: f32, saturation: f32, lightness: f32) -> Rgba<f32> {
+    let saturation = saturation.clamp(0.0, 1.0);
+    let lightness = lightness.clamp(0.0, 1.0);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let channel = |n: f32| {
+        let k = (n + hue.rem_euclid(360.0) / 30.0) % 12.0;
+        lightness - chroma / 2.0 * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    Rgba::opaque(channel(0.0), channel(8.0), channel(4.0))
+}
+
+/// Palette extracted from an image by clustering its pixels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImagePalette {
+    /// Path to the image, relative to the config file.
+    pub image: std::path::PathBuf,
+    /// Number of colors to extract.
+    pub count: usize,
+}
+
+impl ImagePalette {
+    pub async fn extract(&self, base_path: &std::path::Path) -> anyhow::Result<Vec<Rgba<f32>>> {
+        let bytes = file::load_bytes(base_path.join(&self.image)).await?;
+        let image = image::load_from_memory(&bytes)?.into_rgb8();
+        let pixels: Vec<vec3<f32>> = image
+            .pixels()
+            .map(|pixel| vec3(pixel[0], pixel[1], pixel[2]).map(|x| x as f32 / 255.0))
+            .collect();
+        let mut colors: Vec<Rgba<f32>> = k_means(&pixels, self.count)
+            .into_iter()
+            .map(|c| Rgba::opaque(c.x, c.y, c.z))
+            .collect();
+        // Sort from dark to light
+        colors.sort_by_key(|c| r32(0.299 * c.r + 0.587 * c.g + 0.114 * c.b));
+        Ok(colors)
+    }
+}
+
+/// Find `count` cluster centers of the points.
+fn k_means(points: &[vec3<f32>], count: usize) -> Vec<vec3<f32>> {
+    const MAX_SAMPLES: usize = 4096;
+    const ITERATIONS: usize = 10;
+
+    if points.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    // Subsample large images evenly
+    let step = (points.len() / MAX_SAMPLES).max(1);
+    let samples: Vec<vec3<f32>> = points.iter().step_by(step).copied().collect();
+
+    // Deterministic initialization, spread over the samples
+    let mut centers: Vec<vec3<f32>> = (0..count)
+        .map(|i| samples[i * samples.len() / count])
+        .collect();
+
+    for _ in 0..ITERATIONS {
+        let mut sums = vec![(vec3::ZERO, 0); count];
+        for &point in &samples {
+            let closest = centers
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, center)| r32((point - **center).len_sqr()))
+                .map(|(i, _)| i)
+                .unwrap();
+            sums[closest].0 += point;
+            sums[closest].1 += 1;
+        }
+        for (center, (sum, n)) in centers.iter_mut().zip(sums) {
+            if n > 0 {
+                *center = sum / n as f32;
+            }
+        }
+    }
+
+    centers
+}
+
+#[test]
+fn test_k_means() {
+    let dark = vec3(0.1, 0.1, 0.2);
+    let light = vec3(0.9, 0.8, 0.7);
+    let points: Vec<vec3<f32>> = std::iter::repeat(dark)
+        .take(50)
+        .chain(std::iter::repeat(light).take(50))
+        .collect();
+    let mut centers = k_means(&points, 2);
+    centers.sort_by_key(|c| r32(c.x));
+    assert_eq!(centers.len(), 2);
+    assert!((centers[0] - dark).len() < 1e-3);
+    assert!((centers[1] - light).len() < 1e-3);
+}
+
+#[test]
+fn test_generated_palette() {
+    let close = |a: Rgba<f32>, b: Rgba<f32>| {
+        (a.r - b.r).abs() < 1e-3 && (a.g - b.g).abs() < 1e-3 && (a.b - b.b).abs() < 1e-3
+    };
+    assert!(close(hsl(0.0, 1.0, 0.5), Rgba::opaque(1.0, 0.0, 0.0)));
+
+    let palette = GeneratedPalette {
+        hue: 0.0,
+        harmony: Harmony::Complementary,
+        count: 2,
+        saturation: 1.0,
+        lightness: (0.5, 0.5),
+    };
+    let colors = palette.generate();
+    assert_eq!(colors.len(), 2);
+    assert!(close(colors[0], Rgba::opaque(1.0, 0.0, 0.0)));
+    assert!(close(colors[1], Rgba::opaque(0.0, 1.0, 1.0)));
+}
+
+#[test]
+fn test_weighted_choose() {
+    let palette = Palette::Colors(vec![
+        PaletteColor::Weighted {
+            color: Rgba::opaque(1.0, 0.0, 0.0),
+            weight: 0.0,
+        },
+        PaletteColor::Plain(Rgba::opaque(0.0, 0.0, 1.0)),
+    ]);
+    let mut rng = StdRng::seed_from_u64(0);
+    for _ in 0..100 {
+        assert_eq!(palette.choose(&mut rng), Some(Rgba::opaque(0.0, 0.0, 1.0)));
+    }
+}
@@ -0,0 +1,335 @@
+use crate::geometry::{self, Lods, Mesh, Triangle};
+
+use geng::prelude::*;
+
+use std::collections::{HashMap, HashSet};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc;
+
+/// Description of the geometry of a prefab, generated when first spawned.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Shape {
+    Cube,
+    Octahedron,
+    /// Octahedron subdivided the given number of times and rounded onto a sphere.
+    Sphere {
+        subdivisions: u32,
+    },
+    /// Regular polygon with the given number of sides, extruded along the Z axis.
+    Prism {
+        sides: u32,
+    },
+}
+
+impl Shape {
+    /// Sphere subdivisions above this would take too long to generate and slice.
+    pub const MAX_SUBDIVISIONS: u32 = 5;
+    pub const MAX_SIDES: u32 = 64;
+
+    /// Check that the shape can be generated, logging the problem if not.
+    pub fn is_valid(&self, field: &str) -> bool {
+        match *self {
+            Self::Cube | Self::Octahedron => true,
+            Self::Sphere { subdivisions } => {
+                let valid = subdivisions <= Self::MAX_SUBDIVISIONS;
+                if !valid {
+                    log::warn!(
+                        "config: `{field}` sphere can have at most {} subdivisions, got {}",
+                        Self::MAX_SUBDIVISIONS,
+                        subdivisions
+                    );
+                }
+                valid
+            }
+            Self::Prism { sides } => {
+                let valid = (3..=Self::MAX_SIDES).contains(&sides);
+                if !valid {
+                    log::warn!(
+                        "config: `{field}` prism must have from 3 to {} sides, got {}",
+                        Self::MAX_SIDES,
+                        sides
+                    );
+                }
+                valid
+            }
+        }
+    }
+
+    fn generate(&self) -> Mesh {
+        match *self {
+            Self::Cube => Mesh::Static(&geometry::CUBE),
+            Self::Octahedron => Mesh::Static(&geometry::OCTAHEDRON),
+            Self::Sphere { subdivisions } => match geometry::SPHERES.get(subdivisions as usize) {
+                Some(&triangles) => Mesh::Static(triangles),
+                None => Mesh::Shared(geometry::sphere(subdivisions).into()),
+            },
+            Self::Prism { sides } => {
+                let corners: Vec<vec2<f32>> = (0..sides)
+                    .map(|i| {
+                        let angle =
+                            Angle::from_radians(std::f32::consts::TAU * i as f32 / sides as f32);
+                        vec2(1.0, 0.0).rotate(angle)
+                    })
+                    .collect();
+                let mut triangles = Vec::with_capacity(sides as usize * 4);
+                for (i, &a) in corners.iter().enumerate() {
+                    let b = corners[(i + 1) % corners.len()];
+                    // Sides
+                    triangles.push(Triangle::new(a.extend(-1.0), b.extend(-1.0), b.extend(1.0)));
+                    triangles.push(Triangle::new(a.extend(-1.0), b.extend(1.0), a.extend(1.0)));
+                    // Caps
+                    triangles.push(Triangle::new(
+                        vec3(0.0, 0.0, 1.0),
+                        a.extend(1.0),
+                        b.extend(1.0),
+                    ));
+                    triangles.push(Triangle::new(
+                        vec3(0.0, 0.0, -1.0),
+                        b.extend(-1.0),
+                        a.extend(-1.0),
+                    ));
+                }
+                Mesh::Shared(triangles.into())
+            }
+        }
+    }
+}
+
+/// The same triangles in the shapes of two prefabs, to blend from one into the other.
+#[derive(Debug, Clone)]
+pub struct MorphMeshes {
+    pub from: Arc<[Triangle]>,
+    pub to: Arc<[Triangle]>,
+}
+
+impl MorphMeshes {
+    /// Source triangles are subdivided until there are at least this many,
+    /// so that they can follow the target shape closely enough.
+    const MIN_TRIANGLES: usize = 256;
+
+    /// Subdivide the source mesh and project its vertices from the center onto the target.
+    /// Both shapes need to be convex around the origin, as all the prefabs are.
+    fn generate(from: &[Triangle], to: &[Triangle]) -> Self {
+        let mut source = from.to_vec();
+        while !source.is_empty() && source.len() < Self::MIN_TRIANGLES {
+            source = geometry::subdivide(&source);
+        }
+        // Most vertices are shared by several triangles
+        let mut projected: HashMap<[u32; 3], vec3<f32>> = HashMap::new();
+        let mut project = |v: vec3<f32>| {
+            *projected
+                .entry([v.x, v.y, v.z].map(f32::to_bits))
+                .or_insert_with(|| {
+                    let distance = geometry::ray_distance(to, v).unwrap_or(v.len());
+                    v.normalize_or_zero() * distance
+                })
+        };
+        let target: Vec<Triangle> = source
+            .iter()
+            .map(|triangle| Triangle {
+                vertices: triangle.vertices.map(&mut project),
+            })
+            .collect();
+        Self {
+            from: source.into(),
+            to: target.into(),
+        }
+    }
+
+    /// Blend the vertices, from the source shape at 0 to the target at 1.
+    pub fn blend(&self, t: f32) -> Mesh {
+        let triangles: Vec<Triangle> = self
+            .from
+            .iter()
+            .zip(self.to.iter())
+            .map(|(a, b)| Triangle {
+                vertices: std::array::from_fn(|i| {
+                    a.vertices[i] + (b.vertices[i] - a.vertices[i]) * t
+                }),
+            })
+            .collect();
+        Mesh::Shared(triangles.into())
+    }
+}
+
+/// Generates the prefab geometry on first use and caches it.
+/// Shapes can also be prepared ahead of time: natively on separate threads,
+/// and on the web one shape per [PrefabRegistry::poll].
+pub struct PrefabRegistry {
+    lod_levels: usize,
+    meshes: HashMap<Shape, Lods>,
+    /// Shared names, so that spawning does not allocate.
+    names: HashMap<String, Rc<str>>,
+    /// Shapes that are being prepared and are not ready yet.
+    pending: HashSet<Shape>,
+    morphs: HashMap<(Shape, Shape), MorphMeshes>,
+    #[cfg(not(target_arch = "wasm32"))]
+    generated: (mpsc::Sender<(Shape, Lods)>, mpsc::Receiver<(Shape, Lods)>),
+}
+
+impl PrefabRegistry {
+    pub fn new(lod_levels: usize) -> Self {
+        Self {
+            lod_levels,
+            meshes: HashMap::new(),
+            names: HashMap::new(),
+            pending: HashSet::new(),
+            morphs: HashMap::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            generated: mpsc::channel(),
+        }
+    }
+
+    /// Whether the geometry of the shape has already been generated.
+    pub fn is_ready(&self, shape: &Shape) -> bool {
+        self.meshes.contains_key(shape)
+    }
+
+    /// Start generating the shapes that are not ready yet, so that spawning them does not stall.
+    pub fn prepare<'a>(&mut self, shapes: impl IntoIterator<Item = &'a Shape>) {
+        for shape in shapes {
+            if self.is_ready(shape) || !self.pending.insert(shape.clone()) {
+                continue;
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let shape = shape.clone();
+                let lod_levels = self.lod_levels;
+                let sender = self.generated.0.clone();
+                let spawned =
+                    std::thread::Builder::new()
+                        .name("prefab".to_owned())
+                        .spawn(move || {
+                            let lods = Lods::generate(shape.generate(), lod_levels);
+                            // The registry might be gone already
+                            let _ = sender.send((shape, lods));
+                        });
+                if let Err(err) = spawned {
+                    // Leave it to be generated on first use
+                    log::error!("Failed to spawn a prefab thread: {:?}", err);
+                    self.pending.remove(shape);
+                }
+            }
+        }
+    }
+
+    /// Collect the prepared shapes, returns the number of shapes still pending.
+    pub fn poll(&mut self) -> usize {
+        #[cfg(not(target_arch = "wasm32"))]
+        while let Ok((shape, lods)) = self.generated.1.try_recv() {
+            if self.pending.remove(&shape) {
+                self.meshes.entry(shape).or_insert(lods);
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Some(shape) = self.pending.iter().next().cloned() {
+            self.generate(&shape);
+        }
+        self.pending.len()
+    }
+
+    /// Generate the shape right away unless it is ready, waiting for it if it is being prepared.
+    pub fn wait(&mut self, shape: &Shape) {
+        #[cfg(not(target_arch = "wasm32"))]
+        while self.pending.contains(shape) {
+            let Ok((generated, lods)) = self.generated.1.recv() else {
+                break;
+            };
+            if self.pending.remove(&generated) {
+                self.meshes.entry(generated).or_insert(lods);
+            }
+        }
+        self.generate(shape);
+    }
+
+    fn generate(&mut self, shape: &Shape) -> Lods {
+        match self.meshes.get(shape) {
+            Some(lods) => lods.clone(),
+            None => {
+                log::debug!("Generating {:?}", shape);
+                self.pending.remove(shape);
+                let lods = Lods::generate(shape.generate(), self.lod_levels);
+                self.meshes.insert(shape.clone(), lods.clone());
+                lods
+            }
+        }
+    }
+
+    /// Meshes to morph between the shapes, generated on first use and cached.
+    pub fn morph(&mut self, from: &Shape, to: &Shape) -> MorphMeshes {
+        let key = (from.clone(), to.clone());
+        if let Some(meshes) = self.morphs.get(&key) {
+            return meshes.clone();
+        }
+        log::debug!("Generating a morph from {:?} to {:?}", from, to);
+        let source = self.generate(from);
+        let target = self.generate(to);
+        let meshes = MorphMeshes::generate(source.full(), target.full());
+        self.morphs.insert(key, meshes.clone());
+        meshes
+    }
+
+    pub fn get(&mut self, name: &str, shape: &Shape) -> (Rc<str>, Lods) {
+        let name = match self.names.get(name) {
+            Some(name) => name.clone(),
+            None => {
+                let shared: Rc<str> = name.into();
+                self.names.insert(name.to_owned(), shared.clone());
+                shared
+            }
+        };
+        (name, self.generate(shape))
+    }
+}
+
+#[test]
+fn test_prefab_registry() {
+    let mut registry = PrefabRegistry::new(0);
+    let shape = Shape::Sphere { subdivisions: 1 };
+    let (name, sphere) = registry.get("sphere", &shape);
+    assert_eq!(&*name, "sphere");
+    assert_eq!(sphere.full().len(), 8 * 4);
+    // Cached
+    let (other_name, other) = registry.get("sphere", &shape);
+    assert!(Rc::ptr_eq(&name, &other_name));
+    assert!(std::ptr::eq(sphere.full().as_ptr(), other.full().as_ptr()));
+
+    let (_, prism) = registry.get("prism", &Shape::Prism { sides: 6 });
+    assert_eq!(prism.full().len(), 6 * 4);
+}
+
+#[test]
+fn test_morph_meshes() {
+    let mut registry = PrefabRegistry::new(0);
+    let morph = registry.morph(&Shape::Cube, &Shape::Sphere { subdivisions: 3 });
+    assert_eq!(morph.from.len(), morph.to.len());
+    assert!(morph.from.len() >= MorphMeshes::MIN_TRIANGLES);
+    // The corners of the cube are pulled in onto the sphere
+    let corner = vec3(1.0, 1.0, 1.0);
+    let blended = morph.blend(1.0);
+    let vertex = morph
+        .from
+        .iter()
+        .zip(blended.iter())
+        .flat_map(|(a, b)| a.vertices.into_iter().zip(b.vertices))
+        .find(|(a, _)| (*a - corner).len() < 1e-5)
+        .map(|(_, b)| b)
+        .unwrap();
+    assert!((vertex.len() - 1.0).abs() < 0.05);
+}
+
+#[test]
+fn test_prefab_prepare() {
+    let mut registry = PrefabRegistry::new(1);
+    let shapes = [Shape::Cube, Shape::Sphere { subdivisions: 2 }];
+    registry.prepare(&shapes);
+    registry.wait(&shapes[1]);
+    assert!(registry.is_ready(&shapes[1]));
+    while registry.poll() > 0 {
+        std::thread::yield_now();
+    }
+    assert!(registry.is_ready(&shapes[0]));
+    // Preparing again does nothing
+    registry.prepare(&shapes);
+    assert_eq!(registry.poll(), 0);
+}
@@ -0,0 +1,38 @@
+use crate::web;
+
+use geng::prelude::*;
+
+/// Choices made by the visitor through the page, remembered between visits.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub paused: bool,
+    /// Overrides the browser preference and the config if set.
+    pub reduce_motion: Option<bool>,
+    /// Render less to save power.
+    pub low_quality: bool,
+}
+
+impl Preferences {
+    const KEY: &'static str = "nertsal-background.preferences";
+
+    /// Load the saved preferences, or the defaults if there are none.
+    pub fn load() -> Self {
+        let Some(json) = web::load_storage(Self::KEY) else {
+            return Self::default();
+        };
+        serde_json::from_str(&json).unwrap_or_else(|err| {
+            log::warn!("Failed to parse the saved preferences: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self) {
+        let result = serde_json::to_string(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| web::save_storage(Self::KEY, &json));
+        if let Err(err) = result {
+            log::warn!("Failed to save the preferences: {:?}", err);
+        }
+    }
+}
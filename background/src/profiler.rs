@@ -0,0 +1,183 @@
+use crate::gpu_timer::GpuPass;
+
+use geng::prelude::*;
+
+use std::collections::VecDeque;
+
+/// Parts of a frame measured by the [Profiler].
+#[derive(Debug, Clone, Copy)]
+pub enum Phase {
+    Update,
+    Slicing,
+    Triangulation,
+    /// The whole draw, including the slicing and the triangulation.
+    Draw,
+}
+
+impl Phase {
+    const ALL: [Self; 4] = [Self::Update, Self::Slicing, Self::Triangulation, Self::Draw];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Update => "update",
+            Self::Slicing => "slicing",
+            Self::Triangulation => "triangulation",
+            Self::Draw => "draw",
+        }
+    }
+}
+
+/// Averaged timings in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderStats {
+    pub frame: f32,
+    /// By the phase name.
+    pub cpu: BTreeMap<&'static str, f32>,
+    /// By the pass name, only the passes measured so far.
+    pub gpu: BTreeMap<&'static str, f32>,
+}
+
+/// Rolling averages of the CPU time spent in each phase, and a history of frame times.
+pub struct Profiler {
+    /// Averages in milliseconds, indexed by the phase.
+    averages: [f32; 4],
+    /// Averages of the GPU time in milliseconds, indexed by the pass,
+    /// `None` until the pass has been measured.
+    gpu_averages: [Option<f32>; 3],
+    /// Recent frame times in milliseconds.
+    frame_times: VecDeque<f32>,
+}
+
+impl Profiler {
+    /// Weight of the latest measurement in the rolling average.
+    const SMOOTHING: f32 = 0.05;
+    const HISTORY: usize = 120;
+
+    pub fn new() -> Self {
+        Self {
+            averages: [0.0; 4],
+            gpu_averages: [None; 3],
+            frame_times: VecDeque::with_capacity(Self::HISTORY),
+        }
+    }
+
+    /// Start measuring a phase, finished with [Self::record].
+    pub fn start(&self) -> Timer {
+        Timer::new()
+    }
+
+    pub fn record(&mut self, phase: Phase, timer: Timer) {
+        let time = timer.elapsed().as_secs_f64() as f32 * 1000.0;
+        let average = &mut self.averages[phase as usize];
+        *average += (time - *average) * Self::SMOOTHING;
+    }
+
+    pub fn record_gpu(&mut self, pass: GpuPass, time: f32) {
+        let average = &mut self.gpu_averages[pass as usize];
+        *average = Some(match *average {
+            Some(average) => average + (time - average) * Self::SMOOTHING,
+            None => time,
+        });
+    }
+
+    fn frame_time(&self) -> f32 {
+        self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32
+    }
+
+    /// The current averages in milliseconds, to report to the page.
+    pub fn stats(&self) -> RenderStats {
+        RenderStats {
+            frame: self.frame_time(),
+            cpu: Phase::ALL
+                .into_iter()
+                .map(|phase| (phase.name(), self.averages[phase as usize]))
+                .collect(),
+            gpu: GpuPass::ALL
+                .into_iter()
+                .filter_map(|pass| Some((pass.name(), self.gpu_averages[pass as usize]?)))
+                .collect(),
+        }
+    }
+
+    pub fn frame(&mut self, delta_time: f32) {
+        if self.frame_times.len() == Self::HISTORY {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(delta_time * 1000.0);
+    }
+
+    /// Draw the timings and the frame time graph in the top left corner.
+    #[cfg(feature = "debug")]
+    pub fn draw(&self, geng: &Geng, framebuffer: &mut ugli::Framebuffer) {
+        let camera = &geng::PixelPerfectCamera;
+        let screen = framebuffer.size().map(|x| x as f32);
+        let font_size = 16.0;
+        let padding = 8.0;
+        let top_left = vec2(padding, screen.y - padding);
+
+        let background = Rgba::new(0.0, 0.0, 0.0, 0.6);
+        let text_color = Rgba::WHITE;
+        let graph_size = vec2(Self::HISTORY as f32 * 2.0, 60.0);
+        let gpu_lines = self.gpu_averages.iter().flatten().count();
+        let lines = Phase::ALL.len() + 1 + gpu_lines;
+        let panel = Aabb2::from_corners(
+            top_left,
+            top_left
+                + vec2(
+                    graph_size.x + padding * 2.0,
+                    -(font_size * lines as f32 + graph_size.y + padding * 3.0),
+                ),
+        );
+        geng.draw2d()
+            .draw2d(framebuffer, camera, &draw2d::Quad::new(panel, background));
+
+        let mut line = top_left + vec2(padding, -padding - font_size);
+        let mut text = |text: &str| {
+            geng.default_font().draw(
+                framebuffer,
+                camera,
+                text,
+                vec2::splat(geng::TextAlign::LEFT),
+                mat3::translate(line) * mat3::scale_uniform(font_size),
+                text_color,
+            );
+            line.y -= font_size;
+        };
+        text(&format!("frame: {:.2} ms", self.frame_time()));
+        for phase in Phase::ALL {
+            text(&format!(
+                "{}: {:.2} ms",
+                phase.name(),
+                self.averages[phase as usize]
+            ));
+        }
+        for pass in GpuPass::ALL {
+            if let Some(time) = self.gpu_averages[pass as usize] {
+                text(&format!("gpu {}: {:.2} ms", pass.name(), time));
+            }
+        }
+
+        // Frame times, with the 60 fps frame time at the middle
+        let origin = vec2(top_left.x + padding, line.y - padding - graph_size.y);
+        let scale = graph_size.y / (1000.0 / 60.0 * 2.0);
+        let budget = draw2d::Segment::new(
+            Segment(
+                origin + vec2(0.0, graph_size.y / 2.0),
+                origin + vec2(graph_size.x, graph_size.y / 2.0),
+            ),
+            1.0,
+            Rgba::new(1.0, 1.0, 1.0, 0.3),
+        );
+        geng.draw2d().draw2d(framebuffer, camera, &budget);
+        if self.frame_times.len() >= 2 {
+            let points = self
+                .frame_times
+                .iter()
+                .enumerate()
+                .map(|(i, &time)| origin + vec2(i as f32 * 2.0, (time * scale).min(graph_size.y)))
+                .collect();
+            let graph = draw2d::Chain::new(Chain::new(points), 1.5, Rgba::GREEN, 1);
+            geng.draw2d().draw2d(framebuffer, camera, &graph);
+        }
+    }
+}
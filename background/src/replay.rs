@@ -0,0 +1,101 @@
+use crate::Config;
+
+use geng::prelude::*;
+
+/// A recorded run that plays back exactly: the config with its seed,
+/// and the time and the input events of every simulated frame.
+/// The config reloads, the adaptive quality, the audio, and the local time
+/// are outside of the recording, so they are disabled or frozen in both modes.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub config: Config,
+    /// Hours since the local midnight when the recording started, for the day cycle.
+    pub local_hour: f32,
+    pub frames: Vec<ReplayFrame>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReplayFrame {
+    /// Time of the frame after the frame limiter, before the time scale.
+    pub delta_time: f32,
+    /// Size of the window, which determines the view the objects spawn in.
+    pub screen_size: vec2<usize>,
+    /// Events handled since the previous frame, in order.
+    pub events: Vec<geng::Event>,
+}
+
+impl Replay {
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)?;
+        let replay = ron::from_str(&source)
+            .map_err(|err| anyhow::anyhow!("invalid replay {:?}: {}", path, err))?;
+        Ok(replay)
+    }
+
+    fn save(&self, path: &std::path::Path) -> anyhow::Result<()> {
+        // Compact, as there are thousands of frames
+        let text = ron::to_string(self)?;
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+}
+
+/// Collects the frames of a [Replay] as they are simulated.
+/// The file is saved every few seconds and when the recorder is dropped,
+/// so that closing the window loses at most the last few seconds.
+pub struct Recorder {
+    path: std::path::PathBuf,
+    replay: Replay,
+    /// Events since the last frame.
+    events: Vec<geng::Event>,
+    /// Seconds of the frames recorded since the last save.
+    unsaved_time: f32,
+}
+
+impl Recorder {
+    /// Seconds between the saves of the file.
+    const SAVE_INTERVAL: f32 = 10.0;
+
+    pub fn new(path: std::path::PathBuf, config: Config, local_hour: f32) -> Self {
+        log::info!("Recording a replay into {:?}", path);
+        Self {
+            path,
+            replay: Replay {
+                config,
+                local_hour,
+                frames: Vec::new(),
+            },
+            events: Vec::new(),
+            unsaved_time: 0.0,
+        }
+    }
+
+    pub fn event(&mut self, event: &geng::Event) {
+        self.events.push(event.clone());
+    }
+
+    pub fn frame(&mut self, delta_time: f32, screen_size: vec2<usize>) {
+        self.replay.frames.push(ReplayFrame {
+            delta_time,
+            screen_size,
+            events: std::mem::take(&mut self.events),
+        });
+        self.unsaved_time += delta_time;
+        if self.unsaved_time >= Self::SAVE_INTERVAL {
+            self.unsaved_time = 0.0;
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        if let Err(err) = self.replay.save(&self.path) {
+            log::error!("Failed to save the replay: {:?}", err);
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
@@ -0,0 +1,56 @@
+use std::collections::VecDeque;
+
+/// Matches a sequence of keys typed in a row, like a cheat code.
+pub struct KeySequence<T> {
+    keys: Vec<T>,
+    /// The last typed keys, at most as many as in the sequence.
+    recent: VecDeque<T>,
+}
+
+impl<T: PartialEq> KeySequence<T> {
+    pub fn new(keys: impl IntoIterator<Item = T>) -> Self {
+        Self {
+            keys: keys.into_iter().collect(),
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// Feed the next key, returns `true` when the sequence is completed.
+    pub fn push(&mut self, key: T) -> bool {
+        if self.keys.is_empty() {
+            return false;
+        }
+        if self.recent.len() == self.keys.len() {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(key);
+        if self.recent.iter().eq(&self.keys) {
+            self.recent.clear();
+            return true;
+        }
+        false
+    }
+}
+
+#[test]
+fn test_key_sequence() {
+    let mut sequence = KeySequence::new("aab".chars());
+    assert!(!sequence.push('a'));
+    assert!(!sequence.push('a'));
+    assert!(sequence.push('b'));
+
+    // Interrupted and restarted
+    assert!(!sequence.push('a'));
+    assert!(!sequence.push('b'));
+    assert!(!sequence.push('a'));
+    assert!(!sequence.push('a'));
+    assert!(sequence.push('b'));
+
+    // Extra repeated keys at the start
+    assert!(!sequence.push('a'));
+    assert!(!sequence.push('a'));
+    assert!(!sequence.push('a'));
+    assert!(sequence.push('b'));
+
+    assert!(!KeySequence::new([]).push('a'));
+}
@@ -0,0 +1,85 @@
+varying vec4 v_color;
+
+#ifdef VERTEX_SHADER
+attribute vec3 a_a;
+attribute vec3 a_b;
+attribute vec3 a_c;
+attribute vec2 a_corner;
+
+attribute mat4 i_model_matrix;
+attribute vec4 i_color;
+
+// Normalized normal and offset of the plane
+uniform vec4 u_plane;
+uniform mat4 u_plane_matrix;
+uniform float u_width;
+
+uniform mat3 u_projection_matrix;
+uniform mat3 u_view_matrix;
+
+float distance_to_plane(vec3 p) {
+    return dot(u_plane.xyz, p) - u_plane.w;
+}
+
+// Intersection of the plane with the segment from `p` to `q`
+vec3 intersect(vec3 p, float dp, vec3 q, float dq) {
+    return p + (q - p) * (dp / (dp - dq));
+}
+
+// Same as `Plane::project2d` followed by the coordinate system conversion
+vec2 to_plane(vec3 p) {
+    vec4 local = u_plane_matrix * vec4(p, 1.0);
+    return vec2(-local.z, local.y);
+}
+
+void main() {
+    v_color = i_color;
+
+    vec3 a = (i_model_matrix * vec4(a_a, 1.0)).xyz;
+    vec3 b = (i_model_matrix * vec4(a_b, 1.0)).xyz;
+    vec3 c = (i_model_matrix * vec4(a_c, 1.0)).xyz;
+    float da = distance_to_plane(a);
+    float db = distance_to_plane(b);
+    float dc = distance_to_plane(c);
+
+    // Find the vertex alone on its side of the plane
+    vec3 p;
+    vec3 q;
+    if ((da > 0.0) == (db > 0.0)) {
+        if ((da > 0.0) == (dc > 0.0)) {
+            // The triangle does not cross the plane
+            gl_Position = vec4(0.0);
+            return;
+        }
+        p = intersect(c, dc, a, da);
+        q = intersect(c, dc, b, db);
+    } else if ((da > 0.0) == (dc > 0.0)) {
+        p = intersect(b, db, a, da);
+        q = intersect(b, db, c, dc);
+    } else {
+        p = intersect(a, da, b, db);
+        q = intersect(a, da, c, dc);
+    }
+
+    vec2 start = to_plane(p);
+    vec2 end = to_plane(q);
+    vec2 dir = end - start;
+    float len = length(dir);
+    dir = len > 1e-5 ? dir / len : vec2(1.0, 0.0);
+    vec2 normal = vec2(-dir.y, dir.x);
+
+    // Extend the segment to cover the joints with the neighbouring ones
+    float half_width = u_width * 0.5;
+    vec2 pos = mix(start - dir * half_width, end + dir * half_width, a_corner.x)
+        + normal * half_width * a_corner.y;
+
+    vec3 screen = u_projection_matrix * u_view_matrix * vec3(pos, 1.0);
+    gl_Position = vec4(screen.xy, 0.0, screen.z);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+void main() {
+    gl_FragColor = v_color;
+}
+#endif
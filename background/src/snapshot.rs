@@ -0,0 +1,119 @@
+use crate::{
+    config::{Easing, Link},
+    geometry::Rotation,
+    state::{Breathing, Orbit},
+};
+
+use geng::prelude::*;
+
+/// The objects and the timers of a scene, to freeze a pleasing arrangement and restore it later.
+/// Shards of the fractured objects are left out, as their geometry is not a prefab.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneSnapshot {
+    pub simulation_time: f32,
+    /// Time until the next spawn.
+    pub next_spawn: f32,
+    pub timeline_time: f32,
+    pub timeline_index: usize,
+    pub beat_time: f32,
+    /// Tilt of the plane in degrees.
+    pub tilt: vec2<f32>,
+    pub objects: Vec<ObjectSnapshot>,
+    pub clusters: Vec<ClusterSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectSnapshot {
+    /// Name of the prefab in the config, objects of unknown prefabs are skipped on restore.
+    pub prefab: String,
+    pub position: vec3<f32>,
+    pub velocity: vec3<f32>,
+    pub rotation: Rotation,
+    pub angular_velocity: vec3<f32>,
+    pub orbit: Option<Orbit>,
+    pub scale: f32,
+    pub age: f32,
+    pub color: Rgba<f32>,
+    pub link: Option<Link>,
+    #[serde(default)]
+    pub layer: usize,
+    #[serde(default)]
+    pub reversed: bool,
+    pub breathing: Option<Breathing>,
+    pub morph: Option<MorphSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MorphSnapshot {
+    /// Name of the prefab the object turns into.
+    pub to: String,
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterSnapshot {
+    pub id: u64,
+    pub center: vec3<f32>,
+    pub velocity: vec3<f32>,
+    pub rotation: Rotation,
+    /// In radians per second.
+    pub angular_speed: f32,
+    #[serde(default)]
+    pub layer: usize,
+}
+
+impl SceneSnapshot {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let source = std::fs::read_to_string(path)?;
+        let snapshot = ron::from_str(&source)
+            .map_err(|err| anyhow::anyhow!("invalid snapshot {:?}: {}", path, err))?;
+        Ok(snapshot)
+    }
+
+    #[cfg_attr(not(feature = "export"), allow(dead_code))]
+    pub fn to_ron(&self) -> anyhow::Result<String> {
+        let pretty = ron::ser::PrettyConfig::default();
+        Ok(ron::ser::to_string_pretty(self, pretty)?)
+    }
+}
+
+#[test]
+fn test_snapshot_roundtrip() {
+    let snapshot = SceneSnapshot {
+        simulation_time: 12.5,
+        next_spawn: 0.3,
+        timeline_time: 2.0,
+        timeline_index: 1,
+        beat_time: 12.5,
+        tilt: vec2(5.0, -3.0),
+        objects: vec![ObjectSnapshot {
+            prefab: "cube".to_owned(),
+            position: vec3(1.0, 2.0, -3.0),
+            velocity: vec3::UNIT_Z,
+            rotation: Rotation::IDENTITY,
+            angular_velocity: vec3(0.0, 0.5, 0.0),
+            orbit: None,
+            scale: 0.7,
+            age: 4.0,
+            color: Rgba::WHITE,
+            link: None,
+            layer: 0,
+            reversed: false,
+            breathing: None,
+            morph: Some(MorphSnapshot {
+                to: "octahedron".to_owned(),
+                duration: 5.0,
+                easing: Easing::Linear,
+            }),
+        }],
+        clusters: Vec::new(),
+    };
+    let text = snapshot.to_ron().unwrap();
+    let loaded: SceneSnapshot = ron::from_str(&text).unwrap();
+    assert_eq!(loaded.objects.len(), 1);
+    assert_eq!(loaded.objects[0].position, snapshot.objects[0].position);
+    assert_eq!(loaded.objects[0].rotation, Rotation::IDENTITY);
+    assert_eq!(loaded.tilt, snapshot.tilt);
+}
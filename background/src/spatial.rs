@@ -0,0 +1,147 @@
+use geng::prelude::*;
+
+use std::collections::HashMap;
+
+/// A coarse grid of 2D positions for fast neighbourhood queries.
+pub struct SpatialHash {
+    cell_size: f32,
+    /// Indices of the items in each cell.
+    /// Emptied cells are kept around so that rebuilding does not allocate.
+    cells: HashMap<vec2<i32>, Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new() -> Self {
+        Self {
+            cell_size: 1.0,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, position: vec2<f32>) -> vec2<i32> {
+        (position / self.cell_size).map(|x| x.floor() as i32)
+    }
+
+    /// Remove all items and change the cell size.
+    pub fn clear(&mut self, cell_size: f32) {
+        self.cell_size = cell_size.max(1e-3);
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+    }
+
+    pub fn insert(&mut self, index: usize, position: vec2<f32>) {
+        let cell = self.cell(position);
+        self.cells.entry(cell).or_default().push(index);
+    }
+
+    /// Indices of the items that might be within `radius` of the position.
+    pub fn query(&self, position: vec2<f32>, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let min = self.cell(position - vec2::splat(radius));
+        let max = self.cell(position + vec2::splat(radius));
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| vec2(x, y)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Edges between the points closer than a distance, preferring the shorter ones,
+/// with a limited number of edges per point.
+/// The buffers are kept between the builds so that rebuilding does not allocate.
+pub struct ProximityGraph {
+    hash: SpatialHash,
+    degrees: Vec<usize>,
+    /// Indices of the points and their distance, shortest first.
+    edges: Vec<(usize, usize, f32)>,
+}
+
+impl ProximityGraph {
+    pub fn new() -> Self {
+        Self {
+            hash: SpatialHash::new(),
+            degrees: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    pub fn build(
+        &mut self,
+        points: &[vec2<f32>],
+        distance: f32,
+        max_degree: usize,
+    ) -> &[(usize, usize, f32)] {
+        self.hash.clear(distance);
+        for (i, &point) in points.iter().enumerate() {
+            self.hash.insert(i, point);
+        }
+        self.edges.clear();
+        for (i, &point) in points.iter().enumerate() {
+            for j in self.hash.query(point, distance) {
+                if j <= i {
+                    continue;
+                }
+                let length = (points[j] - point).len();
+                if length < distance {
+                    self.edges.push((i, j, length));
+                }
+            }
+        }
+        self.edges.sort_unstable_by(|a, b| a.2.total_cmp(&b.2));
+
+        self.degrees.clear();
+        self.degrees.resize(points.len(), 0);
+        let degrees = &mut self.degrees;
+        self.edges.retain(|&(i, j, _)| {
+            let free = degrees[i] < max_degree && degrees[j] < max_degree;
+            if free {
+                degrees[i] += 1;
+                degrees[j] += 1;
+            }
+            free
+        });
+        &self.edges
+    }
+}
+
+#[test]
+fn test_proximity_graph() {
+    let mut graph = ProximityGraph::new();
+    let points = [
+        vec2(0.0, 0.0),
+        vec2(1.0, 0.0),
+        vec2(0.0, 1.5),
+        vec2(10.0, 10.0),
+    ];
+    let edges = graph.build(&points, 2.0, 1);
+    assert_eq!(edges, [(0, 1, 1.0)]);
+
+    let mut pairs: Vec<_> = graph
+        .build(&points, 2.0, 2)
+        .iter()
+        .map(|&(i, j, _)| (i, j))
+        .collect();
+    pairs.sort();
+    assert_eq!(pairs, [(0, 1), (0, 2), (1, 2)]);
+}
+
+#[test]
+fn test_spatial_hash() {
+    let mut hash = SpatialHash::new();
+    hash.clear(2.0);
+    hash.insert(0, vec2(0.5, 0.5));
+    hash.insert(1, vec2(-3.0, 1.0));
+    hash.insert(2, vec2(10.0, 10.0));
+
+    let mut near: Vec<usize> = hash.query(vec2(0.0, 0.0), 1.0).collect();
+    near.sort();
+    assert_eq!(near, [0]);
+
+    let mut near: Vec<usize> = hash.query(vec2(-1.0, 0.0), 2.5).collect();
+    near.sort();
+    assert_eq!(near, [0, 1]);
+
+    hash.clear(2.0);
+    assert_eq!(hash.query(vec2(0.0, 0.0), 100.0).count(), 0);
+}
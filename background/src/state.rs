@@ -1,239 +1,3169 @@
 use crate::{
-    geometry::{self, CrossSectionVertex, Plane, Triangle, Vertex},
-    Assets,
+    audio::{AudioAnalyzer, AudioLevels},
+    batch::SectionBatch,
+    config::{
+        self, ColorScheme, CursorMode, Easing, Layer, Link, Motion, PageSection, PowerProfile,
+        QualityLevel, Stroke, Theme, TimelineAction,
+    },
+    effects::Effects,
+    frame_limiter::FrameLimiter,
+    geometry::{self, slice_object, Lods, Mesh, Plane, Rotation},
+    gpu_timer::{GpuPass, GpuTimer},
+    palette::Palette,
+    prefab::{MorphMeshes, PrefabRegistry, Shape},
+    preferences::Preferences,
+    profiler::{Phase, Profiler},
+    sequence::KeySequence,
+    spatial::{ProximityGraph, SpatialHash},
+    web, Config, ConfigReloader,
 };
 
+#[cfg(feature = "gpu-slicing")]
+use crate::gpu_slice::GpuSlicer;
+#[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+use crate::replay::{Recorder, Replay, ReplayFrame};
+#[cfg(feature = "debug")]
+use crate::snapshot::{ClusterSnapshot, MorphSnapshot, ObjectSnapshot, SceneSnapshot};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::worker::SliceWorker;
+
 use geng::prelude::*;
 use geng_utils::conversions::Vec2RealConversions;
 
 pub struct Object {
-    pub geometry: Rc<ugli::VertexBuffer<Vertex>>,
+    /// Unique identifier, assigned when the object is added to the scene.
+    pub id: u64,
+    /// Name of the prefab the object was spawned from.
+    pub prefab: Rc<str>,
+    pub geometry: Lods,
     pub position: vec3<f32>,
-    pub orientation: vec3<f32>,
-    pub roll: Angle<f32>,
+    /// Direction and speed of the travel, relative to the configured speed.
+    pub velocity: vec3<f32>,
+    pub rotation: Rotation,
+    /// World space axis of the spin, scaled by the speed in radians per second.
+    pub angular_velocity: vec3<f32>,
+    /// Displacement from the position, only affecting the rendering.
+    pub wobble: vec3<f32>,
+    /// Set if the object is bound into a cluster, which then controls its position.
+    pub orbit: Option<Orbit>,
+    /// Displaced position and rotation before the last simulation step, for interpolation.
+    pub previous: (vec3<f32>, Rotation),
     pub scale: f32,
+    /// Seconds since the object was spawned.
+    pub age: f32,
+    /// Fraction of the scale shown, growing after the spawn and shrinking before the removal.
+    pub size: f32,
     pub color: Rgba<f32>,
+    /// Opened when the section of the object is clicked.
+    pub link: Option<Link>,
+    /// Index of the depth layer in the config.
+    pub layer: usize,
+    /// Whether the object spawned in front of the plane and travels backwards.
+    pub reversed: bool,
+    /// Set for the pieces of a fractured object.
+    pub shard: Option<Shard>,
+    /// Set if the object is turning into another prefab.
+    pub morph: Option<Morphing>,
+    /// Set if the size of the object pulsates.
+    pub breathing: Option<Breathing>,
+    /// Whether the object has reached the plane, to trigger the hit-stop once.
+    pub touched: bool,
+    /// Seconds left of the outline flash after the hit-stop.
+    pub flash: f32,
 }
 
 impl Object {
-    pub fn new(position: vec3<f32>, geometry: Rc<ugli::VertexBuffer<Vertex>>) -> Self {
+    pub fn new(position: vec3<f32>, prefab: Rc<str>, geometry: Lods) -> Self {
         Self {
+            id: 0,
+            prefab,
             geometry,
             position,
-            orientation: vec3::UNIT_X,
-            roll: Angle::ZERO,
+            velocity: vec3::UNIT_Z,
+            rotation: Rotation::IDENTITY,
+            angular_velocity: vec3::ZERO,
+            wobble: vec3::ZERO,
+            orbit: None,
+            previous: (position, Rotation::IDENTITY),
             scale: 1.0,
+            age: 0.0,
+            size: 0.0,
             color: Rgba::WHITE,
+            link: None,
+            layer: 0,
+            reversed: false,
+            shard: None,
+            morph: None,
+            breathing: None,
+            touched: false,
+            flash: 0.0,
+        }
+    }
+
+    /// Transformation blended between the previous and the current simulation step.
+    pub fn interpolated_matrix(&self, alpha: f32) -> mat4<f32> {
+        let (previous_position, previous_rotation) = self.previous;
+        let position = self.position + self.wobble;
+        let position = previous_position + (position - previous_position) * alpha;
+        let rotation = previous_rotation.nlerp(self.rotation, alpha);
+        // The slicing inverts the matrix, so it can not collapse completely
+        let scale = (self.scale * self.size).max(1e-3);
+        mat4::translate(position) * rotation.matrix() * mat4::scale_uniform(scale)
+    }
+
+    /// The level of detail to slice, given the scale of the view and the configured threshold.
+    pub fn mesh(&self, pixels_per_unit: f32, lod_size: Option<f32>) -> &Mesh {
+        self.geometry
+            .select(self.scale * 2.0 * pixels_per_unit, lod_size)
+    }
+
+    /// Sign of the travel along the Z axis.
+    pub fn direction(&self) -> f32 {
+        if self.reversed {
+            -1.0
+        } else {
+            1.0
+        }
+    }
+
+    /// How far the object is past the plane in the direction of its travel.
+    pub fn depth(&self) -> f32 {
+        self.position.z * self.direction()
+    }
+
+    /// Move the object between the simulation steps, along with its previous position,
+    /// so that the interpolation applies the whole offset right away.
+    pub fn shift(&mut self, offset: vec3<f32>) {
+        self.position += offset;
+        self.previous.0 += offset;
+    }
+
+    /// Forget the previous step, so that the object is not interpolated from there.
+    pub fn snap(&mut self) {
+        self.previous = (self.position + self.wobble, self.rotation);
+    }
+
+    /// Spin by the angular velocity over the given time.
+    pub fn rotate(&mut self, delta_time: f32) {
+        let step = Rotation::from_scaled_axis(self.angular_velocity * delta_time);
+        // Normalize to not accumulate the rounding errors
+        self.rotation = (step * self.rotation).normalize();
+    }
+}
+
+/// Place of an object in a [Cluster].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Orbit {
+    pub cluster: u64,
+    /// Offset from the center of the cluster, in the space of the cluster.
+    pub offset: vec3<f32>,
+}
+
+/// Sinusoidal change of the size of an object, see [config::Pulsation].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Breathing {
+    /// Fraction of the size.
+    pub amplitude: f32,
+    /// In radians per second.
+    pub frequency: f32,
+    /// In radians.
+    pub phase: f32,
+}
+
+impl Breathing {
+    /// Multiplier for the size of the object at the given age.
+    pub fn factor(&self, age: f32) -> f32 {
+        1.0 + self.amplitude * (age * self.frequency + self.phase).sin()
+    }
+}
+
+/// Progress of an object turning into another prefab.
+pub struct Morphing {
+    /// Name of the prefab the object turns into.
+    pub to: String,
+    pub meshes: MorphMeshes,
+    /// Seconds since the spawn until the morph is complete.
+    pub duration: f32,
+    pub easing: Easing,
+    /// Progress the geometry was last blended at.
+    pub blended: Option<f32>,
+}
+
+impl Morphing {
+    /// The geometry blended for the age of the object,
+    /// or `None` if it has not changed enough since the last one.
+    pub fn update(&mut self, age: f32) -> Option<Lods> {
+        let t = self.easing.apply(age / self.duration);
+        let changed = self.blended.map_or(true, |blended| {
+            (t - blended).abs() >= MORPH_STEP || t == 1.0 && blended != 1.0
+        });
+        if !changed {
+            return None;
         }
+        self.blended = Some(t);
+        Some(Lods::generate(self.meshes.blend(t), 0))
+    }
+}
+
+/// A piece of a fractured object, flying apart from the others and fading away.
+#[derive(Debug, Clone, Copy)]
+pub struct Shard {
+    /// Seconds until the shard disappears.
+    pub time_left: f32,
+    /// Opacity of the whole object.
+    pub alpha: f32,
+}
+
+/// A transient slowdown of the simulation, easing back to the normal speed.
+struct TimeModifier {
+    /// Time scale at the start.
+    scale: f32,
+    /// Seconds of real time until the normal speed.
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl TimeModifier {
+    fn scale(&self) -> f32 {
+        let t = self.easing.apply(self.elapsed / self.duration);
+        self.scale + (1.0 - self.scale) * t
+    }
+}
+
+/// A few objects orbiting their common center, which drifts like a single object.
+struct Cluster {
+    center: vec3<f32>,
+    /// Relative to the configured speed, like [Object::velocity].
+    velocity: vec3<f32>,
+    rotation: Rotation,
+    /// Speed of the orbit around the local Z axis in radians per second.
+    angular_speed: f32,
+    /// Depth layer of the members.
+    layer: usize,
+}
+
+impl Cluster {
+    fn position(&self, offset: vec3<f32>) -> vec3<f32> {
+        self.center + (self.rotation.matrix() * offset.extend(1.0)).xyz()
     }
+}
+
+/// A cross section of an object with the plane, in world coordinates.
+pub struct Section {
+    pub object: u64,
+    pub color: Rgba<f32>,
+    pub points: Vec<vec2<f32>>,
+}
 
-    pub fn matrix(&self) -> mat4<f32> {
-        let flat = vec2(self.orientation.x, self.orientation.z);
-        let rot_h = flat.arg();
-        let rot_v = vec2(flat.len(), self.orientation.y).arg();
-        mat4::translate(self.position)
-            * mat4::rotate_x(self.roll)
-            * mat4::rotate_z(-rot_v)
-            * mat4::rotate_y(rot_h)
-            * mat4::scale_uniform(self.scale)
+impl Section {
+    pub fn bounding_box(&self) -> Option<Aabb2<f32>> {
+        Aabb2::points_bounding_box(self.points.iter().copied())
     }
 
-    pub fn rotate_y(&mut self, angle: Angle<f32>) {
-        let flat = vec2(self.orientation.x, self.orientation.z);
-        let flat = flat.rotate(angle);
-        self.orientation = vec3(flat.x, self.orientation.y, flat.y);
+    pub fn center(&self) -> vec2<f32> {
+        self.points
+            .iter()
+            .copied()
+            .fold(vec2::ZERO, |sum, p| sum + p)
+            / self.points.len() as f32
     }
 }
 
+/// A pointer drag in progress.
+struct Drag {
+    /// The touch performing the drag, or `None` for the mouse.
+    touch: Option<u64>,
+    /// Screen position where the drag started.
+    start: vec2<f32>,
+    /// Whether the pointer moved far enough to not count as a click.
+    moved: bool,
+}
+
+/// Tilt of the plane around the Y and X axes, with a spring pulling it back.
+#[derive(Default)]
+struct Tilt {
+    angle: vec2<f32>,
+    velocity: vec2<f32>,
+}
+
+/// Temporarily replaces the colors of the new objects.
+struct SceneOverride {
+    palette: Palette,
+    time_left: f32,
+}
+
 pub struct State {
-    geng: Geng,
-    assets: Rc<Assets>,
-    framebuffer_size: vec2<usize>,
+    /// Missing only in the headless benchmark, which never draws.
+    geng: Option<Geng>,
+    config: Config,
+    reloader: ConfigReloader,
+    theme_name: String,
+    theme: Theme,
+    color_scheme: ColorScheme,
+    next_color_scheme_check: f32,
+    /// Colors defined by the page, overriding the theme.
+    css_colors: Option<web::CssColors>,
+    /// Whether the reduced motion profile is active.
+    reduced_motion: bool,
+    /// Whether the device was detected to be weak or low on battery.
+    low_power: bool,
+    rng: StdRng,
+    frame_limiter: FrameLimiter,
+    /// Whether the current frame should be rendered.
+    render_frame: bool,
+    /// Number of frames rendered so far.
+    frames_rendered: u64,
+    /// Frames rendered and time passed since the last frame rate measurement.
+    fps_counter: (u32, f32),
+    /// Whether the last measured frame rate was below `low_fps`.
+    low_fps: bool,
+    /// Frames rendered and time passed since the last adaptive quality decision.
+    frame_time_counter: (u32, f32),
+    /// Number of the adaptive quality levels applied.
+    quality_level: usize,
+    /// Time accumulated over the frames skipped by the limiter.
+    skipped_time: f32,
+    /// Whether the window has focus.
+    focused: bool,
+    /// Whether the page is hidden, following the visibility changes.
+    hidden: bool,
+    /// Set when the page becomes visible again, as the browser stops the frames while hidden
+    /// and the first frame after carries the whole hidden time.
+    shown: bool,
+    /// Whether the simulation was paused on the last update.
+    paused: bool,
+    /// Choices made by the visitor, including whether they paused the simulation.
+    preferences: Preferences,
+    /// Whether the page markup asked to pause the simulation.
+    host_paused: bool,
+    /// Multiplier for the number of objects set by the page.
+    density: f32,
+    konami: KeySequence<geng::Key>,
+    scene_override: Option<SceneOverride>,
+    /// Seconds since the timeline (re)started.
+    timeline_time: f32,
+    /// The next timeline event to play.
+    timeline_index: usize,
+    /// Debug mode forced from the command line.
+    #[cfg(feature = "debug")]
+    debug: bool,
+    #[cfg(feature = "debug")]
+    show_overlay: bool,
+    /// Whether to show the frame timings, only in the debug mode.
+    #[cfg(feature = "debug")]
+    show_profiler: bool,
+    profiler: Profiler,
+    /// Created on the first frame, when the engine context exists.
+    gpu_timer: Option<GpuTimer>,
+    /// Seconds since the last input or scroll.
+    idle_time: f32,
+    /// Multiplier for the simulation speed, ramping down while idle.
+    idle_scale: f32,
+    /// Id of the page section in view.
+    page_section: Option<String>,
+    /// Density and speed multipliers, blending towards the ones of the page section.
+    page_blend: vec2<f32>,
+    /// Hours since the local midnight, updated every second for the day cycle.
+    local_hour: f32,
+    /// Started when an audio source is configured and there is a window.
+    audio: Option<AudioAnalyzer>,
+    /// Smoothed loudness of the audio source.
+    audio_levels: AudioLevels,
+    /// Taken with the debug shortcut, to restore it later.
+    #[cfg(feature = "debug")]
+    saved_snapshot: Option<SceneSnapshot>,
+    /// Set when recording the run with `--record-replay`.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    recorder: Option<Recorder>,
+    /// Frames left to play back, replacing the real time and input.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    playback: Option<std::vec::IntoIter<ReplayFrame>>,
+    /// Seconds since the start, to follow the configured tempo.
+    beat_time: f32,
+    /// Number of the last beat, to accent the start of every bar once.
+    last_beat: Option<u64>,
+    /// Active slowdowns, multiplied into the time scale.
+    time_modifiers: Vec<TimeModifier>,
+    /// Size of the window in physical pixels, which is what the events report.
+    screen_size: vec2<usize>,
+    /// Offscreen target used when rendering at a lower resolution than the screen.
+    render_texture: Option<ugli::Texture>,
     simulation_time: f32,
+    /// Simulation time not yet consumed by the fixed steps.
+    step_accumulator: f32,
+    /// How far the rendered frame is between the previous and the current step.
+    step_alpha: f32,
     next_spawn: f32,
-    prefabs: Vec<Rc<ugli::VertexBuffer<Vertex>>>,
+    prefab_registry: PrefabRegistry,
     objects: Vec<Object>,
+    next_id: u64,
+    clusters: HashMap<u64, Cluster>,
+    next_cluster_id: u64,
+    /// Pairs of objects close enough to be connected in the constellation.
+    links: Vec<(u64, u64)>,
+    /// Section centers and colors by object, reused between the frames to draw the links.
+    link_ends: HashMap<u64, (vec2<f32>, Rgba<f32>)>,
+    /// Section centers and the nearby pairs of them, reused between the frames.
+    section_centers: Vec<vec2<f32>>,
+    section_graph: ProximityGraph,
+    /// Sections computed in the last frame.
+    sections: Vec<Section>,
+    /// Buffers reused between the frames so that the slicing does not allocate.
+    scratch_points: Vec<vec3<f32>>,
+    point_pool: Vec<Vec<vec2<f32>>>,
+    /// Object positions for the spawn overlap checks, rebuilt every update.
+    spatial_hash: SpatialHash,
+    /// Created when GPU slicing is first enabled.
+    #[cfg(feature = "gpu-slicing")]
+    gpu_slicer: Option<GpuSlicer>,
+    /// Started when slicing on a thread is first enabled.
+    #[cfg(not(target_arch = "wasm32"))]
+    slice_worker: Option<SliceWorker>,
+    section_batch: SectionBatch,
+    effects: Effects,
     camera2d: Camera2d,
+    /// Cursor position in screen space.
+    cursor: Option<vec2<f32>>,
+    /// Cursor world position on the previous update, to measure its speed.
+    last_cursor: Option<vec2<f32>>,
+    /// Time until the cursor can emit another ripple.
+    ripple_cooldown: f32,
+    /// Active touches in screen space, each acting as a separate cursor.
+    touches: HashMap<u64, vec2<f32>>,
+    drag: Option<Drag>,
+    /// Tilt of the plane in degrees.
+    tilt: Tilt,
+    /// Page scroll offset in pixels reported by the page.
+    scroll_target: f32,
+    /// Smoothed scroll offset.
+    scroll: f32,
+    /// Device orientation when it was first reported, considered neutral.
+    gyro_neutral: Option<vec2<f32>>,
+    /// Smoothed device tilt relative to the neutral orientation, in degrees.
+    gyro_tilt: vec2<f32>,
 }
 
 impl State {
-    pub fn new(geng: Geng, assets: Rc<Assets>) -> Self {
-        let prefab = |geometry| Rc::new(ugli::VertexBuffer::new_dynamic(geng.ugli(), geometry));
-        Self {
+    pub fn new(geng: Geng, config: Config, reloader: ConfigReloader) -> Self {
+        Self::create(Some(geng), config, reloader)
+    }
+
+    /// A state that can only be updated, for benchmarking the simulation without a window.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    pub fn headless(config: Config, reloader: ConfigReloader, screen_size: vec2<usize>) -> Self {
+        let mut state = Self::create(None, config, reloader);
+        state.screen_size = screen_size;
+        // Spawn every prefab from the start, so that the runs are comparable
+        state.wait_prefabs();
+        state
+    }
+
+    /// A state recording the run from the start into the file, to play it back with [Self::replay].
+    /// Without `geng` it can only be updated, like [Self::headless].
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    pub fn record(
+        geng: Option<Geng>,
+        mut config: Config,
+        reloader: ConfigReloader,
+        path: std::path::PathBuf,
+    ) -> Self {
+        config.seed.get_or_insert_with(|| thread_rng().gen());
+        // These depend on more than the recorded input
+        config.adaptive_quality.enabled = false;
+        config.pause_when_hidden = false;
+        config.audio.source = None;
+        let mut state = Self::create(geng, config, reloader);
+        state.wait_prefabs();
+        state.recorder = Some(Recorder::new(path, state.config.clone(), state.local_hour));
+        state
+    }
+
+    /// A state playing back a recorded run, then continuing live once it ends.
+    /// Without `geng` it can only be updated, like [Self::headless].
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    pub fn replay(geng: Option<Geng>, replay: Replay, reloader: ConfigReloader) -> Self {
+        let mut state = Self::create(geng, replay.config, reloader);
+        state.wait_prefabs();
+        state.local_hour = replay.local_hour;
+        state.playback = Some(replay.frames.into_iter());
+        state
+    }
+
+    /// Generate all the prefabs right away, so that spawning never waits for them.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    fn wait_prefabs(&mut self) {
+        let shapes: Vec<Shape> = self.config.prefabs.values().cloned().collect();
+        for shape in &shapes {
+            self.prefab_registry.wait(shape);
+        }
+    }
+
+    /// Whether the run is being recorded or played back, so it has to stay deterministic.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    fn is_replaying(&self) -> bool {
+        self.recorder.is_some() || self.playback.is_some()
+    }
+
+    #[cfg(not(all(feature = "debug", not(target_arch = "wasm32"))))]
+    fn is_replaying(&self) -> bool {
+        false
+    }
+
+    /// Run a step and slice the objects as a frame would, without drawing.
+    /// Returns the time of the step and of the slicing in milliseconds.
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    pub fn bench_frame(&mut self, delta_time: f32) -> (f32, f32) {
+        let timer = Timer::new();
+        self.step(delta_time);
+        let step_time = timer.elapsed().as_secs_f64() as f32 * 1000.0;
+
+        let cross_plane = self.cross_plane();
+        self.point_pool
+            .extend(self.sections.drain(..).map(|section| section.points));
+        let timer = Timer::new();
+        self.slice_objects(&cross_plane);
+        let slicing_time = timer.elapsed().as_secs_f64() as f32 * 1000.0;
+        (step_time, slicing_time)
+    }
+
+    #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+    pub fn object_count(&self) -> usize {
+        self.objects.len()
+    }
+
+    fn create(geng: Option<Geng>, config: Config, reloader: ConfigReloader) -> Self {
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut state = Self {
             simulation_time: 0.0,
+            step_accumulator: 0.0,
+            step_alpha: 1.0,
             next_spawn: 0.0,
-            framebuffer_size: vec2(1, 1),
+            screen_size: vec2(1, 1),
+            render_texture: None,
             camera2d: Camera2d {
                 center: vec2::ZERO,
                 rotation: Angle::ZERO,
                 fov: 10.0,
             },
             objects: Vec::new(),
-            prefabs: vec![prefab(geometry::unit_cube_triangulated())],
+            next_id: 0,
+            clusters: HashMap::new(),
+            next_cluster_id: 0,
+            links: Vec::new(),
+            link_ends: HashMap::new(),
+            section_centers: Vec::new(),
+            section_graph: ProximityGraph::new(),
+            sections: Vec::new(),
+            scratch_points: Vec::new(),
+            point_pool: Vec::new(),
+            spatial_hash: SpatialHash::new(),
+            #[cfg(feature = "gpu-slicing")]
+            gpu_slicer: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            slice_worker: None,
+            section_batch: SectionBatch::default(),
+            effects: Effects::default(),
+            focused: true,
+            hidden: web::is_hidden(),
+            shown: false,
+            paused: false,
+            preferences: Preferences::default(),
+            host_paused: false,
+            density: 1.0,
+            konami: KeySequence::new([
+                geng::Key::ArrowUp,
+                geng::Key::ArrowUp,
+                geng::Key::ArrowDown,
+                geng::Key::ArrowDown,
+                geng::Key::ArrowLeft,
+                geng::Key::ArrowRight,
+                geng::Key::ArrowLeft,
+                geng::Key::ArrowRight,
+                geng::Key::B,
+                geng::Key::A,
+            ]),
+            scene_override: None,
+            timeline_time: 0.0,
+            timeline_index: 0,
+            #[cfg(feature = "debug")]
+            debug: false,
+            #[cfg(feature = "debug")]
+            show_overlay: false,
+            #[cfg(feature = "debug")]
+            show_profiler: false,
+            profiler: Profiler::new(),
+            gpu_timer: None,
+            idle_time: 0.0,
+            idle_scale: 1.0,
+            page_section: None,
+            page_blend: vec2(1.0, 1.0),
+            local_hour: config::local_hour(),
+            audio: None,
+            audio_levels: AudioLevels::default(),
+            #[cfg(feature = "debug")]
+            saved_snapshot: None,
+            #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+            recorder: None,
+            #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+            playback: None,
+            beat_time: 0.0,
+            last_beat: None,
+            time_modifiers: Vec::new(),
+            cursor: None,
+            last_cursor: None,
+            ripple_cooldown: 0.0,
+            touches: HashMap::new(),
+            drag: None,
+            tilt: Tilt::default(),
+            scroll_target: 0.0,
+            scroll: 0.0,
+            gyro_neutral: None,
+            gyro_tilt: vec2::ZERO,
+            prefab_registry: PrefabRegistry::new(LOD_LEVELS),
             geng,
-            assets,
+            theme_name: config.theme.clone(),
+            config,
+            reloader,
+            theme: Theme::default(),
+            color_scheme: ColorScheme::Dark,
+            next_color_scheme_check: 0.0,
+            reduced_motion: false,
+            low_power: false,
+            css_colors: None,
+            rng,
+            frame_limiter: FrameLimiter::new(),
+            render_frame: true,
+            frames_rendered: 0,
+            fps_counter: (0, 0.0),
+            low_fps: false,
+            frame_time_counter: (0, 0.0),
+            quality_level: 0,
+            skipped_time: 0.0,
+        };
+        state.color_scheme = state.preferred_color_scheme();
+        state.reduced_motion = state.prefers_reduced_motion();
+        state.sync_css_colors();
+        state.apply_theme();
+        web::set_allowed_origins(&state.config.allowed_origins);
+        state
+    }
+
+    /// Start preparing the geometry of all the prefabs,
+    /// waiting only for the most likely one so that the first frame can be shown early.
+    pub fn prepare_prefabs(&mut self) {
+        self.prefab_registry.prepare(self.config.prefabs.values());
+        let weight = |name: &String| self.config.prefab_weights.get(name).copied().unwrap_or(0.0);
+        let first = self
+            .config
+            .prefabs
+            .iter()
+            .max_by(|a, b| weight(a.0).total_cmp(&weight(b.0)));
+        if let Some((_, shape)) = first {
+            let shape = shape.clone();
+            self.prefab_registry.wait(&shape);
         }
     }
 
-    pub fn view(&self) -> Aabb2<f32> {
-        let view = vec2(
-            self.camera2d.fov * self.framebuffer_size.as_f32().aspect(),
-            self.camera2d.fov,
-        );
-        Aabb2::point(self.camera2d.center).extend_symmetric(view)
+    /// Switch to the theme with the given name from the config.
+    /// Objects already on the screen are recolored with the new palette.
+    pub fn set_theme(&mut self, name: &str) {
+        if !self.config.themes.contains_key(name) {
+            log::warn!("Theme {:?} not found in the config", name);
+            return;
+        }
+        self.theme_name = name.to_owned();
+        self.apply_theme();
     }
-}
 
-impl geng::State for State {
-    fn update(&mut self, delta_time: f64) {
-        let delta_time = delta_time as f32;
+    /// Force a color scheme, or follow the browser preference if `None`.
+    pub fn set_color_scheme(&mut self, scheme: Option<ColorScheme>) {
+        self.config.color_scheme = scheme;
+        self.update_color_scheme();
+    }
 
-        self.simulation_time += delta_time;
-        self.next_spawn -= delta_time;
-        let mut rng = thread_rng();
-        while self.next_spawn < 0.0 {
-            self.next_spawn += 0.1;
-            if let Some(geometry) = self.prefabs.choose(&mut rng) {
-                let scale = rng.gen_range(0.3..=1.0);
-                let pos_z = -scale * 2.0;
-
-                let pos = 'outer: {
-                    let mut pos = random_spawn(pos_z, self.view(), &mut rng);
-                    for _ in 0..5 {
-                        let mut good = true;
-                        for obj in &self.objects {
-                            let dist = (pos - obj.position).len();
-                            if dist < (scale + obj.scale) * 1.74 {
-                                // Try another one
-                                pos = random_spawn(pos_z, self.view(), &mut rng);
-                                good = false;
-                                break;
-                            }
-                        }
-                        if good {
-                            break 'outer Some(pos);
-                        }
-                    }
-                    None
-                };
+    fn preferred_color_scheme(&self) -> ColorScheme {
+        self.config
+            .color_scheme
+            .or_else(web::preferred_color_scheme)
+            .unwrap_or(ColorScheme::Dark)
+    }
 
-                if let Some(pos) = pos {
-                    let mut obj = Object::new(pos, geometry.clone());
-                    obj.orientation = vec3(
-                        rng.gen_range(-1.0..=1.0),
-                        rng.gen_range(-1.0..=1.0),
-                        rng.gen_range(-1.0..=1.0),
-                    );
-                    obj.roll = Angle::from_degrees(rng.gen_range(0.0..=360.0));
-                    obj.scale = scale;
-                    obj.color = self
-                        .assets
-                        .config
-                        .object_colors
-                        .choose(&mut rng)
-                        .copied()
-                        .unwrap_or(Rgba::WHITE);
-                    self.objects.push(obj);
-                }
+    fn prefers_reduced_motion(&self) -> bool {
+        self.preferences
+            .reduce_motion
+            .or(self.config.reduce_motion)
+            .or_else(web::prefers_reduced_motion)
+            .unwrap_or(false)
+    }
+
+    /// Multipliers for the translation and rotation speeds of the objects.
+    fn motion_scale(&self) -> (f32, f32) {
+        let profile = &self.config.reduced_motion;
+        if !self.reduced_motion {
+            (1.0, 1.0)
+        } else if profile.frozen {
+            (0.0, 0.0)
+        } else {
+            (profile.speed_scale, profile.rotation_scale)
+        }
+    }
+
+    /// Depth at which new objects are spawned.
+    fn spawn_depth(&self, scale: f32, rng: &mut impl Rng) -> f32 {
+        if let Some(sweep) = &self.config.sweep {
+            // Anywhere the plane reaches
+            let range = sweep.amplitude + scale;
+            self.config.cross_plane.offset + rng.gen_range(-range..=range)
+        } else if self.reduced_motion && self.config.reduced_motion.frozen {
+            // Frozen objects have to be sliced right away to be visible
+            rng.gen_range(-0.5..=0.5) * scale
+        } else {
+            // Just behind the plane
+            -scale * 2.0
+        }
+    }
+
+    fn show_effects(&self) -> bool {
+        if self.power_profile().is_some_and(|profile| !profile.effects) {
+            return false;
+        }
+        if self.quality_levels().contains(&QualityLevel::NoEffects) {
+            return false;
+        }
+        !self.reduced_motion || self.config.reduced_motion.effects
+    }
+
+    fn update_color_scheme(&mut self) {
+        let scheme = self.preferred_color_scheme();
+        if scheme != self.color_scheme {
+            self.color_scheme = scheme;
+            // The page styles might depend on the color scheme too
+            self.sync_css_colors();
+        }
+    }
+
+    /// Read the colors from the page styles and recolor the objects.
+    fn sync_css_colors(&mut self) {
+        self.css_colors = if self.config.css_colors {
+            web::css_colors()
+        } else {
+            None
+        };
+        self.apply_theme();
+    }
+
+    /// Resolve the current theme variant and recolor the objects.
+    fn apply_theme(&mut self) {
+        let Some(theme) = self.config.themes.get(&self.theme_name) else {
+            return;
+        };
+        self.theme = theme.variant(self.color_scheme).clone();
+        if let Some(colors) = &self.css_colors {
+            if let Some(background) = colors.background {
+                self.theme.background_color = background;
+            }
+            if !colors.accents.is_empty() {
+                self.theme.object_colors = Palette::from_colors(colors.accents.iter().copied());
+                self.theme.prefab_colors.clear();
             }
         }
 
         for obj in &mut self.objects {
-            obj.position += vec3::UNIT_Z * 0.5 * delta_time;
-            obj.rotate_y(Angle::from_degrees(45.0 * delta_time));
+            if obj.link.as_ref().is_some_and(|link| link.color.is_some()) {
+                continue;
+            }
+            let color = self.theme.random_color(&obj.prefab, &mut self.rng);
+            obj.color = self.config.layer(obj.layer).apply_color(color);
         }
-        // Delete far objects
-        self.objects.retain(|obj| obj.position.z < obj.scale * 2.0);
     }
 
-    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
-        self.framebuffer_size = framebuffer.size();
-        ugli::clear(
-            framebuffer,
-            Some(self.assets.config.background_color),
-            None,
-            None,
-        );
+    /// Capture the objects and the timers of the scene.
+    #[cfg(feature = "debug")]
+    pub fn snapshot(&self) -> SceneSnapshot {
+        SceneSnapshot {
+            simulation_time: self.simulation_time,
+            next_spawn: self.next_spawn,
+            timeline_time: self.timeline_time,
+            timeline_index: self.timeline_index,
+            beat_time: self.beat_time,
+            tilt: self.tilt.angle,
+            objects: self
+                .objects
+                .iter()
+                .filter(|obj| obj.shard.is_none())
+                .map(|obj| ObjectSnapshot {
+                    prefab: obj.prefab.to_string(),
+                    position: obj.position,
+                    velocity: obj.velocity,
+                    rotation: obj.rotation,
+                    angular_velocity: obj.angular_velocity,
+                    orbit: obj.orbit,
+                    scale: obj.scale,
+                    age: obj.age,
+                    color: obj.color,
+                    link: obj.link.clone(),
+                    layer: obj.layer,
+                    reversed: obj.reversed,
+                    breathing: obj.breathing,
+                    morph: obj.morph.as_ref().map(|morph| MorphSnapshot {
+                        to: morph.to.clone(),
+                        duration: morph.duration,
+                        easing: morph.easing,
+                    }),
+                })
+                .collect(),
+            clusters: self
+                .clusters
+                .iter()
+                .map(|(&id, cluster)| ClusterSnapshot {
+                    id,
+                    center: cluster.center,
+                    velocity: cluster.velocity,
+                    rotation: cluster.rotation,
+                    angular_speed: cluster.angular_speed,
+                    layer: cluster.layer,
+                })
+                .collect(),
+        }
+    }
 
-        let cross_plane = Plane {
-            normal: vec3(0.0, 0.0, 1.0),
-            offset: 0.0,
+    /// Replace the scene with a snapshot, generating the prefab geometry as needed.
+    #[cfg(feature = "debug")]
+    pub fn restore(&mut self, snapshot: &SceneSnapshot) {
+        for obj in self.objects.drain(..) {
+            if obj.shard.is_none() {
+                web::emit(web::HostEvent::ObjectExited { id: obj.id });
+            }
+        }
+        self.links.clear();
+        self.point_pool
+            .extend(self.sections.drain(..).map(|section| section.points));
+        self.simulation_time = snapshot.simulation_time;
+        self.step_accumulator = 0.0;
+        self.next_spawn = snapshot.next_spawn;
+        self.timeline_time = snapshot.timeline_time;
+        self.timeline_index = snapshot.timeline_index;
+        self.beat_time = snapshot.beat_time;
+        self.last_beat = None;
+        self.time_modifiers.clear();
+        self.tilt = Tilt {
+            angle: snapshot.tilt,
+            velocity: vec2::ZERO,
         };
 
-        // Calculate a cross section
-        let cross_sections: Vec<(usize, Vec<CrossSectionVertex>)> = self
-            .objects
+        self.clusters = snapshot
+            .clusters
             .iter()
-            .enumerate()
-            .flat_map(|(i, obj)| {
-                let a = obj.geometry.iter().step_by(3);
-                let b = obj.geometry.iter().skip(1).step_by(3);
-                let c = obj.geometry.iter().skip(2).step_by(3);
-                let transform = |v: vec3<f32>| (obj.matrix() * v.extend(1.0)).into_3d();
-                let triangles = itertools::izip![a, b, c].map(|(a, b, c)| {
-                    Triangle::new(transform(a.a_pos), transform(b.a_pos), transform(c.a_pos))
-                });
-                let cross_section = cross_plane.cross_sect(triangles);
-                (cross_section.len() >= 3).then_some((i, cross_section))
+            .map(|cluster| {
+                let restored = Cluster {
+                    center: cluster.center,
+                    velocity: cluster.velocity,
+                    rotation: cluster.rotation,
+                    angular_speed: cluster.angular_speed,
+                    layer: cluster.layer,
+                };
+                (cluster.id, restored)
             })
             .collect();
+        let last_cluster = snapshot.clusters.iter().map(|cluster| cluster.id).max();
+        self.next_cluster_id = last_cluster.map_or(0, |id| id + 1);
 
-        // Draw the cross section in 2d
-        for (i, cross_section) in &cross_sections {
-            let i = *i;
-            draw_flat_section(
-                cross_section,
-                self.objects[i].color,
-                &self.camera2d,
-                &self.geng,
-                framebuffer,
+        let cross_plane = self.cross_plane();
+        for saved in &snapshot.objects {
+            let Some(shape) = self.config.prefabs.get(&saved.prefab) else {
+                log::warn!("Snapshot object of unknown prefab `{}`", saved.prefab);
+                continue;
+            };
+            let (prefab, geometry) = self.prefab_registry.get(&saved.prefab, shape);
+            let mut obj = Object::new(saved.position, prefab, geometry);
+            obj.velocity = saved.velocity;
+            obj.rotation = saved.rotation;
+            obj.angular_velocity = saved.angular_velocity;
+            obj.orbit = saved.orbit;
+            obj.scale = saved.scale;
+            obj.age = saved.age;
+            obj.color = saved.color;
+            obj.link = saved.link.clone();
+            obj.layer = saved.layer;
+            obj.reversed = saved.reversed;
+            obj.breathing = saved.breathing;
+            // Not stopping the time for the objects already at the plane
+            let distance = cross_plane.distance(obj.position) * obj.direction();
+            obj.touched = distance >= -obj.scale * OVERLAP_DISTANCE;
+            if let Some(morph) = &saved.morph {
+                if let Some(to) = self.config.prefabs.get(&morph.to) {
+                    obj.morph = Some(Morphing {
+                        to: morph.to.clone(),
+                        meshes: self.prefab_registry.morph(shape, to),
+                        duration: morph.duration,
+                        easing: morph.easing,
+                        blended: None,
+                    });
+                }
+            }
+            self.add_object(obj);
+        }
+    }
+
+    /// Keep a snapshot of the scene to restore, also saving it as a file if possible.
+    #[cfg(feature = "debug")]
+    fn save_snapshot(&mut self) {
+        let snapshot = self.snapshot();
+        log::info!("Saved a snapshot of {} objects", snapshot.objects.len());
+        #[cfg(feature = "export")]
+        {
+            let result = snapshot
+                .to_ron()
+                .and_then(|text| web::save_text("snapshot.ron", &text));
+            if let Err(err) = result {
+                log::error!("Failed to export the snapshot: {:?}", err);
+            }
+        }
+        self.saved_snapshot = Some(snapshot);
+    }
+
+    /// Serialize the effective config, including all overrides and live tweaks,
+    /// and save it as a file.
+    pub fn export_config(&self) {
+        #[cfg(not(feature = "export"))]
+        {
+            log::warn!("Exporting the config is not available in this build");
+        }
+        #[cfg(feature = "export")]
+        {
+            let pretty = ron::ser::PrettyConfig::default();
+            let result = ron::ser::to_string_pretty(&self.config, pretty)
+                .map_err(anyhow::Error::from)
+                .and_then(|config| {
+                    log::info!("Current config:\n{}", config);
+                    web::save_text("config.export.ron", &config)
+                });
+            if let Err(err) = result {
+                log::error!("Failed to export the config: {:?}", err);
+            }
+        }
+    }
+
+    /// Randomize the motion and the color of a new object.
+    fn randomize_object(&mut self, obj: &mut Object, rng: &mut impl Rng) {
+        obj.rotation = Rotation {
+            w: rng.gen_range(-1.0..=1.0),
+            v: random_direction(rng),
+        }
+        .normalize();
+        let speed = rng.gen_range(ANGULAR_SPEED).to_radians();
+        obj.angular_velocity = random_direction(rng).normalize_or_zero() * speed;
+        let variation = self.config.speed_variation;
+        let drift = self.config.lateral_drift;
+        obj.velocity = vec3(
+            rng.gen_range(-drift..=drift),
+            rng.gen_range(-drift..=drift),
+            rng.gen_range(1.0 - variation..=1.0 + variation) * obj.direction(),
+        );
+        let palette = match &self.scene_override {
+            Some(scene) => Some(&scene.palette),
+            None => self
+                .current_page_section()
+                .and_then(|section| section.palette.as_ref()),
+        };
+        obj.color = palette
+            .and_then(|palette| palette.choose(rng))
+            .or_else(|| self.day_color(&obj.prefab, rng))
+            .unwrap_or_else(|| self.theme.random_color(&obj.prefab, rng));
+        obj.color = self.config.layer(obj.layer).apply_color(obj.color);
+
+        let links = &self.config.links;
+        let active = self.objects.iter().filter(|obj| obj.link.is_some()).count();
+        if active < links.max_active && rng.gen_bool(links.chance.into()) {
+            obj.link = links.targets.choose(rng).cloned();
+            if let Some(color) = obj.link.as_ref().and_then(|link| link.color) {
+                obj.color = color;
+            }
+        }
+
+        let pulsation = &self.config.pulsation;
+        if pulsation.max_amplitude > 0.0 {
+            obj.breathing = Some(Breathing {
+                amplitude: rng.gen_range(pulsation.min_amplitude..=pulsation.max_amplitude),
+                frequency: rng.gen_range(pulsation.min_frequency..=pulsation.max_frequency)
+                    * std::f32::consts::TAU,
+                phase: rng.gen_range(0.0..std::f32::consts::TAU),
+            });
+        }
+
+        // The first morph of the prefab that passes its chance
+        let morph = self
+            .config
+            .morphs
+            .iter()
+            .filter(|morph| *morph.from == *obj.prefab)
+            .find(|morph| rng.gen_bool(morph.chance.into()));
+        if let Some(morph) = morph {
+            let prefabs = &self.config.prefabs;
+            if let (Some(from), Some(to)) = (prefabs.get(&morph.from), prefabs.get(&morph.to)) {
+                obj.morph = Some(Morphing {
+                    to: morph.to.clone(),
+                    meshes: self.prefab_registry.morph(from, to),
+                    duration: rng.gen_range(morph.min_duration..=morph.max_duration),
+                    easing: morph.easing,
+                    blended: None,
+                });
+            }
+        }
+    }
+
+    /// Spawn a random object just behind the plane at the given position.
+    fn spawn_at(&mut self, position: vec2<f32>) {
+        if self.objects.len() >= self.max_objects() {
+            return;
+        }
+        let mut rng = self.rng.clone();
+        if let Some((prefab, geometry)) = self.choose_prefab(&mut rng) {
+            self.rng = rng;
+            self.spawn_prefab_at(prefab, geometry, position);
+        }
+    }
+
+    /// Spawn an object of the given prefab just behind the plane at the given position.
+    fn spawn_prefab_at(&mut self, prefab: Rc<str>, geometry: Lods, position: vec2<f32>) {
+        let mut rng = self.rng.clone();
+        let layer = self.choose_layer(&mut rng);
+        let scale = random_scale(&mut rng) * self.config.layer(layer).scale;
+        let depth = self.spawn_depth(scale, &mut rng);
+        let mut obj = Object::new(position.extend(depth), prefab, geometry);
+        obj.scale = scale;
+        obj.layer = layer;
+        self.randomize_object(&mut obj, &mut rng);
+        self.add_object(obj);
+        self.rng = rng;
+    }
+
+    #[cfg(feature = "debug")]
+    pub fn set_debug(&mut self, debug: bool) {
+        self.debug = debug;
+    }
+
+    #[cfg(feature = "debug")]
+    fn is_debug(&self) -> bool {
+        self.debug || self.config.debug
+    }
+
+    #[cfg(not(feature = "debug"))]
+    fn is_debug(&self) -> bool {
+        false
+    }
+
+    /// Shortcuts for visual iteration, only available in the debug mode:
+    /// - `1`-`9`: spawn the n-th prefab at the cursor
+    /// - `C`: clear all objects
+    /// - `T`: cycle the themes
+    /// - `O`: toggle the debug overlay
+    /// - `P`: toggle the profiler
+    /// - `N`: reseed the random number generator
+    /// - `S`: save a snapshot of the scene
+    /// - `L`: restore the saved snapshot
+    #[cfg(feature = "debug")]
+    fn handle_debug_key(&mut self, key: geng::Key) {
+        let digits = [
+            geng::Key::Digit1,
+            geng::Key::Digit2,
+            geng::Key::Digit3,
+            geng::Key::Digit4,
+            geng::Key::Digit5,
+            geng::Key::Digit6,
+            geng::Key::Digit7,
+            geng::Key::Digit8,
+            geng::Key::Digit9,
+        ];
+        if let Some(index) = digits.iter().position(|&digit| digit == key) {
+            let Some((name, shape)) = self.config.prefabs.iter().nth(index) else {
+                return;
+            };
+            let (prefab, geometry) = self.prefab_registry.get(name, shape);
+            let position = self.cursor_world().unwrap_or(self.camera2d.center);
+            self.spawn_prefab_at(prefab, geometry, position);
+            return;
+        }
+
+        match key {
+            geng::Key::C => {
+                for obj in self.objects.drain(..) {
+                    if obj.shard.is_none() {
+                        web::emit(web::HostEvent::ObjectExited { id: obj.id });
+                    }
+                }
+            }
+            geng::Key::T => {
+                let mut names: Vec<String> = self.config.themes.keys().cloned().collect();
+                names.sort();
+                let current = names.iter().position(|name| *name == self.theme_name);
+                let next = current.map_or(0, |i| (i + 1) % names.len());
+                if let Some(name) = names.get(next) {
+                    log::info!("Theme: {}", name);
+                    self.set_theme(name);
+                }
+            }
+            geng::Key::S => self.save_snapshot(),
+            geng::Key::L => {
+                if let Some(snapshot) = self.saved_snapshot.take() {
+                    self.restore(&snapshot);
+                    self.saved_snapshot = Some(snapshot);
+                }
+            }
+            geng::Key::O => self.show_overlay = !self.show_overlay,
+            geng::Key::P => self.show_profiler = !self.show_profiler,
+            geng::Key::N => {
+                let seed = thread_rng().gen();
+                log::info!("Seed: {}", seed);
+                self.rng = StdRng::seed_from_u64(seed);
+            }
+            _ => {}
+        }
+    }
+
+    /// Outline the spawn area and the cursor field.
+    #[cfg(feature = "debug")]
+    fn draw_overlay(&self, framebuffer: &mut ugli::Framebuffer) {
+        let color = Rgba::new(1.0, 0.0, 0.0, 0.5);
+        let width = 0.05;
+
+        let area = self.view().extend_uniform(self.config.spawn_margin);
+        let corners = area.corners();
+        let chain = draw2d::Chain::new(
+            Chain::new(corners.iter().chain(corners.first()).copied().collect()),
+            width,
+            color,
+            1,
+        );
+        self.geng()
+            .draw2d()
+            .draw2d(framebuffer, &self.camera2d, &chain);
+
+        let field = &self.config.cursor_field;
+        if field.is_active() {
+            for pointer in self.pointers_world() {
+                self.geng().draw2d().draw2d(
+                    framebuffer,
+                    &self.camera2d,
+                    &draw2d::Ellipse::circle_with_cut(
+                        pointer,
+                        field.radius - width,
+                        field.radius,
+                        color,
+                    ),
+                );
+            }
+        }
+    }
+
+    fn add_object(&mut self, mut obj: Object) {
+        obj.snap();
+        obj.id = self.next_id;
+        self.next_id += 1;
+        web::emit(web::HostEvent::ObjectSpawned {
+            id: obj.id,
+            prefab: obj.prefab.to_string(),
+        });
+        self.objects.push(obj);
+    }
+
+    /// Measure the frame rate once a second and notify the page when it drops.
+    fn update_fps(&mut self, delta_time: f32) {
+        let (frames, time) = &mut self.fps_counter;
+        *time += delta_time;
+        *frames += 1;
+        if *time < 1.0 {
+            return;
+        }
+        let fps = *frames as f32 / *time;
+        self.fps_counter = (0, 0.0);
+        web::emit(web::HostEvent::RenderStats(self.profiler.stats()));
+
+        let low = self.config.low_fps.is_some_and(|threshold| fps < threshold);
+        if low && !self.low_fps {
+            web::emit(web::HostEvent::LowFps { fps });
+        }
+        self.low_fps = low;
+    }
+
+    /// Lower or restore the quality depending on the average frame time.
+    fn update_adaptive_quality(&mut self, delta_time: f32) {
+        let config = &self.config.adaptive_quality;
+        if !config.enabled {
+            self.quality_level = 0;
+            return;
+        }
+        let (frames, time) = &mut self.frame_time_counter;
+        *time += delta_time;
+        *frames += 1;
+        if *time < config.window {
+            return;
+        }
+        let frame_time = *time / *frames as f32 * 1000.0;
+        self.frame_time_counter = (0, 0.0);
+
+        let budget = config
+            .budget
+            .unwrap_or_else(|| 1000.0 / self.target_fps().unwrap_or(60.0) * 1.25);
+        let level = self.quality_level.min(config.levels.len());
+        if frame_time > budget && level < config.levels.len() {
+            log::info!(
+                "Frame time {:.1}ms is over the budget, applying {:?}",
+                frame_time,
+                config.levels[level]
             );
+            self.quality_level = level + 1;
+        } else if frame_time < budget * config.headroom && level > 0 {
+            log::info!(
+                "Frame time {:.1}ms is within the budget, lifting {:?}",
+                frame_time,
+                config.levels[level - 1]
+            );
+            self.quality_level = level - 1;
         }
     }
-}
 
-fn random_spawn(z: f32, view: Aabb2<f32>, rng: &mut impl Rng) -> vec3<f32> {
-    vec3(
-        rng.gen_range(view.min.x..=view.max.x),
-        rng.gen_range(view.min.y..=view.max.y),
-        z,
-    )
-}
+    /// Adaptive quality levels currently applied.
+    fn quality_levels(&self) -> &[QualityLevel] {
+        let levels = &self.config.adaptive_quality.levels;
+        if !self.config.adaptive_quality.enabled {
+            return &[];
+        }
+        &levels[..self.quality_level.min(levels.len())]
+    }
 
-fn draw_flat_section(
-    cross_section: &[CrossSectionVertex],
-    color: Rgba<f32>,
-    camera: &Camera2d,
-    geng: &Geng,
-    framebuffer: &mut ugli::Framebuffer,
-) {
-    if cross_section.len() < 3 {
-        return;
+    /// Set the page scroll offset in pixels for the parallax.
+    pub fn set_scroll(&mut self, offset: f32) {
+        if offset != self.scroll_target {
+            self.idle_time = 0.0;
+        }
+        self.scroll_target = offset;
     }
 
-    // Convert coordinate system
-    let mirror_x = |v: vec2<f32>| vec2(-v.x, v.y);
-    let mut chain: Vec<vec2<f32>> = cross_section
-        .iter()
-        .map(|v| mirror_x(v.projected))
-        .collect();
+    /// Ramp the simulation speed down after a while without input.
+    fn update_idle(&mut self, delta_time: f32) {
+        let config = &self.config.idle;
+        self.idle_time += delta_time;
+        let idle = config.delay.is_some_and(|delay| self.idle_time > delay);
+        let target = if idle { config.time_scale } else { 1.0 };
+        let t = if config.ramp > 0.0 {
+            1.0 - (-delta_time * 3.0 / config.ramp).exp()
+        } else {
+            1.0
+        };
+        self.idle_scale += (target - self.idle_scale) * t;
+    }
 
-    // Optimize small sizes to look better
-    let mut width: f32 = 0.1;
-    let area = Aabb2::points_bounding_box(chain.iter().copied())
-        .expect("there are at least 3 points at this moment");
-    let radius = area.size() / 2.0;
-    width = width.min(radius.x).min(radius.y);
-
-    // Close the chain
-    let mid = (chain[0] + chain[1]) / 2.0;
-    chain.extend([chain[0], mid]);
-    chain[0] = mid;
-
-    // Draw
-    geng.draw2d().draw2d(
-        framebuffer,
-        camera,
-        &draw2d::Chain::new(Chain::new(chain), width, color, 5),
-    );
+    fn handle_command(&mut self, command: web::Command) {
+        match command {
+            web::Command::Pause => self.update_preferences(|prefs| prefs.paused = true),
+            web::Command::Resume => self.update_preferences(|prefs| prefs.paused = false),
+            web::Command::SetReducedMotion(reduce) => {
+                self.update_preferences(|prefs| prefs.reduce_motion = reduce)
+            }
+            web::Command::SetLowQuality(low) => {
+                self.update_preferences(|prefs| prefs.low_quality = low)
+            }
+            web::Command::SetTheme(name) => self.set_theme(&name),
+            web::Command::SetTimeScale(time_scale) => self.set_time_scale(time_scale),
+            web::Command::SpawnBurst(count) => self.spawn_burst(count),
+            web::Command::SetScroll(offset) => self.set_scroll(offset),
+            web::Command::SetDensity(density) => self.set_density(density),
+            web::Command::SetPageSection(id) => self.set_page_section(id),
+            web::Command::SetHostPaused(paused) => self.host_paused = paused,
+            web::Command::SetHidden(hidden) => {
+                self.shown |= self.hidden && !hidden;
+                self.hidden = hidden;
+            }
+            web::Command::SyncCssColors => self.sync_css_colors(),
+        }
+    }
+
+    pub fn set_density(&mut self, density: f32) {
+        if !density.is_finite() || density < 0.0 {
+            log::warn!("Invalid density: {}", density);
+            return;
+        }
+        self.density = density;
+    }
+
+    pub fn set_page_section(&mut self, id: Option<String>) {
+        if let Some(id) = &id {
+            if !self.config.page_sections.contains_key(id) {
+                log::debug!("No parameters for the page section {:?}", id);
+            }
+        }
+        self.page_section = id;
+    }
+
+    fn current_page_section(&self) -> Option<&PageSection> {
+        self.page_section
+            .as_ref()
+            .and_then(|id| self.config.page_sections.get(id))
+    }
+
+    /// Density and speed multipliers of the current time of the day.
+    fn day_blend(&self) -> vec2<f32> {
+        match self.config.day_cycle.blend(self.local_hour) {
+            Some((from, to, t)) => {
+                let from = vec2(from.density, from.speed);
+                let to = vec2(to.density, to.speed);
+                from + (to - from) * t
+            }
+            None => vec2(1.0, 1.0),
+        }
+    }
+
+    /// A color from the palettes of the current time of the day,
+    /// mixed by how far the day is between the two phases.
+    fn day_color(&self, prefab: &str, rng: &mut impl Rng) -> Option<Rgba<f32>> {
+        let (from, to, t) = self.config.day_cycle.blend(self.local_hour)?;
+        if from.palette.is_none() && to.palette.is_none() {
+            return None;
+        }
+        let mut choose = |palette: &Option<Palette>| {
+            palette
+                .as_ref()
+                .and_then(|palette| palette.choose(rng))
+                .unwrap_or_else(|| self.theme.random_color(prefab, rng))
+        };
+        let (a, b) = (choose(&from.palette), choose(&to.palette));
+        Some(Rgba::new(
+            a.r + (b.r - a.r) * t,
+            a.g + (b.g - a.g) * t,
+            a.b + (b.b - a.b) * t,
+            a.a + (b.a - a.a) * t,
+        ))
+    }
+
+    /// Blend the scene parameters towards the ones of the current page section.
+    fn update_page_section(&mut self, delta_time: f32) {
+        let target = self
+            .current_page_section()
+            .map_or(vec2(1.0, 1.0), |section| {
+                vec2(section.density, section.speed)
+            });
+        let transition = self.config.page_transition;
+        let t = if transition > 0.0 {
+            1.0 - (-delta_time * 3.0 / transition).exp()
+        } else {
+            1.0
+        };
+        self.page_blend += (target - self.page_blend) * t;
+    }
+
+    fn update_audio(&mut self, delta_time: f32) {
+        let source = self
+            .config
+            .audio
+            .source
+            .as_ref()
+            .filter(|_| self.geng.is_some());
+        if self.audio.as_ref().map(|audio| audio.source()) != source {
+            self.audio = source.map(AudioAnalyzer::new);
+        }
+        let target = self
+            .audio
+            .as_mut()
+            .map_or(AudioLevels::default(), |audio| audio.levels());
+        self.audio_levels
+            .smooth_towards(target, self.config.audio.smoothing, delta_time);
+    }
+
+    fn update_beat(&mut self, delta_time: f32) {
+        self.beat_time += delta_time;
+        let beat = self
+            .config
+            .beat
+            .position(self.beat_time)
+            .map(|(beat, _)| beat);
+        if beat == self.last_beat {
+            return;
+        }
+        self.last_beat = beat;
+        if beat.is_some_and(|beat| beat % self.config.beat.beats_per_bar as u64 == 0) {
+            self.spawn_burst(self.config.beat.accent);
+        }
+    }
+
+    fn status(&self) -> web::Status {
+        web::Status {
+            paused: self.paused,
+            theme: self.theme_name.clone(),
+            objects: self.objects.len(),
+        }
+    }
+
+    pub fn set_preferences(&mut self, preferences: Preferences) {
+        self.preferences = preferences;
+        self.reduced_motion = self.prefers_reduced_motion();
+    }
+
+    /// Change the preferences and remember them for the next visit.
+    fn update_preferences(&mut self, f: impl FnOnce(&mut Preferences)) {
+        let mut preferences = self.preferences.clone();
+        f(&mut preferences);
+        preferences.save();
+        self.set_preferences(preferences);
+    }
+
+    /// Check whether the device should save power.
+    fn detect_low_power(&self) -> bool {
+        let config = &self.config.low_power;
+        let low_battery = web::battery_status().is_some_and(|battery| {
+            !battery.charging
+                && config
+                    .battery_level
+                    .is_some_and(|level| battery.level < level)
+        });
+        let low_memory = web::device_memory().is_some_and(|memory| {
+            config
+                .device_memory
+                .is_some_and(|threshold| memory <= threshold)
+        });
+        low_battery || low_memory
+    }
+
+    /// The low power profile, if it is active.
+    fn power_profile(&self) -> Option<&PowerProfile> {
+        (self.low_power || self.preferences.low_quality).then_some(&self.config.low_power.profile)
+    }
+
+    /// Frame rate limit, lowered by the power profile.
+    fn target_fps(&self) -> Option<f32> {
+        let fps = self.config.target_fps;
+        match self.power_profile() {
+            Some(profile) => Some(fps.unwrap_or(60.0) * profile.fps_scale),
+            None => fps,
+        }
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        if !time_scale.is_finite() || time_scale < 0.0 {
+            log::warn!("Invalid time scale: {}", time_scale);
+            return;
+        }
+        self.config.time_scale = time_scale;
+    }
+
+    /// Spawn objects at random positions in the view, ignoring the spawn rate.
+    pub fn spawn_burst(&mut self, count: usize) {
+        let view = self.view();
+        for _ in 0..count {
+            let position = vec2(
+                self.rng.gen_range(view.min.x..=view.max.x),
+                self.rng.gen_range(view.min.y..=view.max.y),
+            );
+            self.spawn_at(position);
+        }
+    }
+
+    /// Burst of objects in special colors, then back to normal after a while.
+    fn trigger_easter_egg(&mut self) {
+        let config = self.config.easter_egg.clone();
+        self.scene_override = Some(SceneOverride {
+            palette: config.palette,
+            time_left: config.duration,
+        });
+
+        let view = self.view();
+        let mut rng = self.rng.clone();
+        for _ in 0..config.count {
+            let Some((prefab, geometry)) = self.choose_prefab(&mut rng) else {
+                break;
+            };
+            let position = vec2(
+                rng.gen_range(view.min.x..=view.max.x),
+                rng.gen_range(view.min.y..=view.max.y),
+            );
+            self.spawn_prefab_at(prefab, geometry, position);
+        }
+        self.rng = rng;
+    }
+
+    /// Play the timeline events that are due, starting over when it repeats.
+    fn update_timeline(&mut self, delta_time: f32) {
+        if self.config.timeline.events.is_empty() {
+            return;
+        }
+        self.timeline_time += delta_time;
+        loop {
+            let timeline = &self.config.timeline;
+            if let Some(event) = timeline.events.get(self.timeline_index) {
+                if event.time <= self.timeline_time
+                    && timeline.repeat.map_or(true, |repeat| event.time < repeat)
+                {
+                    let action = event.action.clone();
+                    self.timeline_index += 1;
+                    self.play_timeline_action(action);
+                    continue;
+                }
+            }
+            match timeline.repeat {
+                Some(repeat) if self.timeline_time >= repeat => {
+                    self.timeline_time -= repeat;
+                    self.timeline_index = 0;
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn play_timeline_action(&mut self, action: TimelineAction) {
+        log::debug!("Timeline: {:?}", action);
+        match action {
+            TimelineAction::Theme(name) => self.set_theme(&name),
+            TimelineAction::Palette { palette, duration } => {
+                self.scene_override = Some(SceneOverride {
+                    palette,
+                    time_left: duration,
+                });
+            }
+            TimelineAction::Burst(count) => self.spawn_burst(count),
+            TimelineAction::Tilt(angle) => self.tilt.angle += angle,
+            TimelineAction::Motion(motion) => self.config.motion = motion,
+        }
+    }
+
+    fn update_scene_override(&mut self, delta_time: f32) {
+        if let Some(scene) = &mut self.scene_override {
+            scene.time_left -= delta_time;
+            if scene.time_left <= 0.0 {
+                self.scene_override = None;
+            }
+        }
+    }
+
+    /// Smoothly follow the scroll position with the camera and the objects.
+    fn update_scroll(&mut self, delta_time: f32) {
+        let config = &self.config.parallax;
+        let t = 1.0 - (-config.smoothing * delta_time).exp();
+        let delta = (self.scroll_target - self.scroll) * t;
+        self.scroll += delta;
+
+        let depth_shift =
+            |scale: f32| vec3(0.0, -delta * config.strength * config.depth * scale, 0.0);
+        // The clusters move as a whole, by the average scale of their members
+        let mut cluster_scales: HashMap<u64, (f32, f32)> = HashMap::new();
+        for obj in &self.objects {
+            if let Some(orbit) = obj.orbit {
+                let (sum, count) = cluster_scales.entry(orbit.cluster).or_default();
+                *sum += obj.scale;
+                *count += 1.0;
+            }
+        }
+        let mut cluster_shifts = HashMap::new();
+        for (id, cluster) in &mut self.clusters {
+            if let Some(&(sum, count)) = cluster_scales.get(id) {
+                let shift = depth_shift(sum / count);
+                cluster.center += shift;
+                cluster_shifts.insert(*id, shift);
+            }
+        }
+        for obj in &mut self.objects {
+            let shift = obj
+                .orbit
+                .and_then(|orbit| cluster_shifts.get(&orbit.cluster).copied())
+                .unwrap_or_else(|| depth_shift(obj.scale));
+            obj.shift(shift);
+        }
+        // Scrolling down moves the camera down
+        self.shift_layers(vec2(0.0, -delta * config.strength));
+    }
+
+    /// Move the layers along with the camera moved by the parallax,
+    /// so that they appear to move slower the lower their parallax factor.
+    fn shift_layers(&mut self, camera_delta: vec2<f32>) {
+        if self.config.layers.is_empty() {
+            return;
+        }
+        let shift = |layer: Layer| (camera_delta * (1.0 - layer.parallax)).extend(0.0);
+        for cluster in self.clusters.values_mut() {
+            cluster.center += shift(self.config.layer(cluster.layer));
+        }
+        for obj in &mut self.objects {
+            obj.shift(shift(self.config.layer(obj.layer)));
+        }
+    }
+
+    /// Smoothly follow the device orientation.
+    fn update_gyroscope(&mut self, delta_time: f32) {
+        let Some((gamma, beta)) = web::device_orientation() else {
+            return;
+        };
+        let orientation = vec2(gamma, beta);
+        let neutral = *self.gyro_neutral.get_or_insert(orientation);
+        let max = self.config.gyroscope.max_angle;
+        let target = (orientation - neutral).map(|x| x.clamp(-max, max));
+        let t = 1.0 - (-self.config.parallax.smoothing * delta_time).exp();
+        let delta = (target - self.gyro_tilt) * t;
+        self.gyro_tilt += delta;
+        // Same as in the camera
+        let camera_delta = vec2(delta.x, -delta.y) * self.config.gyroscope.sensitivity;
+        self.shift_layers(camera_delta);
+    }
+
+    fn update_camera(&mut self) {
+        // Scrolling down moves the content up
+        let scroll = vec2(0.0, -self.scroll * self.config.parallax.strength);
+        // Tilting the top of the device away looks further up
+        let gyro = vec2(self.gyro_tilt.x, -self.gyro_tilt.y) * self.config.gyroscope.sensitivity;
+        self.camera2d.center = scroll + gyro;
+    }
+
+    /// The configured plane, tilted by the user.
+    fn cross_plane(&self) -> Plane {
+        let plane = &self.config.cross_plane;
+        let tilt = (self.tilt.angle + self.gyro_tilt * self.config.gyroscope.plane_tilt)
+            .map(Angle::from_degrees);
+        let rotation = mat4::rotate_y(tilt.x) * mat4::rotate_x(tilt.y);
+        let sweep = self.config.sweep.as_ref();
+        Plane {
+            normal: (rotation * plane.normal.extend(0.0)).xyz(),
+            offset: plane.offset + sweep.map_or(0.0, |sweep| sweep.offset(self.simulation_time)),
+        }
+    }
+
+    /// Pull the plane back to its configured orientation when not dragging.
+    fn update_tilt(&mut self, delta_time: f32) {
+        if self.drag.is_some() {
+            return;
+        }
+        // Critically damped spring
+        let stiffness = self.config.drag_tilt.stiffness;
+        let damping = 2.0 * stiffness.sqrt();
+        let tilt = &mut self.tilt;
+        let acceleration = -tilt.angle * stiffness - tilt.velocity * damping;
+        tilt.velocity += acceleration * delta_time;
+        tilt.angle += tilt.velocity * delta_time;
+    }
+
+    fn drag_move(&mut self, touch: Option<u64>, position: vec2<f32>) {
+        let Some(drag) = self.drag.as_mut().filter(|drag| drag.touch == touch) else {
+            return;
+        };
+        let delta = position - drag.start;
+        if !drag.moved && delta.len() < 5.0 {
+            return;
+        }
+        drag.moved = true;
+        let config = &self.config.drag_tilt;
+        if config.is_active() {
+            let max = config.max_angle;
+            self.tilt.angle = (delta * config.sensitivity).map(|x| x.clamp(-max, max));
+            self.tilt.velocity = vec2::ZERO;
+        }
+    }
+
+    /// Pop or spawn an object at the screen position.
+    fn click(&mut self, position: vec2<f32>) {
+        let position = self.screen_to_world(position);
+        if self.open_link_at(position) {
+            return;
+        }
+        let popped = self.config.click_pop && self.pop_at(position);
+        if !popped {
+            self.cursor_ripple(position);
+            if self.config.click_spawn {
+                self.spawn_at(position);
+            }
+        }
+    }
+
+    fn cursor_ripple(&mut self, position: vec2<f32>) {
+        let config = &self.config.cursor_ripple;
+        if !config.enabled || !self.show_effects() {
+            return;
+        }
+        let color = self.theme.random_object_color(&mut self.rng);
+        self.effects
+            .ripple(position, config.radius, self.theme.stroke.width, color);
+    }
+
+    /// Emit ripples when the cursor moves quickly.
+    fn update_cursor_ripple(&mut self, delta_time: f32) {
+        self.ripple_cooldown -= delta_time;
+        let cursor = self.cursor_world();
+        let last = std::mem::replace(&mut self.last_cursor, cursor);
+        let (Some(cursor), Some(last)) = (cursor, last) else {
+            return;
+        };
+        if delta_time <= 0.0 || self.ripple_cooldown > 0.0 {
+            return;
+        }
+        let speed = (cursor - last).len() / delta_time;
+        if speed > self.config.cursor_ripple.speed_threshold {
+            self.ripple_cooldown = self.config.cursor_ripple.cooldown;
+            self.cursor_ripple(cursor);
+        }
+    }
+
+    /// Find the section under the given world position.
+    fn section_at(&self, position: vec2<f32>) -> Option<&Section> {
+        // Sections drawn last are on top
+        self.sections
+            .iter()
+            .rev()
+            .find(|section| geometry::point_in_polygon(position, &section.points))
+    }
+
+    /// Open the link of the object whose section is under the position.
+    /// Returns `false` if there is no section with a link there.
+    fn open_link_at(&mut self, position: vec2<f32>) -> bool {
+        let Some(section) = self.section_at(position) else {
+            return false;
+        };
+        let center = section.center();
+        let Some(obj) = self.objects.iter().find(|obj| obj.id == section.object) else {
+            return false;
+        };
+        let Some(link) = &obj.link else {
+            return false;
+        };
+        let (url, color, scale) = (link.url.clone(), obj.color, obj.scale);
+
+        // Click feedback
+        if self.show_effects() {
+            let width = self.theme.stroke.width;
+            self.effects.ripple(center, scale * 2.0, width, color);
+            self.effects.ripple(center, scale * 3.0, width, color);
+        }
+        web::open_link(&url);
+        true
+    }
+
+    /// Remove the object whose section is under the position with a burst effect.
+    /// Returns `false` if there is no section there.
+    fn pop_at(&mut self, position: vec2<f32>) -> bool {
+        let Some(section) = self.section_at(position) else {
+            return false;
+        };
+        let id = section.object;
+        let center = section.center();
+        let radius = section
+            .points
+            .iter()
+            .map(|p| r32((*p - center).len()))
+            .max()
+            .map_or(0.0, |r| r.as_f32());
+
+        let Some(index) = self.objects.iter().position(|obj| obj.id == id) else {
+            return false;
+        };
+        let obj = self.objects.swap_remove(index);
+        self.sections.retain(|section| section.object != id);
+        // Shards are not reported to the page
+        if obj.shard.is_none() {
+            web::emit(web::HostEvent::ObjectExited { id });
+        }
+
+        if self.show_effects() {
+            self.effects.burst(center, obj.color, &mut self.rng);
+            self.effects
+                .ripple(center, radius * 2.0, self.theme.stroke.width, obj.color);
+        }
+        true
+    }
+
+    /// Convert a position on the screen to the world coordinates.
+    fn screen_to_world(&self, position: vec2<f32>) -> vec2<f32> {
+        self.camera2d
+            .screen_to_world(self.screen_size.as_f32(), position)
+    }
+
+    /// Cursor position in the world coordinates.
+    fn cursor_world(&self) -> Option<vec2<f32>> {
+        self.cursor.map(|position| self.screen_to_world(position))
+    }
+
+    /// Positions of the cursor and all the touches in the world coordinates.
+    fn pointers_world(&self) -> Vec<vec2<f32>> {
+        self.cursor_world()
+            .into_iter()
+            .chain(
+                self.touches
+                    .values()
+                    .map(|&position| self.screen_to_world(position)),
+            )
+            .collect()
+    }
+
+    /// Push the objects away from or pull them towards the cursor and the touches.
+    fn apply_cursor_field(&mut self, delta_time: f32) {
+        let field = &self.config.cursor_field;
+        if !field.is_active() {
+            return;
+        }
+        for cursor in self.pointers_world() {
+            for obj in &mut self.objects {
+                let delta = obj.position.xy() - cursor;
+                let distance = delta.len();
+                if distance < field.radius {
+                    let step = field.strength * (1.0 - distance / field.radius) * delta_time;
+                    let shift = match field.mode {
+                        CursorMode::None => 0.0,
+                        CursorMode::Repel => step,
+                        // Don't overshoot the cursor
+                        CursorMode::Attract => -step.min(distance),
+                    };
+                    obj.position += (delta.normalize_or_zero() * shift).extend(0.0);
+                }
+            }
+        }
+    }
+
+    /// Push the overlapping objects apart, treating them as their bounding spheres.
+    /// Only a part of the overlap is resolved every step, so that the objects nudge each other.
+    fn resolve_collisions(&mut self, delta_time: f32) {
+        let max_scale = self.rebuild_spatial_hash();
+        let t = (COLLISION_STIFFNESS * delta_time).min(1.0);
+        for i in 0..self.objects.len() {
+            let position = self.objects[i].position.xy();
+            let reach = (self.objects[i].scale + max_scale) * OVERLAP_DISTANCE;
+            for j in self.spatial_hash.query(position, reach) {
+                // Every pair once
+                if j <= i {
+                    continue;
+                }
+                let (a, b) = (&self.objects[i], &self.objects[j]);
+                if a.shard.is_some() || b.shard.is_some() {
+                    continue;
+                }
+                // The orbit keeps the objects of a cluster apart
+                if let (Some(a), Some(b)) = (a.orbit, b.orbit) {
+                    if a.cluster == b.cluster {
+                        continue;
+                    }
+                }
+                let delta = b.position - a.position;
+                let overlap = (a.scale + b.scale) * OVERLAP_DISTANCE - delta.len();
+                if overlap <= 0.0 {
+                    continue;
+                }
+                // The smaller object gets pushed further
+                let share = b.scale / (a.scale + b.scale);
+                let push = delta.normalize_or_zero() * overlap * t;
+                self.objects[i].position -= push * share;
+                self.objects[j].position += push * (1.0 - share);
+            }
+        }
+    }
+
+    /// Link the objects close to each other and pull them together with weak springs.
+    fn update_constellation(&mut self, delta_time: f32) {
+        self.links.clear();
+        let config = &self.config.constellation;
+        if !config.enabled {
+            return;
+        }
+        let (distance, strength) = (config.distance, config.strength);
+        let rest = distance / 2.0;
+        let t = (strength * delta_time).min(1.0);
+        // Reuse the hash from the collisions when possible
+        if !self.config.collisions {
+            self.rebuild_spatial_hash();
+        }
+        for i in 0..self.objects.len() {
+            let position = self.objects[i].position.xy();
+            for j in self.spatial_hash.query(position, distance) {
+                if j <= i || self.objects[i].shard.is_some() || self.objects[j].shard.is_some() {
+                    continue;
+                }
+                let delta = self.objects[j].position - self.objects[i].position;
+                let length = delta.len();
+                if length >= distance {
+                    continue;
+                }
+                self.links.push((self.objects[i].id, self.objects[j].id));
+                let stretch = length - rest;
+                if stretch > 0.0 {
+                    let pull = delta.normalize_or_zero() * stretch * t / 2.0;
+                    self.objects[i].position += pull;
+                    self.objects[j].position -= pull;
+                }
+            }
+        }
+    }
+
+    pub fn view(&self) -> Aabb2<f32> {
+        let view = vec2(
+            self.camera2d.fov * self.screen_size.as_f32().aspect(),
+            self.camera2d.fov,
+        );
+        Aabb2::point(self.camera2d.center).extend_symmetric(view)
+    }
+
+    /// How much the spawn parameters are scaled to keep the density constant.
+    fn density_scale(&self) -> f32 {
+        match self.config.reference_area {
+            Some(reference) => {
+                let size = self.view().size();
+                size.x * size.y / reference
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Fewer objects with the low power profile.
+    fn quality_scale(&self) -> f32 {
+        self.power_profile()
+            .map_or(1.0, |profile| profile.density_scale)
+    }
+
+    /// Combined multiplier for the number of objects.
+    fn density_multiplier(&self) -> f32 {
+        self.density
+            * self.page_blend.x
+            * self.day_blend().x
+            * self.quality_scale()
+            * self.density_scale()
+    }
+
+    fn spawn_rate(&self) -> f32 {
+        let adaptive: f32 = self
+            .quality_levels()
+            .iter()
+            .map(|level| match *level {
+                QualityLevel::SpawnRate(scale) => scale,
+                _ => 1.0,
+            })
+            .product();
+        let audio = 1.0 + self.config.audio.spawn_rate * self.audio_levels.bass;
+        self.config.spawn_rate * self.density_multiplier() * adaptive * audio
+    }
+
+    fn max_objects(&self) -> usize {
+        (self.config.max_objects as f32 * self.density_multiplier()).round() as usize
+    }
+
+    /// Advance the simulation.
+    fn step(&mut self, delta_time: f32) {
+        for obj in &mut self.objects {
+            obj.snap();
+        }
+        self.simulation_time += delta_time;
+        let spawn_interval = 1.0 / self.spawn_rate();
+        let max_objects = self.max_objects();
+        // Limit the debt accumulated over a long step
+        let max_debt = spawn_interval * self.config.max_spawns_per_step as f32;
+        self.next_spawn = (self.next_spawn.min(spawn_interval) - delta_time).max(-max_debt);
+        let mut rng = self.rng.clone();
+        let mut max_scale = self.rebuild_spatial_hash();
+        // Once the area is full, the rest of the spawns this step would fail just as slowly
+        let mut area_full = false;
+        while self.next_spawn < 0.0 {
+            self.next_spawn += spawn_interval;
+            if self.objects.len() >= max_objects || area_full {
+                continue;
+            }
+            if let Some((prefab, geometry)) = self.choose_prefab(&mut rng) {
+                let layer = self.choose_layer(&mut rng);
+                let scale = random_scale(&mut rng) * self.config.layer(layer).scale;
+                // The objects do not travel through the sweeping plane
+                let reversed =
+                    self.config.sweep.is_none() && rng.gen_bool(self.config.reverse_chance.into());
+                let pos_z = self.spawn_depth(scale, &mut rng) * if reversed { -1.0 } else { 1.0 };
+
+                // A cluster takes the room of its whole orbit, with the largest possible members
+                let cluster = rng.gen_bool(self.config.cluster_chance as f64);
+                let footprint = if cluster {
+                    let largest = self.config.layer(layer).scale;
+                    cluster_radius(largest, MAX_CLUSTER_SIZE) / OVERLAP_DISTANCE + largest
+                } else {
+                    scale
+                };
+
+                let spawn_area = self.view().extend_uniform(self.config.spawn_margin);
+                let pos = self.sample_spawn(pos_z, footprint, max_scale, spawn_area, &mut rng);
+                if pos.is_none() {
+                    log::debug!("No space left to spawn an object of scale {footprint}");
+                    area_full = true;
+                }
+
+                if let Some(pos) = pos {
+                    let mut obj = Object::new(pos, prefab, geometry);
+                    obj.scale = scale;
+                    obj.layer = layer;
+                    obj.reversed = reversed;
+                    self.randomize_object(&mut obj, &mut rng);
+                    let first = self.objects.len();
+                    if cluster {
+                        self.spawn_cluster(obj, &mut rng);
+                    } else {
+                        self.add_object(obj);
+                    }
+                    for (i, obj) in self.objects.iter().enumerate().skip(first) {
+                        self.spatial_hash.insert(i, obj.position.xy());
+                        max_scale = max_scale.max(obj.scale);
+                    }
+                }
+            }
+        }
+        self.rng = rng;
+
+        let (speed_scale, rotation_scale) = self.motion_scale();
+        let drift = self.config.sweep.as_ref().map_or(1.0, |sweep| sweep.drift);
+        let speed =
+            self.config.speed * speed_scale * self.page_blend.y * self.day_blend().y * drift;
+        let time = self.simulation_time;
+        let wind = self.config.wind.velocity(time);
+        for cluster in self.clusters.values_mut() {
+            let speed = speed * self.config.layer(cluster.layer).speed;
+            cluster.center += (cluster.velocity + wind) * speed * delta_time;
+            let orbit = vec3::UNIT_Z * cluster.angular_speed * rotation_scale * delta_time;
+            cluster.rotation = (cluster.rotation * Rotation::from_scaled_axis(orbit)).normalize();
+        }
+        for obj in &mut self.objects {
+            obj.rotate(rotation_scale * delta_time);
+            if let Some(orbit) = obj.orbit {
+                if let Some(cluster) = self.clusters.get(&orbit.cluster) {
+                    obj.position = cluster.position(orbit.offset);
+                    continue;
+                }
+            }
+            for attractor in &self.config.attractors {
+                let pull = attractor.pull(obj.position.xy());
+                obj.velocity += pull.extend(0.0) * delta_time;
+            }
+            let velocity = match &self.config.motion {
+                Motion::Drift => obj.velocity,
+                Motion::Flow(flow) => obj.velocity + flow.velocity(obj.position, time),
+            } + wind;
+            let speed = speed * self.config.layer(obj.layer).speed;
+            obj.position += velocity * speed * delta_time;
+        }
+        self.apply_cursor_field(delta_time * speed_scale);
+        if self.config.collisions {
+            self.resolve_collisions(delta_time);
+        }
+        self.update_constellation(delta_time);
+        let pulse = 1.0 + self.config.audio.pulse * self.audio_levels.mid;
+        let fade_time = self.config.fracture.fade_time;
+        for obj in &mut self.objects {
+            obj.wobble = self.config.wobble.offset(obj.position, time) * speed_scale;
+            obj.age += delta_time;
+            if let Some(shard) = &mut obj.shard {
+                shard.time_left -= delta_time;
+                obj.color.a = shard.alpha * (shard.time_left / fade_time).clamp(0.0, 1.0);
+                continue;
+            }
+            let lifetime = &self.config.lifetime;
+            let size = match &self.config.sweep {
+                // Shrinking before the end of the lifetime the same way as growing
+                Some(sweep) => {
+                    lifetime.size(obj.age, f32::INFINITY)
+                        * lifetime.size(sweep.lifetime - obj.age, f32::INFINITY)
+                }
+                None => lifetime.size(obj.age, obj.scale * 2.0 - obj.depth()),
+            };
+            let breathing = obj
+                .breathing
+                .map_or(1.0, |breathing| breathing.factor(obj.age));
+            obj.size = size * pulse * breathing;
+            if let Some(geometry) = obj.morph.as_mut().and_then(|morph| morph.update(obj.age)) {
+                obj.geometry = geometry;
+            }
+        }
+        self.fracture_objects();
+        self.detect_hit_stops(delta_time);
+        self.effects.update(delta_time);
+        // Delete far objects
+        self.objects.retain(|obj| {
+            if let Some(shard) = obj.shard {
+                return shard.time_left > 0.0;
+            }
+            let keep = match &self.config.sweep {
+                Some(sweep) => obj.age < sweep.lifetime,
+                None => obj.depth() < obj.scale * 2.0,
+            };
+            if !keep {
+                web::emit(web::HostEvent::ObjectExited { id: obj.id });
+            }
+            keep
+        });
+        let objects = &self.objects;
+        self.clusters.retain(|id, _| {
+            objects
+                .iter()
+                .any(|obj| obj.orbit.is_some_and(|orbit| orbit.cluster == *id))
+        });
+        // Drawn in order, so the far layers go first.
+        // The sort is stable and the objects mostly in place, so it is cheap
+        if !self.config.layers.is_empty() {
+            self.objects.sort_by_key(|obj| obj.layer);
+        }
+    }
+
+    /// Slow down the time and flash the outline when a large object first reaches the plane.
+    fn detect_hit_stops(&mut self, delta_time: f32) {
+        let config = &self.config.hit_stop;
+        for obj in &mut self.objects {
+            obj.flash = (obj.flash - delta_time).max(0.0);
+        }
+        if !config.enabled {
+            return;
+        }
+        let cross_plane = self.cross_plane();
+        let mut hits = 0;
+        for obj in &mut self.objects {
+            if obj.touched || obj.shard.is_some() || obj.scale < config.min_scale {
+                continue;
+            }
+            let distance = cross_plane.distance(obj.position);
+            let reach = obj.scale * OVERLAP_DISTANCE;
+            let reached = match &self.config.sweep {
+                // The plane comes from either side
+                Some(_) => distance.abs() < reach,
+                None => distance * obj.direction() >= -reach,
+            };
+            if !reached {
+                continue;
+            }
+            obj.touched = true;
+            obj.flash = config.flash_time;
+            hits += 1;
+        }
+        // Simultaneous hits stop the time once
+        if hits > 0 {
+            self.time_modifiers.push(TimeModifier {
+                scale: config.time_scale,
+                duration: config.duration,
+                elapsed: 0.0,
+                easing: config.easing,
+            });
+        }
+    }
+
+    /// Advance the slowdowns by the real time and return their combined time scale.
+    fn update_time_modifiers(&mut self, delta_time: f32) -> f32 {
+        for modifier in &mut self.time_modifiers {
+            modifier.elapsed += delta_time;
+        }
+        self.time_modifiers
+            .retain(|modifier| modifier.elapsed < modifier.duration);
+        self.time_modifiers
+            .iter()
+            .map(TimeModifier::scale)
+            .product()
+    }
+
+    /// Break the objects leaving the plane into shards.
+    fn fracture_objects(&mut self) {
+        // The objects do not leave through the sweeping plane
+        if !self.config.fracture.enabled || self.config.sweep.is_some() {
+            return;
+        }
+        let mut rng = self.rng.clone();
+        let mut i = 0;
+        while i < self.objects.len() {
+            let obj = &self.objects[i];
+            if obj.shard.is_some() || obj.depth() < obj.scale * FRACTURE_DEPTH {
+                i += 1;
+                continue;
+            }
+            let obj = self.objects.swap_remove(i);
+            web::emit(web::HostEvent::ObjectExited { id: obj.id });
+            for mut shard in self.shatter(&obj, &mut rng) {
+                // Not reported to the page, as the object has already exited
+                shard.id = self.next_id;
+                self.next_id += 1;
+                self.objects.push(shard);
+            }
+        }
+        self.rng = rng;
+    }
+
+    /// Cut the object into pieces flying apart from its center.
+    fn shatter(&self, obj: &Object, rng: &mut impl Rng) -> Vec<Object> {
+        let config = &self.config.fracture;
+        let mut pieces = vec![obj.geometry.full().to_vec()];
+        for _ in 0..config.cuts {
+            let plane = Plane {
+                normal: random_direction(rng),
+                offset: rng.gen_range(-0.3..=0.3),
+            };
+            pieces = pieces
+                .iter()
+                .flat_map(|piece| {
+                    let (front, back) = geometry::cut(piece, &plane);
+                    [front, back]
+                })
+                .filter(|piece| !piece.is_empty())
+                .collect();
+        }
+
+        let matrix = obj.rotation.matrix() * mat4::scale_uniform(obj.scale * obj.size);
+        pieces
+            .into_iter()
+            .map(|mut piece| {
+                let vertex_count = piece.len() as f32 * 3.0;
+                let center = piece
+                    .iter()
+                    .flat_map(|triangle| triangle.vertices)
+                    .fold(vec3::ZERO, vec3::add)
+                    / vertex_count;
+                // Around its own center, so that the shard spins in place
+                for triangle in &mut piece {
+                    for v in &mut triangle.vertices {
+                        *v -= center;
+                    }
+                }
+                let offset = (matrix * center.extend(1.0)).xyz();
+                let geometry = Lods::generate(Mesh::Shared(piece.into()), 0);
+                let mut shard = Object::new(obj.position + offset, obj.prefab.clone(), geometry);
+                shard.velocity = obj.velocity + offset.normalize_or_zero() * config.spread;
+                shard.rotation = obj.rotation;
+                shard.angular_velocity = obj.angular_velocity
+                    + random_direction(rng) * rng.gen_range(ANGULAR_SPEED).to_radians();
+                shard.wobble = obj.wobble;
+                shard.scale = obj.scale;
+                shard.age = obj.age;
+                shard.size = obj.size;
+                shard.color = obj.color;
+                shard.layer = obj.layer;
+                shard.reversed = obj.reversed;
+                shard.shard = Some(Shard {
+                    time_left: config.fade_time,
+                    alpha: obj.color.a,
+                });
+                shard.snap();
+                shard
+            })
+            .collect()
+    }
+
+    /// Bind the object together with one or two new ones, orbiting their common center.
+    fn spawn_cluster(&mut self, first: Object, rng: &mut impl Rng) {
+        let center = first.position;
+        let velocity = first.velocity;
+        let first_layer = first.layer;
+        let reversed = first.reversed;
+        let mut members = vec![first];
+        for _ in 1..rng.gen_range(2..=MAX_CLUSTER_SIZE) {
+            let Some((prefab, geometry)) = self.choose_prefab(rng) else {
+                continue;
+            };
+            let mut obj = Object::new(center, prefab, geometry);
+            obj.scale = random_scale(rng) * self.config.layer(first_layer).scale;
+            obj.layer = first_layer;
+            obj.reversed = reversed;
+            self.randomize_object(&mut obj, rng);
+            members.push(obj);
+        }
+        if members.len() < 2 {
+            self.add_object(members.pop().unwrap());
+            return;
+        }
+
+        let id = self.next_cluster_id;
+        self.next_cluster_id += 1;
+        let cluster = Cluster {
+            center,
+            velocity,
+            rotation: Rotation {
+                w: rng.gen_range(-1.0..=1.0),
+                v: random_direction(rng),
+            }
+            .normalize(),
+            angular_speed: rng.gen_range(ORBIT_SPEED).to_radians(),
+            layer: first_layer,
+        };
+        let count = members.len();
+        let largest = members.iter().map(|obj| obj.scale).fold(0.0, f32::max);
+        let radius = cluster_radius(largest, count);
+        for (i, mut obj) in members.into_iter().enumerate() {
+            let angle = Angle::from_radians(std::f32::consts::TAU * i as f32 / count as f32);
+            let offset = vec2(radius, 0.0).rotate(angle).extend(0.0);
+            obj.orbit = Some(Orbit {
+                cluster: id,
+                offset,
+            });
+            obj.velocity = velocity;
+            obj.position = cluster.position(offset);
+            self.add_object(obj);
+        }
+        self.clusters.insert(id, cluster);
+    }
+
+    /// Find a place for a new object in the spawn area, not overlapping the others.
+    /// Uses the Poisson-disk sampling by Bridson seeded by the existing objects:
+    /// after a random place, candidates are tried in the ring around a few random objects.
+    /// The number of candidates is bounded, so that a full area fails quickly.
+    fn sample_spawn(
+        &self,
+        z: f32,
+        scale: f32,
+        max_scale: f32,
+        area: Aabb2<f32>,
+        rng: &mut impl Rng,
+    ) -> Option<vec3<f32>> {
+        let pos = random_spawn(z, area, rng);
+        if !self.overlaps(pos, scale, max_scale) {
+            return Some(pos);
+        }
+
+        for _ in 0..POISSON_SEEDS {
+            let obj = self.objects.choose(rng)?;
+            if obj.shard.is_some() || !area.contains(obj.position.xy()) {
+                continue;
+            }
+            let radius = (scale + obj.scale) * OVERLAP_DISTANCE;
+            for _ in 0..POISSON_ATTEMPTS {
+                let angle = Angle::from_radians(rng.gen_range(0.0..std::f32::consts::TAU));
+                let offset = vec2(rng.gen_range(radius..2.0 * radius), 0.0).rotate(angle);
+                let pos = (obj.position.xy() + offset).extend(z);
+                if area.contains(pos.xy()) && !self.overlaps(pos, scale, max_scale) {
+                    return Some(pos);
+                }
+            }
+        }
+        None
+    }
+
+    /// Whether a new object at the position would overlap any other,
+    /// given the largest scale in the spatial hash.
+    fn overlaps(&self, pos: vec3<f32>, scale: f32, max_scale: f32) -> bool {
+        let reach = (scale + max_scale) * OVERLAP_DISTANCE;
+        self.spatial_hash.query(pos.xy(), reach).any(|i| {
+            let obj = &self.objects[i];
+            let dist = (pos - obj.position).len();
+            dist < (scale + obj.scale) * OVERLAP_DISTANCE
+        })
+    }
+
+    /// Index the objects by their position for the spawn overlap checks.
+    /// Returns the largest object scale.
+    fn rebuild_spatial_hash(&mut self) -> f32 {
+        let max_scale = self.objects.iter().map(|obj| obj.scale).fold(1.0, f32::max);
+        // Neighbours of a new object are at most in the adjacent cells
+        self.spatial_hash.clear(max_scale * 2.0 * OVERLAP_DISTANCE);
+        for (i, obj) in self.objects.iter().enumerate() {
+            self.spatial_hash.insert(i, obj.position.xy());
+        }
+        max_scale
+    }
+
+    /// Pick a random prefab according to the configured weights.
+    /// Nothing is spawned while the geometry of the picked prefab is still being prepared.
+    fn choose_prefab(&mut self, rng: &mut impl Rng) -> Option<(Rc<str>, Lods)> {
+        let (name, shape) = Self::pick_prefab(&self.config, rng)?;
+        if !self.prefab_registry.is_ready(shape) {
+            return None;
+        }
+        Some(self.prefab_registry.get(name, shape))
+    }
+
+    /// Index of a random layer by the weights, always 0 if there are no layers.
+    fn choose_layer(&self, rng: &mut impl Rng) -> usize {
+        let layers = &self.config.layers;
+        let total: f32 = layers.iter().map(|layer| layer.weight).sum();
+        if total <= 0.0 {
+            return 0;
+        }
+        let mut pick = rng.gen_range(0.0..total);
+        for (index, layer) in layers.iter().enumerate() {
+            if pick < layer.weight {
+                return index;
+            }
+            pick -= layer.weight;
+        }
+        layers.len() - 1
+    }
+
+    fn pick_prefab<'a>(config: &'a Config, rng: &mut impl Rng) -> Option<(&'a String, &'a Shape)> {
+        let prefabs = &config.prefabs;
+        let weights = &config.prefab_weights;
+        if weights.is_empty() {
+            return prefabs.iter().choose(rng);
+        }
+
+        // Walk the cumulative weights instead of collecting the prefabs to avoid allocating
+        let weight = |name: &str| weights.get(name).copied().unwrap_or(0.0).max(0.0);
+        let total: f32 = prefabs.keys().map(|name| weight(name)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0.0..total);
+        let mut last = None;
+        for prefab in prefabs {
+            let weight = weight(prefab.0);
+            if weight <= 0.0 {
+                continue;
+            }
+            if pick < weight {
+                return Some(prefab);
+            }
+            pick -= weight;
+            last = Some(prefab);
+        }
+        // Rounding errors
+        last
+    }
+}
+
+impl geng::State for State {
+    fn update(&mut self, delta_time: f64) {
+        let delta_time = delta_time as f32;
+        let timer = self.profiler.start();
+
+        #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+        if let Some(playback) = &mut self.playback {
+            match playback.next() {
+                Some(frame) => {
+                    self.screen_size = frame.screen_size;
+                    for event in frame.events {
+                        self.handle_input(event);
+                    }
+                    self.render_frame = true;
+                    self.update_frame(frame.delta_time);
+                    self.profiler.record(Phase::Update, timer);
+                    return;
+                }
+                None => {
+                    log::info!("Replay finished");
+                    self.playback = None;
+                }
+            }
+        }
+
+        let commands = web::take_commands();
+        let handled_commands = !commands.is_empty();
+        for command in commands {
+            self.handle_command(command);
+        }
+
+        let hidden = !self.focused || self.hidden;
+        let paused =
+            self.preferences.paused || self.host_paused || self.config.pause_when_hidden && hidden;
+        let was_paused = std::mem::replace(&mut self.paused, paused);
+        let shown = std::mem::take(&mut self.shown);
+        let resumed = was_paused && !paused || self.config.pause_when_hidden && shown;
+        if handled_commands || was_paused != paused {
+            web::post_status(&self.status());
+        }
+        if paused {
+            // Keep presenting the frozen scene
+            self.render_frame = self.frame_limiter.frame(self.target_fps());
+            self.skipped_time = 0.0;
+            return;
+        }
+        // The time spent paused should not be simulated
+        let delta_time = if resumed { 0.0 } else { delta_time };
+
+        self.render_frame = self.frame_limiter.frame(self.target_fps());
+        if !self.render_frame {
+            self.skipped_time += delta_time;
+            return;
+        }
+        let delta_time = delta_time + std::mem::take(&mut self.skipped_time);
+        // A long frame, e.g. after the tab was asleep, should not teleport the scene
+        let delta_time = delta_time.min(self.config.max_frame_time);
+        #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+        if let Some(recorder) = &mut self.recorder {
+            recorder.frame(delta_time, self.screen_size);
+        }
+        self.update_frame(delta_time);
+        self.profiler.record(Phase::Update, timer);
+    }
+
+    fn handle_event(&mut self, event: geng::Event) {
+        #[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+        {
+            // The recorded input replaces the real one
+            if self.playback.is_some() {
+                return;
+            }
+            if let Some(recorder) = &mut self.recorder {
+                recorder.event(&event);
+            }
+        }
+        self.handle_input(event);
+    }
+
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        if !self.render_frame {
+            return;
+        }
+        self.screen_size = framebuffer.size();
+        self.frames_rendered += 1;
+        if self.frames_rendered == 1 {
+            web::emit(web::HostEvent::FirstFrame);
+        }
+
+        let profiler = &mut self.profiler;
+        self.gpu_timer
+            .get_or_insert_with(GpuTimer::new)
+            .poll(|pass, time| profiler.record_gpu(pass, time));
+
+        let timer = self.profiler.start();
+        let render_size = self.render_size();
+        if render_size == self.screen_size {
+            self.render_texture = None;
+            self.draw_scene(framebuffer);
+        } else {
+            // Render at a lower resolution and upscale
+            let mut texture = match self.render_texture.take() {
+                Some(texture) if texture.size() == render_size => texture,
+                _ => ugli::Texture::new_uninitialized(self.geng().ugli(), render_size),
+            };
+            {
+                let geng = self.geng().clone();
+                let mut target = ugli::Framebuffer::new_color(
+                    geng.ugli(),
+                    ugli::ColorAttachment::Texture(&mut texture),
+                );
+                self.draw_scene(&mut target);
+            }
+            let screen = Aabb2::ZERO.extend_positive(self.screen_size.as_f32());
+            self.gpu_begin(GpuPass::Upscale);
+            self.geng().draw2d().draw2d(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                &draw2d::TexturedQuad::new(screen, &texture),
+            );
+            self.gpu_end();
+            self.render_texture = Some(texture);
+        }
+        self.profiler.record(Phase::Draw, timer);
+
+        #[cfg(feature = "debug")]
+        if self.is_debug() && self.show_profiler {
+            self.profiler.draw(self.geng(), framebuffer);
+        }
+    }
+}
+
+impl State {
+    /// Everything that happens on a simulated frame, live or played back.
+    fn update_frame(&mut self, delta_time: f32) {
+        self.update_fps(delta_time);
+        self.profiler.frame(delta_time);
+        self.update_adaptive_quality(delta_time);
+
+        if let Some(config) = self
+            .reloader
+            .update(delta_time)
+            .filter(|_| !self.is_replaying())
+        {
+            let theme = if config.themes.contains_key(&self.theme_name) {
+                self.theme_name.clone()
+            } else {
+                config.theme.clone()
+            };
+            self.config = config;
+            web::set_allowed_origins(&self.config.allowed_origins);
+            self.timeline_time = 0.0;
+            self.timeline_index = 0;
+            self.prefab_registry.prepare(self.config.prefabs.values());
+            self.set_theme(&theme);
+        }
+        self.prefab_registry.poll();
+
+        self.next_color_scheme_check -= delta_time;
+        if self.next_color_scheme_check < 0.0 {
+            self.next_color_scheme_check = 1.0;
+            if !self.is_replaying() {
+                self.local_hour = config::local_hour();
+            }
+            self.update_color_scheme();
+            self.reduced_motion = self.prefers_reduced_motion();
+            let low_power = self.detect_low_power();
+            if low_power != self.low_power {
+                log::info!("Low power mode: {}", low_power);
+                self.low_power = low_power;
+            }
+            web::request_battery_status();
+        }
+
+        self.update_tilt(delta_time);
+        self.update_scroll(delta_time);
+        self.update_gyroscope(delta_time);
+        self.update_camera();
+        self.update_page_section(delta_time);
+        self.update_idle(delta_time);
+        self.update_scene_override(delta_time);
+        self.update_timeline(delta_time);
+        self.update_audio(delta_time);
+        self.update_beat(delta_time);
+        self.update_cursor_ripple(delta_time);
+
+        let delta_time = delta_time
+            * self.config.time_scale
+            * self.idle_scale
+            * self.update_time_modifiers(delta_time);
+        match self.config.simulation_rate {
+            Some(rate) => {
+                let step = 1.0 / rate;
+                // Drop the time that would take too many steps to catch up,
+                // so that a long hitch does not teleport the objects
+                self.step_accumulator =
+                    (self.step_accumulator + delta_time).min(step * MAX_STEPS_PER_FRAME as f32);
+                while self.step_accumulator >= step {
+                    self.step_accumulator -= step;
+                    self.step(step);
+                }
+                self.step_alpha = self.step_accumulator / step;
+            }
+            None => {
+                self.step(delta_time);
+                self.step_alpha = 1.0;
+            }
+        }
+    }
+
+    fn handle_input(&mut self, event: geng::Event) {
+        if matches!(
+            event,
+            geng::Event::CursorMove { .. }
+                | geng::Event::MousePress { .. }
+                | geng::Event::Wheel { .. }
+                | geng::Event::TouchStart(_)
+                | geng::Event::TouchMove(_)
+                | geng::Event::KeyPress { .. }
+        ) {
+            self.idle_time = 0.0;
+        }
+        if matches!(
+            event,
+            geng::Event::MousePress { .. }
+                | geng::Event::TouchStart(_)
+                | geng::Event::KeyPress { .. }
+        ) {
+            if let Some(audio) = &self.audio {
+                audio.resume();
+            }
+        }
+
+        match event {
+            geng::Event::Focused(focused) => self.focused = focused,
+            geng::Event::CursorMove { position } => {
+                let position = position.map(|x| x as f32);
+                self.cursor = Some(position);
+                self.drag_move(None, position);
+            }
+            geng::Event::MousePress {
+                button: geng::MouseButton::Left,
+            } => {
+                if let Some(start) = self.cursor {
+                    self.drag = Some(Drag {
+                        touch: None,
+                        start,
+                        moved: false,
+                    });
+                }
+            }
+            geng::Event::MouseRelease {
+                button: geng::MouseButton::Left,
+            } => {
+                if let Some(drag) = self.drag.take_if(|drag| drag.touch.is_none()) {
+                    if !drag.moved {
+                        self.click(drag.start);
+                    }
+                }
+            }
+            geng::Event::TouchStart(touch) => {
+                let position = touch.position.map(|x| x as f32);
+                // Only the first finger can tap or tilt, more fingers only push the objects
+                if self.drag.is_none() {
+                    self.drag = Some(Drag {
+                        touch: Some(touch.id),
+                        start: position,
+                        moved: false,
+                    });
+                }
+                if self.config.cursor_field.is_active() {
+                    self.touches.insert(touch.id, position);
+                }
+            }
+            geng::Event::TouchMove(touch) => {
+                let position = touch.position.map(|x| x as f32);
+                if let Some(touch_position) = self.touches.get_mut(&touch.id) {
+                    *touch_position = position;
+                }
+                self.drag_move(Some(touch.id), position);
+            }
+            geng::Event::TouchEnd(touch) => {
+                self.touches.remove(&touch.id);
+                if let Some(drag) = self.drag.take_if(|drag| drag.touch == Some(touch.id)) {
+                    if !drag.moved {
+                        self.click(drag.start);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        if let geng::Event::KeyPress { key } = event {
+            if self.konami.push(key) {
+                self.trigger_easter_egg();
+            }
+            if self.reloader.is_watching() || self.is_debug() {
+                match key {
+                    geng::Key::R => self.reloader.request(),
+                    geng::Key::E => self.export_config(),
+                    _ => {}
+                }
+            }
+            #[cfg(feature = "debug")]
+            if self.is_debug() {
+                self.handle_debug_key(key);
+            }
+        }
+    }
+
+    /// Size of the rendered image in physical pixels,
+    /// reduced by the pixel ratio cap and the adaptive quality.
+    fn render_size(&self) -> vec2<usize> {
+        let ratio_scale = match (self.config.max_pixel_ratio, web::device_pixel_ratio()) {
+            (Some(max_ratio), Some(ratio)) if ratio > max_ratio => max_ratio / ratio,
+            _ => 1.0,
+        };
+        let adaptive_scale: f32 = self
+            .quality_levels()
+            .iter()
+            .map(|level| match *level {
+                QualityLevel::RenderScale(scale) => scale,
+                _ => 1.0,
+            })
+            .product();
+        let scale = ratio_scale * adaptive_scale;
+        if scale >= 1.0 {
+            return self.screen_size;
+        }
+        self.screen_size
+            .map(|x| ((x as f32 * scale).round() as usize).max(1))
+    }
+
+    /// Parameters for [Object::mesh].
+    fn lod(&self) -> (f32, Option<f32>) {
+        let pixels_per_unit = self.screen_size.y as f32 / self.view().height();
+        (pixels_per_unit, self.config.lod_size)
+    }
+
+    /// Compute the cross sections of all objects, reusing the pooled point buffers.
+    fn slice_objects(&mut self, cross_plane: &Plane) {
+        if self.config.slicing_thread {
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.slice_objects_on_worker(cross_plane);
+                return;
+            }
+        }
+        #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+        if self.objects.len() >= PARALLEL_SLICING_THRESHOLD {
+            self.slice_objects_parallel(cross_plane);
+            return;
+        }
+
+        let (pixels_per_unit, lod_size) = self.lod();
+        for obj in &self.objects {
+            let matrix = obj.interpolated_matrix(self.step_alpha);
+            let mut points = self.point_pool.pop().unwrap_or_default();
+            if slice_object(
+                cross_plane,
+                matrix,
+                obj.mesh(pixels_per_unit, lod_size),
+                &mut self.scratch_points,
+                &mut points,
+            ) {
+                self.sections.push(Section {
+                    object: obj.id,
+                    color: obj.color,
+                    points,
+                });
+            } else {
+                self.point_pool.push(points);
+            }
+        }
+    }
+
+    /// Same as [Self::slice_objects], but done on the background thread while drawing.
+    /// The sections lag one frame behind.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn slice_objects_on_worker(&mut self, cross_plane: &Plane) {
+        let (pixels_per_unit, lod_size) = self.lod();
+        let worker = self.slice_worker.get_or_insert_with(SliceWorker::spawn);
+        let mut job = worker.job();
+        job.plane = Some(cross_plane.clone());
+        job.objects.extend(self.objects.iter().map(|obj| {
+            let matrix = obj.interpolated_matrix(self.step_alpha);
+            let mesh = obj.mesh(pixels_per_unit, lod_size).clone();
+            (obj.id, obj.color, matrix, mesh)
+        }));
+        job.pool.append(&mut self.point_pool);
+        if let Some(sections) = worker.swap(job) {
+            self.sections.extend(sections);
+        }
+    }
+
+    /// Same as [Self::slice_objects], but spread over the threads.
+    #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+    fn slice_objects_parallel(&mut self, cross_plane: &Plane) {
+        use rayon::prelude::*;
+
+        // The objects hold `Rc`s, so collect what the threads need
+        let (pixels_per_unit, lod_size) = self.lod();
+        let jobs: Vec<_> = self
+            .objects
+            .iter()
+            .map(|obj| {
+                let points = self.point_pool.pop().unwrap_or_default();
+                let matrix = obj.interpolated_matrix(self.step_alpha);
+                let mesh = &obj.mesh(pixels_per_unit, lod_size)[..];
+                (obj.id, obj.color, matrix, mesh, points)
+            })
+            .collect();
+        let sections: Vec<Section> = jobs
+            .into_par_iter()
+            .map_init(
+                Vec::new,
+                |scratch, (object, color, matrix, geometry, mut points)| {
+                    slice_object(cross_plane, matrix, geometry, scratch, &mut points).then_some(
+                        Section {
+                            object,
+                            color,
+                            points,
+                        },
+                    )
+                },
+            )
+            .flatten()
+            .collect();
+        self.sections.extend(sections);
+    }
+
+    fn geng(&self) -> &Geng {
+        self.geng.as_ref().expect("a headless state can not draw")
+    }
+
+    fn gpu_begin(&mut self, pass: GpuPass) {
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.begin(pass);
+        }
+    }
+
+    fn gpu_end(&mut self) {
+        if let Some(timer) = &mut self.gpu_timer {
+            timer.end();
+        }
+    }
+
+    fn draw_scene(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        ugli::clear(framebuffer, Some(self.theme.background_color), None, None);
+
+        let cross_plane = self.cross_plane();
+
+        // Recycle the point buffers of the last frame
+        self.point_pool
+            .extend(self.sections.drain(..).map(|section| section.points));
+
+        let gpu_slicing = self.config.gpu_slicing && cfg!(feature = "gpu-slicing");
+        self.gpu_begin(GpuPass::Sections);
+        if gpu_slicing {
+            #[cfg(feature = "gpu-slicing")]
+            self.draw_gpu_sections(framebuffer, &cross_plane);
+        } else {
+            self.draw_sections(framebuffer, &cross_plane);
+        }
+        self.gpu_end();
+
+        self.gpu_begin(GpuPass::Effects);
+        self.effects.draw(self.geng(), framebuffer, &self.camera2d);
+        self.gpu_end();
+
+        #[cfg(feature = "debug")]
+        if self.is_debug() && self.show_overlay {
+            self.draw_overlay(framebuffer);
+        }
+    }
+
+    #[cfg(feature = "gpu-slicing")]
+    fn draw_gpu_sections(&mut self, framebuffer: &mut ugli::Framebuffer, cross_plane: &Plane) {
+        let geng = self.geng().clone();
+        let slicer = self.gpu_slicer.get_or_insert_with(|| GpuSlicer::new(&geng));
+        slicer.clear();
+        for obj in &self.objects {
+            slicer.add(
+                geng.ugli(),
+                obj.geometry.full(),
+                obj.interpolated_matrix(self.step_alpha),
+                obj.color,
+            );
+        }
+        slicer.draw(
+            framebuffer,
+            &self.camera2d,
+            cross_plane,
+            self.theme.stroke.width,
+        );
+    }
+
+    fn draw_sections(&mut self, framebuffer: &mut ugli::Framebuffer, cross_plane: &Plane) {
+        // Calculate a cross section
+        let timer = self.profiler.start();
+        self.slice_objects(cross_plane);
+        self.profiler.record(Phase::Slicing, timer);
+
+        let hovered = self
+            .cursor_world()
+            .and_then(|cursor| self.section_at(cursor))
+            .map(|section| section.object);
+        let beat = &self.config.beat;
+        let pulse = beat.pulse.scaled(beat.pulse(self.beat_time));
+        let stroke = pulse.apply_stroke(&self.theme.stroke);
+        let highlight_stroke = self.theme.highlight.apply_stroke(&stroke);
+        let glow = self.config.audio.glow * self.audio_levels.treble;
+        let flash_time = self.config.hit_stop.flash_time;
+        let flashes: Vec<(u64, f32)> = self
+            .objects
+            .iter()
+            .filter(|obj| obj.flash > 0.0)
+            .map(|obj| (obj.id, obj.flash / flash_time))
+            .collect();
+
+        // Skip the sections entirely outside of the view, including their outline
+        let view = self
+            .view()
+            .extend_uniform(stroke.width.max(highlight_stroke.width));
+
+        // Draw the cross section in 2d
+        let timer = self.profiler.start();
+        self.section_batch.clear();
+        self.batch_links();
+        self.batch_section_lines();
+        for section in &self.sections {
+            if !section
+                .bounding_box()
+                .is_some_and(|bounds| bounds.intersects(&view))
+            {
+                continue;
+            }
+            let color = pulse.apply_color(section.color);
+            let flash = flashes.iter().find(|(id, _)| *id == section.object);
+            let flash_stroke;
+            let (color, stroke) = if Some(section.object) == hovered {
+                (self.theme.highlight.apply_color(color), &highlight_stroke)
+            } else if let Some(&(_, t)) = flash {
+                let flash = self.theme.highlight.scaled(t);
+                flash_stroke = flash.apply_stroke(&stroke);
+                (flash.apply_color(color), &flash_stroke)
+            } else {
+                (color, &stroke)
+            };
+            let color = Rgba::new(
+                color.r + (1.0 - color.r) * glow,
+                color.g + (1.0 - color.g) * glow,
+                color.b + (1.0 - color.b) * glow,
+                color.a,
+            );
+            batch_flat_section(&mut self.section_batch, &section.points, color, stroke);
+        }
+        self.profiler.record(Phase::Triangulation, timer);
+        let geng = self.geng().clone();
+        self.section_batch.draw(&geng, framebuffer, &self.camera2d);
+    }
+
+    /// Connect the centers of the linked sections, behind the sections.
+    fn batch_links(&mut self) {
+        let config = &self.config.constellation;
+        if !config.enabled || self.links.is_empty() {
+            return;
+        }
+        self.link_ends.clear();
+        self.link_ends.extend(
+            self.sections
+                .iter()
+                .map(|section| (section.object, (section.center(), section.color))),
+        );
+        for (a, b) in &self.links {
+            let (Some(&(a, color_a)), Some(&(b, color_b))) =
+                (self.link_ends.get(a), self.link_ends.get(b))
+            else {
+                continue;
+            };
+            let color = mix_colors(color_a, color_b, config.opacity);
+            self.section_batch.add_line(a, b, config.width, color);
+        }
+    }
+
+    /// Connect the centers of the sections close to each other, behind the sections.
+    fn batch_section_lines(&mut self) {
+        let config = &self.config.section_lines;
+        if !config.enabled || config.max_degree == 0 {
+            return;
+        }
+        self.section_centers.clear();
+        self.section_centers
+            .extend(self.sections.iter().map(Section::center));
+        let edges =
+            self.section_graph
+                .build(&self.section_centers, config.distance, config.max_degree);
+        for &(a, b, length) in edges {
+            let fade = 1.0 - length / config.distance;
+            let color = mix_colors(
+                self.sections[a].color,
+                self.sections[b].color,
+                config.opacity * fade,
+            );
+            let (a, b) = (self.section_centers[a], self.section_centers[b]);
+            self.section_batch.add_line(a, b, config.width, color);
+        }
+    }
+}
+
+/// Average of the colors of two connected sections.
+fn mix_colors(a: Rgba<f32>, b: Rgba<f32>, opacity: f32) -> Rgba<f32> {
+    Rgba::new(
+        (a.r + b.r) / 2.0,
+        (a.g + b.g) / 2.0,
+        (a.b + b.b) / 2.0,
+        (a.a + b.a) / 2.0 * opacity,
+    )
+}
+
+/// Number of objects from which the slicing is spread over the threads.
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+const PARALLEL_SLICING_THRESHOLD: usize = 64;
+
+/// Range of the spin speed of the objects in degrees per second.
+const ANGULAR_SPEED: std::ops::RangeInclusive<f32> = 20.0..=70.0;
+/// Range of the orbit speed of the clusters in degrees per second.
+const ORBIT_SPEED: std::ops::RangeInclusive<f32> = 30.0..=60.0;
+
+/// Number of simplified versions generated for every prefab.
+const LOD_LEVELS: usize = 2;
+
+/// Limits the simulation steps done in a single frame when catching up.
+const MAX_STEPS_PER_FRAME: usize = 8;
+
+/// Morphing objects are blended again once their progress changes by this much.
+const MORPH_STEP: f32 = 0.01;
+
+/// Objects break into shards once their center is this many scales past the plane,
+/// while their trailing part still crosses it.
+const FRACTURE_DEPTH: f32 = 1.0;
+
+/// Objects closer than their scales times this are considered overlapping.
+const OVERLAP_DISTANCE: f32 = 1.74;
+
+/// Most objects bound into a single cluster.
+const MAX_CLUSTER_SIZE: usize = 3;
+
+/// Candidates tried around each object in the Poisson-disk sampling of the spawns.
+const POISSON_ATTEMPTS: usize = 30;
+
+/// Objects tried in the Poisson-disk sampling of a single spawn.
+const POISSON_SEEDS: usize = 10;
+
+/// Fraction of the overlap between two objects resolved per second.
+const COLLISION_STIFFNESS: f32 = 2.0;
+
+/// Distance of the members from the center of a cluster,
+/// far enough apart for the neighbours on the orbit not to overlap.
+fn cluster_radius(largest: f32, count: usize) -> f32 {
+    largest * OVERLAP_DISTANCE / (std::f32::consts::PI / count as f32).sin()
+}
+
+fn random_scale(rng: &mut impl Rng) -> f32 {
+    rng.gen_range(0.3..=1.0)
+}
+
+fn random_direction(rng: &mut impl Rng) -> vec3<f32> {
+    vec3(
+        rng.gen_range(-1.0..=1.0),
+        rng.gen_range(-1.0..=1.0),
+        rng.gen_range(-1.0..=1.0),
+    )
+}
+
+fn random_spawn(z: f32, view: Aabb2<f32>, rng: &mut impl Rng) -> vec3<f32> {
+    vec3(
+        rng.gen_range(view.min.x..=view.max.x),
+        rng.gen_range(view.min.y..=view.max.y),
+        z,
+    )
+}
+
+fn batch_flat_section(
+    batch: &mut SectionBatch,
+    points: &[vec2<f32>],
+    color: Rgba<f32>,
+    stroke: &Stroke,
+) {
+    if points.len() < 3 {
+        return;
+    }
+
+    // Optimize small sizes to look better
+    let area = Aabb2::points_bounding_box(points.iter().copied())
+        .expect("there are at least 3 points at this moment");
+    let radius = area.size() / 2.0;
+    if stroke
+        .fill_below
+        .is_some_and(|threshold| radius.x.max(radius.y) < threshold)
+    {
+        batch.add_fill(points, color);
+        return;
+    }
+    let width = stroke
+        .width
+        .min(radius.x)
+        .min(radius.y)
+        .max(stroke.min_width);
+    batch.add_outline(points, width, color);
+}
+
+#[cfg(all(feature = "debug", not(target_arch = "wasm32")))]
+#[test]
+fn test_replay() {
+    use geng::State as _;
+
+    let path = std::env::temp_dir().join(format!("background-replay-{}.ron", std::process::id()));
+    let reloader = || ConfigReloader::new("missing.ron", config::ConfigOverrides::default(), false);
+    let mut config = Config::default();
+    // Natively the limiter would sleep between the frames
+    config.target_fps = None;
+    config.simulation_rate = Some(60.0);
+
+    let frames = 300;
+    let mut recording = State::record(None, config, reloader(), path.clone());
+    recording.screen_size = vec2(1920, 1080);
+    for i in 0..frames {
+        let position = vec2(960.0 + 300.0 * (i as f64 * 0.05).sin(), 540.0);
+        recording.handle_event(geng::Event::CursorMove { position });
+        if i % 60 == 30 {
+            recording.handle_event(geng::Event::MousePress {
+                button: geng::MouseButton::Left,
+            });
+            recording.handle_event(geng::Event::MouseRelease {
+                button: geng::MouseButton::Left,
+            });
+        }
+        // Uneven frames, as in a real run
+        recording.update(if i % 7 == 0 { 1.0 / 30.0 } else { 1.0 / 75.0 });
+    }
+    // Saves the file
+    recording.recorder = None;
+    let positions = |state: &State| -> Vec<(u64, vec3<f32>)> {
+        state
+            .objects
+            .iter()
+            .map(|obj| (obj.id, obj.position))
+            .collect()
+    };
+    let recorded = positions(&recording);
+    assert!(!recorded.is_empty());
+
+    let replay = Replay::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay.frames.len(), frames);
+    let mut playback = State::replay(None, replay, reloader());
+    for _ in 0..frames {
+        playback.update(0.0);
+    }
+    assert_eq!(positions(&playback), recorded);
 }
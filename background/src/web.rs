@@ -0,0 +1,913 @@
+//! Integration with the browser environment.
+//! On native builds every query returns `None`.
+
+use crate::{
+    config::{ColorScheme, ConfigOverrides},
+    profiler::RenderStats,
+};
+
+use geng::prelude::Rgba;
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+#[cfg(target_arch = "wasm32")]
+use geng::prelude::*;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+/// A request from the page, handled by the state on the next update.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub enum Command {
+    Pause,
+    Resume,
+    SetTheme(String),
+    SetTimeScale(f32),
+    /// Spawn this many objects at once.
+    SpawnBurst(usize),
+    /// Vertical scroll offset of the page in pixels.
+    SetScroll(f32),
+    /// Multiplier for the number of objects.
+    SetDensity(f32),
+    /// Read the colors from the page styles again.
+    SyncCssColors,
+    /// Force reduced motion on or off, or follow the browser if `None`.
+    SetReducedMotion(Option<bool>),
+    SetLowQuality(bool),
+    /// Id of the page section currently in view, if any.
+    SetPageSection(Option<String>),
+    /// Pause requested by the markup of the page, not remembered between visits.
+    SetHostPaused(bool),
+    /// The page became hidden or visible again.
+    SetHidden(bool),
+}
+
+/// A command sent by the embedding page with `postMessage`,
+/// e.g. `{ "type": "theme", "name": "default" }`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+enum Message {
+    Pause,
+    Resume,
+    Theme { name: String },
+    Scroll { offset: f32 },
+    Density { value: f32 },
+    Section { id: Option<String> },
+}
+
+impl From<Message> for Command {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::Pause => Self::Pause,
+            Message::Resume => Self::Resume,
+            Message::Theme { name } => Self::SetTheme(name),
+            Message::Scroll { offset } => Self::SetScroll(offset),
+            Message::Density { value } => Self::SetDensity(value),
+            Message::Section { id } => Self::SetPageSection(id),
+        }
+    }
+}
+
+/// State reported to the embedding page,
+/// as `{ "type": "status", "paused": false, "theme": "default", "objects": 42 }`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename = "status")]
+pub struct Status {
+    pub paused: bool,
+    pub theme: String,
+    pub objects: usize,
+}
+
+/// An event reported to the callbacks registered by the page.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum HostEvent {
+    ObjectSpawned {
+        id: u64,
+        prefab: String,
+    },
+    /// The object passed through the plane and was removed.
+    ObjectExited {
+        id: u64,
+    },
+    /// The first frame has been rendered.
+    FirstFrame,
+    /// The frame rate dropped below the configured threshold.
+    LowFps {
+        fps: f32,
+    },
+    /// An object carrying a link was clicked.
+    LinkClicked {
+        url: String,
+    },
+    /// Averaged frame timings, reported once a second.
+    RenderStats(RenderStats),
+}
+
+impl HostEvent {
+    #[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+    fn name(&self) -> &'static str {
+        match self {
+            Self::ObjectSpawned { .. } => "objectSpawned",
+            Self::ObjectExited { .. } => "objectExited",
+            Self::FirstFrame => "firstFrame",
+            Self::LowFps { .. } => "lowFps",
+            Self::LinkClicked { .. } => "linkClicked",
+            Self::RenderStats(_) => "renderStats",
+        }
+    }
+}
+
+/// Id of the canvas the engine renders to, looked up by the engine on startup.
+#[cfg(target_arch = "wasm32")]
+pub const CANVAS_ID: &str = "geng-canvas";
+
+thread_local! {
+    static COMMANDS: RefCell<Vec<Command>> = RefCell::new(Vec::new());
+    #[cfg(target_arch = "wasm32")]
+    static CALLBACKS: RefCell<HashMap<&'static str, Vec<js_sys::Function>>> =
+        RefCell::new(HashMap::new());
+    static BATTERY: RefCell<Option<BatteryStatus>> = RefCell::new(None);
+    /// Latest device orientation as `(gamma, beta)` in degrees.
+    static DEVICE_ORIENTATION: RefCell<Option<(f32, f32)>> = RefCell::new(None);
+    /// Other origins accepted by [listen_messages], from the config.
+    static ALLOWED_ORIGINS: RefCell<Vec<String>> = RefCell::new(Vec::new());
+}
+
+fn push_command(command: Command) {
+    COMMANDS.with(|commands| commands.borrow_mut().push(command));
+}
+
+/// Take all the commands received since the last call.
+pub fn take_commands() -> Vec<Command> {
+    COMMANDS.with(|commands| std::mem::take(&mut *commands.borrow_mut()))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn add_callback(event: &'static str, callback: js_sys::Function) {
+    CALLBACKS.with(|callbacks| {
+        callbacks
+            .borrow_mut()
+            .entry(event)
+            .or_default()
+            .push(callback)
+    });
+}
+
+/// Call the callbacks registered for the event with its data.
+pub fn emit(event: HostEvent) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        CALLBACKS.with(|callbacks| {
+            let callbacks = callbacks.borrow();
+            let Some(callbacks) = callbacks.get(event.name()) else {
+                return;
+            };
+            let data = serde_json::to_string(&event)
+                .ok()
+                .and_then(|json| js_sys::JSON::parse(&json).ok())
+                .unwrap_or(JsValue::NULL);
+            for callback in callbacks {
+                if let Err(err) = callback.call1(&JsValue::NULL, &data) {
+                    log::warn!("Callback for {} failed: {:?}", event.name(), err);
+                }
+            }
+        });
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = event;
+    }
+}
+
+/// Let the page handle the link with its `onLinkClicked` callbacks,
+/// or navigate to it directly if there are none.
+pub fn open_link(url: &str) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let handled = CALLBACKS.with(|callbacks| {
+            callbacks
+                .borrow()
+                .get("linkClicked")
+                .is_some_and(|callbacks| !callbacks.is_empty())
+        });
+        if handled {
+            emit(HostEvent::LinkClicked {
+                url: url.to_owned(),
+            });
+            return;
+        }
+        if let Some(window) = web_sys::window() {
+            if let Err(err) = window.location().set_href(url) {
+                log::warn!("Failed to open {}: {:?}", url, err);
+            }
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        log::info!("Link clicked: {}", url);
+    }
+}
+
+/// Report the page scroll offset, e.g. from a `scroll` event listener.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+pub fn set_scroll(offset: f32) {
+    push_command(Command::SetScroll(offset));
+}
+
+/// Set the origins of the other pages allowed to send the commands.
+pub fn set_allowed_origins(origins: &[String]) {
+    ALLOWED_ORIGINS.with(|allowed| *allowed.borrow_mut() = origins.to_vec());
+}
+
+/// Whether the messages from the origin are accepted:
+/// the own origin of the page, or one of the allowed ones.
+#[cfg(target_arch = "wasm32")]
+fn is_origin_allowed(origin: &str) -> bool {
+    let own = web_sys::window().and_then(|window| window.location().origin().ok());
+    own.as_deref() == Some(origin)
+        || ALLOWED_ORIGINS.with(|allowed| {
+            allowed
+                .borrow()
+                .iter()
+                .any(|allowed| allowed == "*" || allowed == origin)
+        })
+}
+
+/// The origin of the embedding page, from the referrer,
+/// or the own origin of the page if it is not known.
+#[cfg(target_arch = "wasm32")]
+fn parent_origin(window: &web_sys::Window) -> Option<String> {
+    let referrer = window.document()?.referrer();
+    if !referrer.is_empty() {
+        if let Ok(url) = web_sys::Url::new(&referrer) {
+            return Some(url.origin());
+        }
+    }
+    window.location().origin().ok()
+}
+
+/// Accept commands over `postMessage` when embedded in an iframe,
+/// only from the allowed origins.
+pub fn listen_messages() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let callback =
+            Closure::<dyn FnMut(web_sys::MessageEvent)>::new(|event: web_sys::MessageEvent| {
+                let origin = event.origin();
+                if !is_origin_allowed(&origin) {
+                    log::debug!("Ignoring a message from {}", origin);
+                    return;
+                }
+                let Ok(json) = js_sys::JSON::stringify(&event.data()) else {
+                    return;
+                };
+                let json = String::from(json);
+                match serde_json::from_str::<Message>(&json) {
+                    Ok(message) => push_command(message.into()),
+                    Err(err) => log::debug!("Ignoring message {}: {}", json, err),
+                }
+            });
+        if let Err(err) =
+            window.add_event_listener_with_callback("message", callback.as_ref().unchecked_ref())
+        {
+            log::warn!("Failed to listen to messages: {:?}", err);
+        }
+        // The listener lives as long as the page
+        callback.forget();
+    }
+}
+
+/// Send the status to the embedding page, if there is one.
+pub fn post_status(status: &Status) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let Some(parent) = window.parent().ok().flatten() else {
+            return;
+        };
+        if js_sys::Object::is(&parent, &window) {
+            // Not embedded
+            return;
+        }
+        let Ok(json) = serde_json::to_string(status) else {
+            return;
+        };
+        let Ok(message) = js_sys::JSON::parse(&json) else {
+            return;
+        };
+        // Only the embedding page gets to read the status
+        let Some(origin) = parent_origin(&window) else {
+            return;
+        };
+        if let Err(err) = parent.post_message(&message, &origin) {
+            log::warn!("Failed to post the status: {:?}", err);
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = status;
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen(inline_js = r#"
+const ATTRIBUTES = ["theme", "density", "paused"];
+
+// Put the engine canvas inside the element, creating it if the page has none,
+// so that the engine picks it up by id when it starts
+function attachCanvas(element, canvasId) {
+    let canvas = document.getElementById(canvasId);
+    if (!canvas) {
+        canvas = document.createElement("canvas");
+        canvas.id = canvasId;
+    }
+    if (canvas.parentElement !== element) {
+        canvas.style.width = "100%";
+        canvas.style.height = "100%";
+        element.appendChild(canvas);
+    }
+}
+
+export function attach_background_canvas(canvasId) {
+    const element = document.querySelector("nertsal-background");
+    if (element) {
+        attachCanvas(element, canvasId);
+    }
+}
+
+export function define_background_element(canvasId, onAttribute) {
+    if (customElements.get("nertsal-background")) {
+        return;
+    }
+    customElements.define("nertsal-background", class extends HTMLElement {
+        static get observedAttributes() {
+            return ATTRIBUTES;
+        }
+
+        connectedCallback() {
+            attachCanvas(this, canvasId);
+            for (const name of ATTRIBUTES) {
+                if (this.hasAttribute(name)) {
+                    onAttribute(name, this.getAttribute(name));
+                }
+            }
+        }
+
+        attributeChangedCallback(name, _oldValue, value) {
+            onAttribute(name, value);
+        }
+    });
+}
+"#)]
+extern "C" {
+    fn attach_background_canvas(canvas_id: &str);
+    fn define_background_element(
+        canvas_id: &str,
+        on_attribute: &Closure<dyn FnMut(String, Option<String>)>,
+    );
+}
+
+/// Define the `<nertsal-background theme="default" density="0.5" paused>` custom element,
+/// which creates the canvas for the engine and maps its attributes to the commands.
+/// Must be called before the engine starts, so the element in the markup gets upgraded
+/// and creates the canvas before the engine looks for it.
+pub fn define_custom_element() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let callback = Closure::<dyn FnMut(String, Option<String>)>::new(
+            |name: String, value: Option<String>| match name.as_str() {
+                "theme" => {
+                    if let Some(theme) = value {
+                        push_command(Command::SetTheme(theme));
+                    }
+                }
+                "density" => {
+                    let density = value.as_deref().map(str::parse).transpose();
+                    match density {
+                        Ok(density) => push_command(Command::SetDensity(density.unwrap_or(1.0))),
+                        Err(err) => log::warn!("Invalid `density` attribute: {}", err),
+                    }
+                }
+                "paused" => {
+                    let paused = value.is_some_and(|value| value != "false");
+                    push_command(Command::SetHostPaused(paused));
+                }
+                _ => {}
+            },
+        );
+        define_background_element(CANVAS_ID, &callback);
+        // The element lives as long as the page
+        callback.forget();
+    }
+}
+
+/// Move the engine canvas into the custom element once the engine has started,
+/// in case the page provided its own canvas or added the element later.
+pub fn attach_canvas() {
+    #[cfg(target_arch = "wasm32")]
+    attach_background_canvas(CANVAS_ID);
+}
+
+/// Handle for the page to control the animation:
+/// ```js
+/// const background = new BackgroundHandle();
+/// background.setTheme("default");
+/// background.spawnBurst(10);
+/// background.onLowFps(({ fps }) => console.log("low fps", fps));
+/// ```
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub struct BackgroundHandle;
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+impl BackgroundHandle {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn pause(&self) {
+        push_command(Command::Pause);
+    }
+
+    pub fn resume(&self) {
+        push_command(Command::Resume);
+    }
+
+    #[wasm_bindgen(js_name = setTheme)]
+    pub fn set_theme(&self, name: String) {
+        push_command(Command::SetTheme(name));
+    }
+
+    #[wasm_bindgen(js_name = setTimeScale)]
+    pub fn set_time_scale(&self, time_scale: f32) {
+        push_command(Command::SetTimeScale(time_scale));
+    }
+
+    #[wasm_bindgen(js_name = spawnBurst)]
+    pub fn spawn_burst(&self, count: usize) {
+        push_command(Command::SpawnBurst(count));
+    }
+
+    #[wasm_bindgen(js_name = setScroll)]
+    pub fn set_scroll(&self, offset: f32) {
+        set_scroll(offset);
+    }
+
+    /// Pass `undefined` to follow the browser preference again.
+    #[wasm_bindgen(js_name = setReducedMotion)]
+    pub fn set_reduced_motion(&self, reduce: Option<bool>) {
+        push_command(Command::SetReducedMotion(reduce));
+    }
+
+    /// Report the page section currently in view, e.g. from an `IntersectionObserver`.
+    #[wasm_bindgen(js_name = setSection)]
+    pub fn set_section(&self, id: Option<String>) {
+        push_command(Command::SetPageSection(id));
+    }
+
+    #[wasm_bindgen(js_name = setLowQuality)]
+    pub fn set_low_quality(&self, low: bool) {
+        push_command(Command::SetLowQuality(low));
+    }
+
+    /// Called with `{ id, prefab }`.
+    #[wasm_bindgen(js_name = onObjectSpawned)]
+    pub fn on_object_spawned(&self, callback: js_sys::Function) {
+        add_callback("objectSpawned", callback);
+    }
+
+    /// Called with `{ id }`.
+    #[wasm_bindgen(js_name = onObjectExited)]
+    pub fn on_object_exited(&self, callback: js_sys::Function) {
+        add_callback("objectExited", callback);
+    }
+
+    #[wasm_bindgen(js_name = onFirstFrame)]
+    pub fn on_first_frame(&self, callback: js_sys::Function) {
+        add_callback("firstFrame", callback);
+    }
+
+    /// Called with `{ fps }`.
+    #[wasm_bindgen(js_name = onLowFps)]
+    pub fn on_low_fps(&self, callback: js_sys::Function) {
+        add_callback("lowFps", callback);
+    }
+
+    /// Called with `{ url }` instead of navigating to the link.
+    #[wasm_bindgen(js_name = onLinkClicked)]
+    pub fn on_link_clicked(&self, callback: js_sys::Function) {
+        add_callback("linkClicked", callback);
+    }
+
+    /// Called once a second with `{ frame, cpu, gpu }`, the average timings in milliseconds.
+    /// `cpu` and `gpu` map the passes to their times,
+    /// `gpu` is empty if the browser does not support the timer queries.
+    #[wasm_bindgen(js_name = onRenderStats)]
+    pub fn on_render_stats(&self, callback: js_sys::Function) {
+        add_callback("renderStats", callback);
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn media_matches(query: &str) -> Option<bool> {
+    let window = web_sys::window()?;
+    let list = window.match_media(query).ok()??;
+    Some(list.matches())
+}
+
+/// The color scheme preferred by the browser.
+pub fn preferred_color_scheme() -> Option<ColorScheme> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        media_matches("(prefers-color-scheme: light)").map(|light| {
+            if light {
+                ColorScheme::Light
+            } else {
+                ColorScheme::Dark
+            }
+        })
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Whether the user asked the browser to minimize animations.
+pub fn prefers_reduced_motion() -> Option<bool> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        media_matches("(prefers-reduced-motion: reduce)")
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Ratio of the physical pixels to the CSS pixels of the display.
+pub fn device_pixel_ratio() -> Option<f32> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window().map(|window| window.device_pixel_ratio() as f32)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Whether the page is in a background tab or otherwise not visible.
+pub fn is_hidden() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()
+            .and_then(|window| window.document())
+            .map_or(false, |document| document.hidden())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        false
+    }
+}
+
+/// Follow the visibility of the page, which can not be polled while hidden,
+/// as the browser stops the frames.
+pub fn listen_visibility() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        let callback =
+            Closure::<dyn FnMut()>::new(|| push_command(Command::SetHidden(is_hidden())));
+        if let Err(err) = document
+            .add_event_listener_with_callback("visibilitychange", callback.as_ref().unchecked_ref())
+        {
+            log::warn!("Failed to listen to visibility changes: {:?}", err);
+        }
+        // The listener lives as long as the page
+        callback.forget();
+    }
+}
+
+/// Start tracking the device orientation, if the device reports it.
+pub fn listen_device_orientation() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let callback = Closure::<dyn FnMut(web_sys::DeviceOrientationEvent)>::new(
+            |event: web_sys::DeviceOrientationEvent| {
+                // Desktop browsers fire the event with null angles
+                let (Some(gamma), Some(beta)) = (event.gamma(), event.beta()) else {
+                    return;
+                };
+                DEVICE_ORIENTATION.with(|orientation| {
+                    *orientation.borrow_mut() = Some((gamma as f32, beta as f32));
+                });
+            },
+        );
+        if let Err(err) = window.add_event_listener_with_callback(
+            "deviceorientation",
+            callback.as_ref().unchecked_ref(),
+        ) {
+            log::warn!("Failed to listen to the device orientation: {:?}", err);
+        }
+        // The listener lives as long as the page
+        callback.forget();
+    }
+}
+
+/// The device tilt left-right and front-back in degrees,
+/// or `None` if the device does not report its orientation.
+pub fn device_orientation() -> Option<(f32, f32)> {
+    DEVICE_ORIENTATION.with(|orientation| *orientation.borrow())
+}
+
+/// Colors defined by the page in CSS custom properties.
+#[derive(Debug, Clone)]
+pub struct CssColors {
+    /// From `--bg-color`.
+    pub background: Option<Rgba<f32>>,
+    /// From `--accent-1`, `--accent-2`, and so on.
+    pub accents: Vec<Rgba<f32>>,
+}
+
+/// Read the colors from the CSS custom properties of the page root,
+/// or `None` if the page does not define any.
+pub fn css_colors() -> Option<CssColors> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let window = web_sys::window()?;
+        let root = window.document()?.document_element()?;
+        let style = window.get_computed_style(&root).ok()??;
+        let property = |name: &str| {
+            let value = style.get_property_value(name).ok()?;
+            let value = value.trim();
+            if value.is_empty() {
+                return None;
+            }
+            crate::config::parse_color(value)
+                .map_err(|err| log::warn!("Invalid css color `{}: {}`: {}", name, value, err))
+                .ok()
+        };
+        let background = property("--bg-color");
+        let accents: Vec<_> = (1..)
+            .map_while(|i| property(&format!("--accent-{i}")))
+            .collect();
+        (background.is_some() || !accents.is_empty()).then_some(CssColors {
+            background,
+            accents,
+        })
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Read the CSS colors again whenever the page dispatches a `themechange` event on the document.
+pub fn listen_theme_change() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(document) = web_sys::window().and_then(|window| window.document()) else {
+            return;
+        };
+        let callback = Closure::<dyn FnMut()>::new(|| push_command(Command::SyncCssColors));
+        if let Err(err) = document
+            .add_event_listener_with_callback("themechange", callback.as_ref().unchecked_ref())
+        {
+            log::warn!("Failed to listen to theme changes: {:?}", err);
+        }
+        // The listener lives as long as the page
+        callback.forget();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatteryStatus {
+    /// Charge level from 0 to 1.
+    pub level: f32,
+    pub charging: bool,
+}
+
+/// Ask the browser for the battery status, which is then available from [`battery_status`].
+/// Does nothing if the browser does not support the Battery Status API.
+pub fn request_battery_status() {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(window) = web_sys::window() else {
+            return;
+        };
+        let navigator = window.navigator();
+        let Ok(get_battery) = js_sys::Reflect::get(&navigator, &"getBattery".into()) else {
+            return;
+        };
+        let Some(get_battery) = get_battery.dyn_ref::<js_sys::Function>() else {
+            return;
+        };
+        let Ok(promise) = get_battery.call0(&navigator) else {
+            return;
+        };
+        let Ok(promise) = promise.dyn_into::<js_sys::Promise>() else {
+            return;
+        };
+        let callback = Closure::once(|battery: JsValue| {
+            let get = |key: &str| js_sys::Reflect::get(&battery, &key.into()).ok();
+            let level = get("level").and_then(|value| value.as_f64());
+            let charging = get("charging").and_then(|value| value.as_bool());
+            if let (Some(level), Some(charging)) = (level, charging) {
+                BATTERY.with(|battery| {
+                    *battery.borrow_mut() = Some(BatteryStatus {
+                        level: level as f32,
+                        charging,
+                    });
+                });
+            }
+        });
+        let _ = promise.then(&callback);
+        // Dropped after the promise resolves
+        callback.forget();
+    }
+}
+
+/// The last known battery status, if the device has a battery and reports it.
+pub fn battery_status() -> Option<BatteryStatus> {
+    BATTERY.with(|battery| *battery.borrow())
+}
+
+/// Approximate amount of the device memory in gigabytes, if the browser reports it.
+pub fn device_memory() -> Option<f32> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let navigator = web_sys::window()?.navigator();
+        let memory = js_sys::Reflect::get(&navigator, &"deviceMemory".into()).ok()?;
+        memory.as_f64().map(|memory| memory as f32)
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        None
+    }
+}
+
+/// Fill in config overrides from the page URL query,
+/// e.g. `?theme=default&seed=42&speed=1.0&spawn_rate=5&palette=565a75,c6b7be`.
+pub fn apply_query_overrides(overrides: &mut ConfigOverrides) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let Some(params) = query_params() else {
+            return;
+        };
+
+        fn parse<T: std::str::FromStr>(params: &web_sys::UrlSearchParams, key: &str) -> Option<T>
+        where
+            T::Err: std::fmt::Display,
+        {
+            let value = params.get(key)?;
+            value
+                .parse()
+                .map_err(|err| log::warn!("Invalid url parameter `{}={}`: {}", key, value, err))
+                .ok()
+        }
+
+        if let Some(preset) = params.get("preset") {
+            overrides.preset = Some(preset);
+        }
+        if let Some(theme) = params.get("theme") {
+            overrides.theme = Some(theme);
+        }
+        if let Some(seed) = parse(&params, "seed") {
+            overrides.seed = Some(seed);
+        }
+        if let Some(time_scale) = parse(&params, "time_scale") {
+            overrides.time_scale = Some(time_scale);
+        }
+        if let Some(spawn_rate) = parse(&params, "spawn_rate") {
+            overrides.spawn_rate = Some(spawn_rate);
+        }
+        if let Some(speed) = parse(&params, "speed") {
+            overrides.speed = Some(speed);
+        }
+        if let Some(fps) = parse(&params, "fps_limit") {
+            overrides.fps_limit = Some(fps);
+        }
+        if let Some(palette) = params.get("palette") {
+            match palette.split(',').map(crate::config::parse_color).collect() {
+                Ok(palette) => overrides.palette = Some(palette),
+                Err(err) => log::warn!("Invalid url parameter `palette={}`: {}", palette, err),
+            }
+        }
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let _ = overrides;
+    }
+}
+
+/// Whether the page url asks to periodically refetch the config with `?hot_reload`,
+/// since the web build can not receive the command line arguments.
+pub fn query_hot_reload() -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        query_params()
+            .and_then(|params| params.get("hot_reload"))
+            .is_some_and(|value| !matches!(value.as_str(), "false" | "0"))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        false
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn query_params() -> Option<web_sys::UrlSearchParams> {
+    let search = web_sys::window()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search).ok()
+}
+
+/// Read a value saved with [`save_storage`].
+pub fn load_storage(key: &str) -> Option<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let storage = web_sys::window()?.local_storage().ok()??;
+        storage.get_item(key).ok()?
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::read_to_string(storage_path(key)).ok()
+    }
+}
+
+/// Save a value in the local storage on the web, or to a file natively.
+pub fn save_storage(key: &str, value: &str) -> anyhow::Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let storage = web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| anyhow::anyhow!("local storage is not available"))?;
+        storage
+            .set_item(key, value)
+            .map_err(|err| anyhow::anyhow!("{:?}", err))
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::write(storage_path(key), value)?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn storage_path(key: &str) -> std::path::PathBuf {
+    geng::prelude::run_dir().join(format!("{key}.json"))
+}
+
+/// Offer the text as a file download on the web, or save it to a file natively.
+#[cfg(feature = "export")]
+pub fn save_text(file_name: &str, text: &str) -> anyhow::Result<()> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        use wasm_bindgen::JsCast;
+
+        let js_error = |err: wasm_bindgen::JsValue| anyhow::anyhow!("{:?}", err);
+
+        let parts = js_sys::Array::of1(&wasm_bindgen::JsValue::from_str(text));
+        let options = web_sys::BlobPropertyBag::new();
+        options.set_type("text/plain");
+        let blob =
+            web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_error)?;
+        let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+
+        let document = web_sys::window()
+            .and_then(|window| window.document())
+            .ok_or_else(|| anyhow::anyhow!("no document"))?;
+        let anchor: web_sys::HtmlAnchorElement = document
+            .create_element("a")
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(|_| anyhow::anyhow!("failed to create a link"))?;
+        anchor.set_href(&url);
+        anchor.set_download(file_name);
+        anchor.click();
+
+        web_sys::Url::revoke_object_url(&url).map_err(js_error)?;
+        Ok(())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::fs::write(file_name, text)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    geometry::{slice_object, Mesh, Plane},
+    state::Section,
+};
+
+use geng::prelude::*;
+
+use std::sync::mpsc;
+
+/// Objects to slice, taken from a single frame.
+#[derive(Default)]
+pub struct SliceJob {
+    pub plane: Option<Plane>,
+    pub objects: Vec<(u64, Rgba<f32>, mat4<f32>, Mesh)>,
+    /// Buffers to write the sections into.
+    pub pool: Vec<Vec<vec2<f32>>>,
+}
+
+/// Slices the objects on a background thread while the previous frame is drawn.
+/// The sections are double buffered: the result of a job is taken when the next one is sent,
+/// so the drawn sections lag one frame behind the simulation.
+pub struct SliceWorker {
+    jobs: mpsc::Sender<SliceJob>,
+    results: mpsc::Receiver<(SliceJob, Vec<Section>)>,
+    /// A job is being processed.
+    busy: bool,
+    /// The last returned job, kept to reuse its allocations.
+    spare: SliceJob,
+}
+
+impl SliceWorker {
+    pub fn spawn() -> Self {
+        let (jobs, job_receiver) = mpsc::channel::<SliceJob>();
+        let (result_sender, results) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("slicing".to_owned())
+            .spawn(move || {
+                let mut scratch = Vec::new();
+                for mut job in job_receiver {
+                    let mut sections = Vec::with_capacity(job.objects.len());
+                    if let Some(plane) = &job.plane {
+                        for (object, color, matrix, geometry) in job.objects.drain(..) {
+                            let mut points = job.pool.pop().unwrap_or_default();
+                            if slice_object(plane, matrix, &geometry, &mut scratch, &mut points) {
+                                sections.push(Section {
+                                    object,
+                                    color,
+                                    points,
+                                });
+                            } else {
+                                job.pool.push(points);
+                            }
+                        }
+                    }
+                    if result_sender.send((job, sections)).is_err() {
+                        // The state is gone
+                        break;
+                    }
+                }
+            })
+            .expect("failed to spawn the slicing thread");
+        Self {
+            jobs,
+            results,
+            busy: false,
+            spare: SliceJob::default(),
+        }
+    }
+
+    /// An empty job to fill for the next frame.
+    pub fn job(&mut self) -> SliceJob {
+        let mut job = std::mem::take(&mut self.spare);
+        job.objects.clear();
+        job
+    }
+
+    /// Wait for the sections of the previous job, if any, and start slicing the next one.
+    pub fn swap(&mut self, job: SliceJob) -> Option<Vec<Section>> {
+        let result = if self.busy {
+            match self.results.recv() {
+                Ok((spare, sections)) => {
+                    self.spare = spare;
+                    Some(sections)
+                }
+                Err(_) => None,
+            }
+        } else {
+            None
+        };
+        self.busy = self.jobs.send(job).is_ok();
+        if !self.busy {
+            log::error!("The slicing thread has stopped");
+        }
+        result
+    }
+}